@@ -0,0 +1,189 @@
+// Provider-agnostic LLM client used by the AI analysis commands.
+// Supports OpenAI-compatible APIs, Anthropic and a local Ollama server. Settings (provider, model,
+// API key, base URL) live in app_settings and are supplied by the user; nothing is sent anywhere
+// unless a provider has been configured.
+
+use crate::commands::get_app_setting;
+use rusqlite::Connection;
+use serde_json::json;
+use std::collections::HashMap;
+
+pub const PROVIDERS: [&str; 3] = ["openai", "anthropic", "ollama"];
+
+pub const SETTING_PROVIDER: &str = "ai_provider";
+pub const SETTING_MODEL: &str = "ai_model";
+pub const SETTING_API_KEY: &str = "ai_api_key";
+pub const SETTING_BASE_URL: &str = "ai_base_url";
+
+#[derive(Debug, Clone)]
+pub struct AiConfig {
+    pub provider: String,
+    pub model: String,
+    pub api_key: Option<String>,
+    pub base_url: Option<String>,
+}
+
+pub fn default_model(provider: &str) -> &'static str {
+    match provider {
+        "anthropic" => "claude-3-5-haiku-latest",
+        "ollama" => "llama3.1",
+        _ => "gpt-4o-mini",
+    }
+}
+
+impl AiConfig {
+    /// Configured provider, or an error telling the user to set one up.
+    pub fn load(conn: &Connection) -> Result<AiConfig, String> {
+        let provider = get_app_setting(conn, SETTING_PROVIDER)
+            .filter(|p| PROVIDERS.contains(&p.as_str()))
+            .ok_or("No AI provider configured. Add one in Settings > AI.")?;
+        let model = get_app_setting(conn, SETTING_MODEL)
+            .filter(|m| !m.trim().is_empty())
+            .unwrap_or_else(|| default_model(&provider).to_string());
+        let api_key = get_app_setting(conn, SETTING_API_KEY).filter(|k| !k.trim().is_empty());
+        if provider != "ollama" && api_key.is_none() {
+            return Err(format!("An API key is required for {}", provider));
+        }
+        Ok(AiConfig {
+            provider,
+            model,
+            api_key,
+            base_url: get_app_setting(conn, SETTING_BASE_URL).filter(|u| !u.trim().is_empty()),
+        })
+    }
+}
+
+/// Send one system + user prompt and return the model's text reply. `json_output` asks the provider
+/// for a JSON object where it supports that.
+pub async fn complete(config: &AiConfig, system: &str, prompt: &str, json_output: bool) -> Result<String, String> {
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(180))
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+    let base = config.base_url.as_deref().map(|u| u.trim_end_matches('/').to_string());
+    let api_key = config.api_key.clone().unwrap_or_default();
+
+    let (request, text_path): (reqwest::RequestBuilder, &[&str]) = match config.provider.as_str() {
+        "anthropic" => {
+            let url = format!("{}/v1/messages", base.unwrap_or_else(|| "https://api.anthropic.com".to_string()));
+            let body = json!({
+                "model": config.model,
+                "max_tokens": 4096,
+                "system": system,
+                "messages": [{ "role": "user", "content": prompt }],
+            });
+            (
+                client.post(url).header("x-api-key", api_key).header("anthropic-version", "2023-06-01").json(&body),
+                &["content", "0", "text"],
+            )
+        }
+        "ollama" => {
+            let url = format!("{}/api/chat", base.unwrap_or_else(|| "http://localhost:11434".to_string()));
+            let mut body = json!({
+                "model": config.model,
+                "stream": false,
+                "messages": [
+                    { "role": "system", "content": system },
+                    { "role": "user", "content": prompt },
+                ],
+            });
+            if json_output {
+                body["format"] = json!("json");
+            }
+            (client.post(url).json(&body), &["message", "content"])
+        }
+        _ => {
+            let url = format!("{}/v1/chat/completions", base.unwrap_or_else(|| "https://api.openai.com".to_string()));
+            let mut body = json!({
+                "model": config.model,
+                "messages": [
+                    { "role": "system", "content": system },
+                    { "role": "user", "content": prompt },
+                ],
+            });
+            if json_output {
+                body["response_format"] = json!({ "type": "json_object" });
+            }
+            (client.post(url).bearer_auth(api_key).json(&body), &["choices", "0", "message", "content"])
+        }
+    };
+
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("Could not reach {}: {}", config.provider, e))?;
+    let status = response.status();
+    let data: serde_json::Value = response
+        .json()
+        .await
+        .map_err(|e| format!("Invalid response from {}: {}", config.provider, e))?;
+    if !status.is_success() {
+        let message = data
+            .pointer("/error/message")
+            .or_else(|| data.get("error"))
+            .map(|m| m.as_str().map(|s| s.to_string()).unwrap_or_else(|| m.to_string()))
+            .unwrap_or_else(|| status.to_string());
+        return Err(format!("{} error: {}", config.provider, message));
+    }
+
+    let pointer = format!("/{}", text_path.join("/"));
+    data.pointer(&pointer)
+        .and_then(|t| t.as_str())
+        .map(|t| t.to_string())
+        .ok_or_else(|| format!("Unexpected response format from {}", config.provider))
+}
+
+/// The JSON object in a model reply, tolerating ```json fences and leading/trailing prose.
+pub fn extract_json(text: &str) -> Option<serde_json::Value> {
+    let start = text.find('{')?;
+    let end = text.rfind('}')?;
+    serde_json::from_str(text.get(start..=end)?).ok()
+}
+
+/// Replaces symbols with stable aliases (SYM1, SYM2, ...) before text leaves the machine and maps
+/// them back in the reply.
+#[derive(Default)]
+pub struct SymbolMasker {
+    aliases: HashMap<String, String>,
+}
+
+impl SymbolMasker {
+    pub fn mask(&mut self, symbol: &str) -> String {
+        let next = self.aliases.len() + 1;
+        self.aliases
+            .entry(symbol.to_uppercase())
+            .or_insert_with(|| format!("SYM{}", next))
+            .clone()
+    }
+
+    /// Mask every known symbol inside free text (journal notes mention tickers too).
+    pub fn mask_text(&self, text: &str) -> String {
+        let mut symbols: Vec<(&String, &String)> = self.aliases.iter().collect();
+        // Longest first so "SPY251218C00679000" is replaced before "SPY"
+        symbols.sort_by_key(|(symbol, _)| std::cmp::Reverse(symbol.len()));
+        let mut out = text.to_string();
+        for (symbol, alias) in symbols {
+            out = replace_word(&out, symbol, alias);
+        }
+        out
+    }
+
+    pub fn unmask(&self, text: &str) -> String {
+        let mut aliases: Vec<(&String, &String)> = self.aliases.iter().collect();
+        // SYM12 before SYM1
+        aliases.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then(b.1.cmp(a.1)));
+        let mut out = text.to_string();
+        for (symbol, alias) in aliases {
+            out = replace_word(&out, alias, symbol);
+        }
+        out
+    }
+}
+
+// Replace whole-word, case-insensitive occurrences of `from`
+fn replace_word(text: &str, from: &str, to: &str) -> String {
+    match regex::Regex::new(&format!(r"(?i)\b{}\b", regex::escape(from))) {
+        Ok(re) => re.replace_all(text, regex::NoExpand(to)).to_string(),
+        Err(_) => text.to_string(),
+    }
+}
@@ -0,0 +1,101 @@
+// Passphrase encryption for exported backups.
+// The whole backup JSON is sealed with AES-256-GCM under a key derived from the passphrase with
+// PBKDF2-HMAC-SHA256, and wrapped in a small JSON envelope carrying the salt, nonce and KDF settings.
+// The passphrase is never stored; a lost passphrase means the backup cannot be restored.
+
+use aes_gcm::aead::{rand_core::RngCore, Aead, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Nonce};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde::{Deserialize, Serialize};
+
+const ENVELOPE_FORMAT: &str = "tradebutler-encrypted-backup";
+const KDF: &str = "pbkdf2-sha256";
+const PBKDF2_ITERATIONS: u32 = 600_000;
+const MIN_PASSPHRASE_LEN: usize = 8;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct EncryptedBackup {
+    format: String,
+    version: u32,
+    kdf: String,
+    iterations: u32,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], iterations: u32) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<sha2::Sha256>(passphrase.as_bytes(), salt, iterations, &mut key);
+    key
+}
+
+/// Whether `data` is an encrypted backup envelope rather than a plain export.
+pub fn is_encrypted_backup(data: &str) -> bool {
+    serde_json::from_str::<serde_json::Value>(data)
+        .map(|v| v["format"].as_str() == Some(ENVELOPE_FORMAT))
+        .unwrap_or(false)
+}
+
+/// Encrypt a backup with `passphrase`; returns the envelope as pretty JSON.
+pub fn encrypt_backup(plaintext: &str, passphrase: &str) -> Result<String, String> {
+    if passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+        return Err(format!("Passphrase must be at least {} characters", MIN_PASSPHRASE_LEN));
+    }
+    let mut salt = [0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+    let mut nonce = [0u8; 12];
+    OsRng.fill_bytes(&mut nonce);
+
+    let key = derive_key(passphrase, &salt, PBKDF2_ITERATIONS);
+    let cipher = Aes256Gcm::new_from_slice(&key).map_err(|e| e.to_string())?;
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), plaintext.as_bytes())
+        .map_err(|_| "Failed to encrypt backup".to_string())?;
+
+    let envelope = EncryptedBackup {
+        format: ENVELOPE_FORMAT.to_string(),
+        version: 1,
+        kdf: KDF.to_string(),
+        iterations: PBKDF2_ITERATIONS,
+        salt: BASE64.encode(salt),
+        nonce: BASE64.encode(nonce),
+        ciphertext: BASE64.encode(ciphertext),
+    };
+    serde_json::to_string_pretty(&envelope).map_err(|e| e.to_string())
+}
+
+/// Decrypt an envelope made by `encrypt_backup`. A wrong passphrase and a tampered file both fail
+/// authentication and give the same error.
+pub fn decrypt_backup(data: &str, passphrase: &str) -> Result<String, String> {
+    let envelope: EncryptedBackup =
+        serde_json::from_str(data).map_err(|e| format!("Not an encrypted TradeButler backup: {}", e))?;
+    if envelope.format != ENVELOPE_FORMAT {
+        return Err("Not an encrypted TradeButler backup".to_string());
+    }
+    if envelope.version != 1 || envelope.kdf != KDF {
+        return Err(format!(
+            "Unsupported backup encryption (version {}, {}); update TradeButler to restore it",
+            envelope.version, envelope.kdf
+        ));
+    }
+    // Version 1 always uses PBKDF2_ITERATIONS; any other count is a damaged or crafted file, and a huge
+    // one would tie up a thread deriving the key
+    if envelope.iterations != PBKDF2_ITERATIONS {
+        return Err("Backup encryption settings are corrupt".to_string());
+    }
+    let decode = |field: &str, value: &str| BASE64.decode(value).map_err(|_| format!("Backup {} is corrupt", field));
+    let salt = decode("salt", &envelope.salt)?;
+    let nonce = decode("nonce", &envelope.nonce)?;
+    let ciphertext = decode("data", &envelope.ciphertext)?;
+    if nonce.len() != 12 {
+        return Err("Backup nonce is corrupt".to_string());
+    }
+
+    let key = derive_key(passphrase, &salt, envelope.iterations);
+    let cipher = Aes256Gcm::new_from_slice(&key).map_err(|e| e.to_string())?;
+    let plaintext = cipher
+        .decrypt(Nonce::from_slice(&nonce), ciphertext.as_ref())
+        .map_err(|_| "Wrong passphrase, or the backup file is damaged".to_string())?;
+    String::from_utf8(plaintext).map_err(|_| "Backup contents are not valid text".to_string())
+}
@@ -0,0 +1,394 @@
+// Live broker sync: pulls fills straight from a broker's REST API (Alpaca orders, Tradier account
+// history) so the journal stays current without CSV exports. Credentials and sync cursors live in
+// app_settings; fills are normalized to `Trade` here and inserted by the sync commands through the
+// normal import pipeline (batch + dedup).
+
+use crate::commands::{self, get_app_setting};
+use crate::database::{Database, Trade};
+use crate::importers;
+use chrono::{DateTime, NaiveDate, Utc};
+use rusqlite::Connection;
+use serde::Deserialize;
+use std::time::{Duration, Instant};
+
+pub const ALPACA_KEY_ID: &str = "alpaca_key_id";
+pub const ALPACA_SECRET_KEY: &str = "alpaca_secret_key";
+pub const ALPACA_PAPER: &str = "alpaca_paper";
+pub const ALPACA_SYNC_INTERVAL: &str = "alpaca_sync_interval_minutes";
+pub const ALPACA_CURSOR: &str = "alpaca_sync_cursor";
+pub const ALPACA_LAST_SYNC: &str = "alpaca_last_sync_at";
+
+pub const TRADIER_ACCESS_TOKEN: &str = "tradier_access_token";
+pub const TRADIER_ACCOUNT_ID: &str = "tradier_account_id";
+pub const TRADIER_SANDBOX: &str = "tradier_sandbox";
+pub const TRADIER_SYNC_INTERVAL: &str = "tradier_sync_interval_minutes";
+pub const TRADIER_CURSOR: &str = "tradier_sync_cursor";
+pub const TRADIER_LAST_SYNC: &str = "tradier_last_sync_at";
+
+const SYNC_TICK_SECONDS: u64 = 60;
+const ALPACA_PAGE_SIZE: usize = 500;
+const TRADIER_PAGE_SIZE: usize = 1000;
+
+// Orders that may still fill; everything else (filled, canceled, expired, replaced, rejected) is final
+const ALPACA_OPEN_STATUSES: [&str; 12] = [
+    "new",
+    "partially_filled",
+    "done_for_day",
+    "accepted",
+    "pending_new",
+    "accepted_for_bidding",
+    "pending_cancel",
+    "pending_replace",
+    "stopped",
+    "suspended",
+    "calculated",
+    "held",
+];
+
+#[derive(Debug, Clone)]
+pub struct AlpacaConfig {
+    pub key_id: String,
+    pub secret_key: String,
+    pub paper: bool,
+}
+
+impl AlpacaConfig {
+    /// Stored API keys, or an error telling the user to add them.
+    pub fn load(conn: &Connection) -> Result<AlpacaConfig, String> {
+        let key_id = get_app_setting(conn, ALPACA_KEY_ID).filter(|k| !k.trim().is_empty());
+        let secret_key = get_app_setting(conn, ALPACA_SECRET_KEY).filter(|k| !k.trim().is_empty());
+        match (key_id, secret_key) {
+            (Some(key_id), Some(secret_key)) => Ok(AlpacaConfig {
+                key_id,
+                secret_key,
+                paper: get_app_setting(conn, ALPACA_PAPER).as_deref() != Some("false"),
+            }),
+            _ => Err("No Alpaca API keys configured. Add them in Settings > Broker Sync.".to_string()),
+        }
+    }
+
+    fn base_url(&self) -> &'static str {
+        if self.paper {
+            "https://paper-api.alpaca.markets"
+        } else {
+            "https://api.alpaca.markets"
+        }
+    }
+}
+
+// One entry of GET /v2/orders; quantities and prices come back as strings
+#[derive(Debug, Deserialize)]
+struct AlpacaOrder {
+    id: String,
+    symbol: Option<String>,
+    side: String,
+    #[serde(rename = "type")]
+    order_type: Option<String>,
+    status: String,
+    filled_qty: Option<String>,
+    filled_avg_price: Option<String>,
+    filled_at: Option<String>,
+    submitted_at: String,
+    /// "buy_to_open", "sell_to_close", ... (options orders)
+    #[serde(default)]
+    position_intent: Option<String>,
+}
+
+pub struct AlpacaFetch {
+    pub trades: Vec<Trade>,
+    pub orders_seen: usize,
+    /// `after` for the next sync: just before the oldest order that can still fill, else the newest order seen
+    pub next_cursor: Option<String>,
+}
+
+/// Every order submitted after `after` (RFC 3339), oldest first, with the filled ones as trades.
+/// Orders that were partly filled and then canceled are kept with their filled quantity.
+pub async fn fetch_alpaca_orders(config: &AlpacaConfig, after: &str) -> Result<AlpacaFetch, String> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(60))
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+    let url = format!("{}/v2/orders", config.base_url());
+
+    let mut orders: Vec<AlpacaOrder> = Vec::new();
+    let mut page_after = after.to_string();
+    loop {
+        let limit = ALPACA_PAGE_SIZE.to_string();
+        let response = client
+            .get(&url)
+            .header("APCA-API-KEY-ID", &config.key_id)
+            .header("APCA-API-SECRET-KEY", &config.secret_key)
+            .query(&[
+                ("status", "all"),
+                ("direction", "asc"),
+                ("nested", "false"),
+                ("limit", limit.as_str()),
+                ("after", page_after.as_str()),
+            ])
+            .send()
+            .await
+            .map_err(|e| format!("Could not reach Alpaca: {}", e))?;
+        let status = response.status();
+        if status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN {
+            return Err("Alpaca rejected the API keys. Check them (and the paper/live setting) in Settings.".to_string());
+        }
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(format!("Alpaca returned {}: {}", status, body.trim()));
+        }
+        let page: Vec<AlpacaOrder> = response
+            .json()
+            .await
+            .map_err(|e| format!("Invalid response from Alpaca: {}", e))?;
+        let full_page = page.len() >= ALPACA_PAGE_SIZE;
+        let last_submitted = page.last().map(|o| o.submitted_at.clone());
+        orders.extend(page);
+        match last_submitted {
+            Some(last) if full_page && last != page_after => page_after = last,
+            _ => break,
+        }
+    }
+
+    let oldest_open = orders
+        .iter()
+        .filter(|o| ALPACA_OPEN_STATUSES.contains(&o.status.as_str()))
+        .filter_map(|o| DateTime::parse_from_rfc3339(&o.submitted_at).ok())
+        .min()
+        // `after` is exclusive
+        .map(|t| (t.with_timezone(&Utc) - chrono::Duration::seconds(1)).to_rfc3339());
+    let next_cursor = oldest_open.or_else(|| orders.last().map(|o| o.submitted_at.clone()));
+    let trades = orders
+        .iter()
+        .filter(|o| !ALPACA_OPEN_STATUSES.contains(&o.status.as_str()))
+        .filter_map(alpaca_order_to_trade)
+        .collect();
+    Ok(AlpacaFetch {
+        trades,
+        orders_seen: orders.len(),
+        next_cursor,
+    })
+}
+
+/// A finished order with a fill as a trade. Equities keep their ticker, options come back as OCC
+/// symbols and crypto as "BTC/USD"; the order ID becomes the import identity.
+fn alpaca_order_to_trade(order: &AlpacaOrder) -> Option<Trade> {
+    let symbol = order.symbol.as_deref().map(str::trim).filter(|s| !s.is_empty())?;
+    let quantity = order.filled_qty.as_deref()?.trim().parse::<f64>().ok().filter(|q| *q > 0.0)?;
+    let price = order.filled_avg_price.as_deref()?.trim().parse::<f64>().ok()?;
+    let filled_at = DateTime::parse_from_rfc3339(order.filled_at.as_deref()?).ok()?;
+    Some(Trade {
+        id: None,
+        symbol: symbol.to_uppercase(),
+        side: importers::normalize_side(&order.side)?.to_string(),
+        quantity,
+        price,
+        timestamp: importers::utc_to_exchange_time(filled_at.with_timezone(&Utc)),
+        order_type: order.order_type.as_deref().unwrap_or("market").to_uppercase(),
+        status: "Filled".to_string(),
+        fees: None,
+        notes: None,
+        strategy_id: None,
+        external_id: Some(format!("alpaca:{}", order.id)),
+        event_type: None,
+        fx_rate: None,
+        position_effect: order.position_intent.as_deref().and_then(importers::position_effect_of_side),
+        is_paper: false,
+    })
+}
+
+#[derive(Debug, Clone)]
+pub struct TradierConfig {
+    pub access_token: String,
+    /// None until the first sync looks it up from the token's profile
+    pub account_id: Option<String>,
+    pub sandbox: bool,
+}
+
+impl TradierConfig {
+    /// Stored OAuth token, or an error telling the user to add one.
+    pub fn load(conn: &Connection) -> Result<TradierConfig, String> {
+        let access_token = get_app_setting(conn, TRADIER_ACCESS_TOKEN)
+            .filter(|t| !t.trim().is_empty())
+            .ok_or("No Tradier access token configured. Add it in Settings > Broker Sync.")?;
+        Ok(TradierConfig {
+            access_token,
+            account_id: get_app_setting(conn, TRADIER_ACCOUNT_ID).filter(|a| !a.trim().is_empty()),
+            sandbox: get_app_setting(conn, TRADIER_SANDBOX).as_deref() == Some("true"),
+        })
+    }
+
+    fn base_url(&self) -> &'static str {
+        if self.sandbox {
+            "https://sandbox.tradier.com"
+        } else {
+            "https://api.tradier.com"
+        }
+    }
+}
+
+// Tradier returns a single item as an object and several as an array (and "null" for none)
+fn one_or_many(value: &serde_json::Value) -> Vec<&serde_json::Value> {
+    match value {
+        serde_json::Value::Array(items) => items.iter().collect(),
+        serde_json::Value::Object(_) => vec![value],
+        _ => Vec::new(),
+    }
+}
+
+async fn tradier_get(
+    client: &reqwest::Client,
+    config: &TradierConfig,
+    path: &str,
+    query: &[(&str, String)],
+) -> Result<serde_json::Value, String> {
+    let response = client
+        .get(format!("{}{}", config.base_url(), path))
+        .bearer_auth(&config.access_token)
+        .header("Accept", "application/json")
+        .query(query)
+        .send()
+        .await
+        .map_err(|e| format!("Could not reach Tradier: {}", e))?;
+    let status = response.status();
+    if status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN {
+        return Err("Tradier rejected the access token. Check it (and the sandbox setting) in Settings.".to_string());
+    }
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!("Tradier returned {}: {}", status, body.trim()));
+    }
+    response.json().await.map_err(|e| format!("Invalid response from Tradier: {}", e))
+}
+
+/// Account number for the token: the configured one, else the first account on the user's profile.
+pub async fn tradier_account_id(config: &TradierConfig) -> Result<String, String> {
+    if let Some(account_id) = &config.account_id {
+        return Ok(account_id.clone());
+    }
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(60))
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+    let profile = tradier_get(&client, config, "/v1/user/profile", &[]).await?;
+    one_or_many(&profile["profile"]["account"])
+        .into_iter()
+        .find_map(|account| account["account_number"].as_str().map(str::to_string))
+        .ok_or_else(|| "No accounts found for this Tradier token".to_string())
+}
+
+/// Trade events from the account history between two dates (inclusive), oldest first.
+/// Tradier's history only carries the trade date, so fills are stored at midnight of that day and
+/// identified by content; always fetching whole days keeps re-syncs idempotent.
+pub async fn fetch_tradier_trades(config: &TradierConfig, account_id: &str, start: NaiveDate, end: NaiveDate) -> Result<Vec<Trade>, String> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(60))
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+    let path = format!("/v1/accounts/{}/history", account_id);
+
+    let mut trades = Vec::new();
+    for page in 1.. {
+        let data = tradier_get(
+            &client,
+            config,
+            &path,
+            &[
+                ("type", "trade".to_string()),
+                ("start", start.format("%Y-%m-%d").to_string()),
+                ("end", end.format("%Y-%m-%d").to_string()),
+                ("limit", TRADIER_PAGE_SIZE.to_string()),
+                ("page", page.to_string()),
+            ],
+        )
+        .await?;
+        let events = one_or_many(&data["history"]["event"]);
+        let count = events.len();
+        trades.extend(events.into_iter().filter_map(tradier_event_to_trade));
+        if count < TRADIER_PAGE_SIZE {
+            break;
+        }
+    }
+    // Newest first from the API; stable sort keeps the same-day order
+    trades.reverse();
+    trades.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+    Ok(trades)
+}
+
+/// One "trade" history event. The quantity is negative for sells; option symbols are already OCC.
+fn tradier_event_to_trade(event: &serde_json::Value) -> Option<Trade> {
+    let trade = &event["trade"];
+    let symbol = trade["symbol"].as_str().map(str::trim).filter(|s| !s.is_empty())?;
+    let signed_quantity = trade["quantity"].as_f64().filter(|q| *q != 0.0)?;
+    let price = trade["price"].as_f64()?;
+    let date = event["date"]
+        .as_str()
+        .and_then(|d| d.get(..10))
+        .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())?;
+    let fees = trade["commission"].as_f64().map(f64::abs).filter(|c| *c > 0.0);
+    Some(Trade {
+        id: None,
+        symbol: symbol.to_uppercase(),
+        side: if signed_quantity > 0.0 { "BUY" } else { "SELL" }.to_string(),
+        quantity: signed_quantity.abs(),
+        price,
+        timestamp: importers::parse_flexible_timestamp(&date.format("%Y-%m-%d").to_string(), None)?,
+        order_type: "MARKET".to_string(),
+        status: "Filled".to_string(),
+        fees,
+        notes: trade["description"].as_str().map(str::trim).filter(|d| !d.is_empty()).map(str::to_string),
+        strategy_id: None,
+        external_id: None,
+        event_type: None,
+        fx_rate: None,
+        position_effect: None,
+        is_paper: false,
+    })
+}
+
+/// Background thread that runs each configured broker sync on its interval (minutes in app_settings;
+/// unset or 0 means manual only). Failed runs wait a full interval before retrying.
+pub fn start_sync_scheduler(db: Database) {
+    std::thread::spawn(move || {
+        let runtime = match tokio::runtime::Builder::new_current_thread().enable_all().build() {
+            Ok(runtime) => runtime,
+            Err(e) => {
+                eprintln!("[Broker sync] Could not start scheduler: {}", e);
+                return;
+            }
+        };
+        let mut alpaca_attempt: Option<Instant> = None;
+        let mut tradier_attempt: Option<Instant> = None;
+        loop {
+            if sync_due(&db, ALPACA_SYNC_INTERVAL, &mut alpaca_attempt) {
+                if let Err(e) = runtime.block_on(commands::sync_alpaca(&db, None)) {
+                    eprintln!("[Broker sync] Alpaca: {}", e);
+                }
+            }
+            if sync_due(&db, TRADIER_SYNC_INTERVAL, &mut tradier_attempt) {
+                if let Err(e) = runtime.block_on(commands::sync_tradier_history(&db, None)) {
+                    eprintln!("[Broker sync] Tradier: {}", e);
+                }
+            }
+            std::thread::sleep(Duration::from_secs(SYNC_TICK_SECONDS));
+        }
+    });
+}
+
+/// True (and records the attempt) when the broker has an interval set and it has passed since the last attempt.
+fn sync_due(db: &Database, interval_setting: &str, last_attempt: &mut Option<Instant>) -> bool {
+    let interval = match sync_interval(db, interval_setting) {
+        Some(interval) => interval,
+        None => return false,
+    };
+    if last_attempt.is_some_and(|t| t.elapsed() < interval) {
+        return false;
+    }
+    *last_attempt = Some(Instant::now());
+    true
+}
+
+fn sync_interval(db: &Database, setting: &str) -> Option<Duration> {
+    let conn = db.connection().ok()?;
+    let minutes = get_app_setting(&conn, setting)?.trim().parse::<u64>().ok().filter(|m| *m > 0)?;
+    Some(Duration::from_secs(minutes * 60))
+}
@@ -0,0 +1,200 @@
+// Market event calendar: earnings dates and scheduled economic releases (FOMC, CPI, jobs, ...) kept in
+// the market_events table so analytics can flag trades taken around them. Events come from CSV files,
+// Finnhub, or the built-in economic schedule; one row per (date, type, symbol), market-wide events
+// with an empty symbol.
+
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarketEvent {
+    pub id: Option<i64>,
+    /// YYYY-MM-DD
+    pub date: String,
+    /// "earnings", "fomc", "cpi", "jobs", "gdp", "ppi", "retail_sales", ...
+    pub event_type: String,
+    /// Ticker for earnings; None for market-wide events
+    pub symbol: Option<String>,
+    pub title: String,
+    /// "high", "medium" or "low"
+    pub importance: Option<String>,
+    /// Earnings timing: "bmo" (before the open), "amc" (after the close), "dmh" (during market hours)
+    pub timing: Option<String>,
+    pub details: Option<String>,
+    /// "csv", "finnhub", "builtin" or "manual"
+    pub source: String,
+}
+
+/// Lowercase, underscore-separated event type ("Retail Sales" -> "retail_sales").
+pub fn normalize_event_type(value: &str) -> String {
+    value
+        .trim()
+        .to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+/// Event type for a named economic release, or None for releases the analytics don't track.
+pub fn classify_economic_event(name: &str) -> Option<&'static str> {
+    let name = name.to_lowercase();
+    if name.contains("fomc") || name.contains("fed interest rate decision") {
+        Some("fomc")
+    } else if name.contains("cpi") || name.contains("consumer price") {
+        Some("cpi")
+    } else if name.contains("nonfarm payrolls") || name.contains("non farm payrolls") || name.contains("non-farm payrolls") {
+        Some("jobs")
+    } else if name.contains("gdp") {
+        Some("gdp")
+    } else if name.contains("ppi") || name.contains("producer price") {
+        Some("ppi")
+    } else if name.contains("retail sales") {
+        Some("retail_sales")
+    } else {
+        None
+    }
+}
+
+/// YYYY-MM-DD from "2025-01-29", "2025-01-29T14:00:00", "01/29/2025" or "1/29/25".
+pub fn normalize_event_date(value: &str) -> Option<String> {
+    let value = value.trim();
+    let date_part = value.split(['T', ' ']).next().unwrap_or(value);
+    ["%Y-%m-%d", "%m/%d/%Y", "%m/%d/%y", "%Y/%m/%d"]
+        .iter()
+        .find_map(|format| chrono::NaiveDate::parse_from_str(date_part, format).ok())
+        .map(|date| date.format("%Y-%m-%d").to_string())
+}
+
+/// Events from a CSV with a header row. Columns are matched loosely: date, type/event_type/event
+/// (required), symbol/ticker, title/name/description, importance/impact, timing/hour/time and
+/// details/notes. A row whose type column names an economic release ("FOMC Rate Decision") is
+/// classified like the API feed. Returns the events and a message per skipped row.
+pub fn parse_events_csv(csv_data: &str) -> Result<(Vec<MarketEvent>, Vec<String>), String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(true)
+        .flexible(true)
+        .from_reader(csv_data.as_bytes());
+    let headers: Vec<String> = reader
+        .headers()
+        .map_err(|e| e.to_string())?
+        .iter()
+        .map(|h| h.trim().to_lowercase())
+        .collect();
+    let find = |names: &[&str]| headers.iter().position(|h| names.contains(&h.as_str()));
+    let date_col = find(&["date", "event date", "event_date", "report date", "report_date"]).ok_or("No date column found")?;
+    let type_col = find(&["type", "event_type", "event type", "event", "category"]).ok_or("No event type column found")?;
+    let symbol_col = find(&["symbol", "ticker"]);
+    let title_col = find(&["title", "name", "description"]);
+    let importance_col = find(&["importance", "impact"]);
+    let timing_col = find(&["timing", "hour", "time", "session"]);
+    let details_col = find(&["details", "notes", "note"]);
+
+    let mut events = Vec::new();
+    let mut skipped = Vec::new();
+    for (index, record) in reader.records().enumerate() {
+        let line = index + 2;
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                skipped.push(format!("Line {}: {}", line, e));
+                continue;
+            }
+        };
+        let field = |col: Option<usize>| {
+            col.and_then(|c| record.get(c))
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(str::to_string)
+        };
+        let Some(date) = field(Some(date_col)).as_deref().and_then(normalize_event_date) else {
+            skipped.push(format!("Line {}: missing or unreadable date", line));
+            continue;
+        };
+        let Some(raw_type) = field(Some(type_col)) else {
+            skipped.push(format!("Line {}: missing event type", line));
+            continue;
+        };
+        let event_type = classify_economic_event(&raw_type)
+            .map(str::to_string)
+            .unwrap_or_else(|| normalize_event_type(&raw_type));
+        let symbol = field(symbol_col).map(|s| s.to_uppercase());
+        if event_type == "earnings" && symbol.is_none() {
+            skipped.push(format!("Line {}: earnings event without a symbol", line));
+            continue;
+        }
+        events.push(MarketEvent {
+            id: None,
+            date,
+            title: field(title_col).unwrap_or_else(|| raw_type.clone()),
+            event_type,
+            symbol,
+            importance: field(importance_col).map(|i| i.to_lowercase()),
+            timing: field(timing_col).map(|t| t.to_lowercase()),
+            details: field(details_col),
+            source: "csv".to_string(),
+        });
+    }
+    Ok((events, skipped))
+}
+
+/// Insert or update by (date, type, symbol). Returns true when the event was new.
+pub fn upsert_event(conn: &Connection, event: &MarketEvent) -> Result<bool, String> {
+    let symbol = event.symbol.as_deref().map(str::trim).unwrap_or("").to_uppercase();
+    let exists: bool = conn
+        .query_row(
+            "SELECT COUNT(*) FROM market_events WHERE date = ?1 AND event_type = ?2 AND symbol = ?3",
+            params![event.date, event.event_type, symbol],
+            |row| row.get::<_, i64>(0),
+        )
+        .map_err(|e| e.to_string())?
+        > 0;
+    conn.execute(
+        "INSERT INTO market_events (date, event_type, symbol, title, importance, timing, details, source)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+         ON CONFLICT(date, event_type, symbol) DO UPDATE SET title = excluded.title,
+             importance = COALESCE(excluded.importance, importance), timing = COALESCE(excluded.timing, timing),
+             details = COALESCE(excluded.details, details), source = excluded.source",
+        params![event.date, event.event_type, symbol, event.title, event.importance, event.timing, event.details, event.source],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(!exists)
+}
+
+/// Events between `start` and `end` (inclusive, YYYY-MM-DD), oldest first. `symbol` keeps that
+/// ticker's events plus market-wide ones.
+pub fn load_events(
+    conn: &Connection,
+    start: Option<&str>,
+    end: Option<&str>,
+    event_type: Option<&str>,
+    symbol: Option<&str>,
+) -> Result<Vec<MarketEvent>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, date, event_type, symbol, title, importance, timing, details, source FROM market_events
+             WHERE (?1 IS NULL OR date >= ?1) AND (?2 IS NULL OR date <= ?2)
+               AND (?3 IS NULL OR event_type = ?3) AND (?4 IS NULL OR symbol = ?4 OR symbol = '')
+             ORDER BY date, event_type, symbol",
+        )
+        .map_err(|e| e.to_string())?;
+    let symbol = symbol.map(|s| s.trim().to_uppercase());
+    let events = stmt
+        .query_map(params![start, end, event_type, symbol], |row| {
+            Ok(MarketEvent {
+                id: row.get(0)?,
+                date: row.get(1)?,
+                event_type: row.get(2)?,
+                symbol: row.get::<_, String>(3).map(|s| if s.is_empty() { None } else { Some(s) })?,
+                title: row.get(4)?,
+                importance: row.get(5)?,
+                timing: row.get(6)?,
+                details: row.get(7)?,
+                source: row.get(8)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<_, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(events)
+}
@@ -1,6 +1,9 @@
 use rusqlite::{Connection, Result};
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use std::cell::Cell;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
+use std::time::Duration;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Trade {
@@ -15,6 +18,164 @@ pub struct Trade {
     pub fees: Option<f64>,
     pub notes: Option<String>,
     pub strategy_id: Option<i64>,
+    /// Import identity: broker fill ID ("ninjatrader:123") or a content hash ("h:...:1"); None for manual trades
+    #[serde(default)]
+    pub external_id: Option<String>,
+    /// Option lifecycle event that closed the position instead of a fill: "expiration", "assignment"
+    /// or "exercise". Such rows only close positions; None for ordinary fills
+    #[serde(default)]
+    pub event_type: Option<String>,
+    /// Quote-currency -> account-currency rate at the time of the fill (forex/foreign instruments);
+    /// None uses the instrument's latest fx_rate
+    #[serde(default)]
+    pub fx_rate: Option<f64>,
+    /// "open" or "close" when the broker reports the position effect (SELL_SHORT, BUY_TO_COVER,
+    /// option to-open/to-close fills); None lets pairing decide from the open positions
+    #[serde(default)]
+    pub position_effect: Option<String>,
+    /// Simulated (paper account) fill. Analytics show either paper or live trades, never both
+    #[serde(default)]
+    pub is_paper: bool,
+}
+
+/// Marker older versions appended to notes instead of setting trades.is_paper
+pub const PAPER_NOTES_TAG: &str = "[PAPER]";
+
+/// Notes with the [PAPER] tag (any case) removed, or None when the tag isn't there. The inner None is
+/// notes that held nothing but the tag.
+fn strip_paper_tag(notes: &str) -> Option<Option<String>> {
+    let start = notes.to_ascii_uppercase().find(PAPER_NOTES_TAG)?;
+    let rest = format!("{} {}", &notes[..start], &notes[start + PAPER_NOTES_TAG.len()..]);
+    let rest = rest.split_whitespace().collect::<Vec<_>>().join(" ");
+    Some(if rest.is_empty() { None } else { Some(rest) })
+}
+
+impl Trade {
+    /// Rewrite side, status and position effect in their stored spellings ("Bought" -> "BUY",
+    /// "FILLED" -> "Filled", "SELL_SHORT" -> SELL opening), and turn a [PAPER] notes tag into the
+    /// is_paper flag. Fails for a side that is neither a buy nor a sell or an unreadable position
+    /// effect; unknown statuses are kept as given.
+    pub fn normalize(&mut self) -> std::result::Result<(), String> {
+        if let Some(rest) = self.notes.as_deref().and_then(strip_paper_tag) {
+            self.notes = rest;
+            self.is_paper = true;
+        }
+        let (side, side_effect) = Side::parse_with_effect(&self.side)
+            .ok_or_else(|| format!("Unknown side '{}'. Use BUY, SELL, SELL_SHORT or BUY_TO_COVER", self.side.trim()))?;
+        self.side = side.as_str().to_string();
+        let effect = match self.position_effect.as_deref().map(str::trim).filter(|e| !e.is_empty()) {
+            Some(value) => Some(PositionEffect::parse(value).ok_or_else(|| format!("Unknown position effect '{}'. Use open or close", value))?),
+            None => side_effect,
+        };
+        self.position_effect = effect.map(|effect| effect.as_str().to_string());
+        self.status = canonical_status(&self.status);
+        Ok(())
+    }
+}
+
+/// Trade direction as stored in trades.side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+impl Side {
+    /// Broker spellings: "Buy", "BOT", "Bought", "Buy to Cover", "Sell", "SLD", "Sold", "Sell Short", "Short", ...
+    pub fn parse(value: &str) -> Option<Side> {
+        Side::parse_with_effect(value).map(|(side, _)| side)
+    }
+
+    /// The side plus the position effect explicit sides carry: SELL_SHORT and the to-open sides open,
+    /// BUY_TO_COVER and the to-close sides close.
+    pub fn parse_with_effect(value: &str) -> Option<(Side, Option<PositionEffect>)> {
+        let key: String = value.chars().filter(|c| c.is_ascii_alphanumeric()).collect::<String>().to_uppercase();
+        match key.as_str() {
+            "BUY" | "B" | "BOT" | "BOUGHT" | "LONG" => Some((Side::Buy, None)),
+            "BUYTOOPEN" | "BTO" | "BOUGHTTOOPEN" => Some((Side::Buy, Some(PositionEffect::Open))),
+            "BUYTOCLOSE" | "BUYTOCOVER" | "BTC" | "COVER" | "BOUGHTTOCOVER" | "BOUGHTTOCLOSE" => Some((Side::Buy, Some(PositionEffect::Close))),
+            "SELL" | "S" | "SLD" | "SOLD" => Some((Side::Sell, None)),
+            "SELLSHORT" | "SHORT" | "SS" | "SOLDSHORT" | "SELLTOOPEN" | "STO" | "SOLDTOOPEN" => Some((Side::Sell, Some(PositionEffect::Open))),
+            "SELLTOCLOSE" | "STC" | "SOLDTOCLOSE" => Some((Side::Sell, Some(PositionEffect::Close))),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Side::Buy => "BUY",
+            Side::Sell => "SELL",
+        }
+    }
+}
+
+/// Whether a fill opens or closes a position, as stored in trades.position_effect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PositionEffect {
+    Open,
+    Close,
+}
+
+impl PositionEffect {
+    /// "open", "Opening", "TO OPEN", "O", "close", "Closing", "TO CLOSE", "C"
+    pub fn parse(value: &str) -> Option<PositionEffect> {
+        let key: String = value.chars().filter(|c| c.is_ascii_alphanumeric()).collect::<String>().to_uppercase();
+        match key.as_str() {
+            "OPEN" | "OPENING" | "TOOPEN" | "O" => Some(PositionEffect::Open),
+            "CLOSE" | "CLOSING" | "TOCLOSE" | "C" => Some(PositionEffect::Close),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PositionEffect::Open => "open",
+            PositionEffect::Close => "close",
+        }
+    }
+}
+
+/// Order status as stored in trades.status. Only Filled trades count toward P&L.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Filled,
+    PartiallyFilled,
+    Pending,
+    Cancelled,
+    Rejected,
+    Expired,
+}
+
+impl Status {
+    /// "Filled", "FILLED", "Executed", "Partially Filled", "PARTIAL", "Canceled", "CXL", "Working", ...
+    pub fn parse(value: &str) -> Option<Status> {
+        let key: String = value.chars().filter(|c| c.is_ascii_alphanumeric()).collect::<String>().to_uppercase();
+        match key.as_str() {
+            "FILLED" | "FILL" | "EXECUTED" | "COMPLETE" | "COMPLETED" | "DONE" => Some(Status::Filled),
+            "PARTIALLYFILLED" | "PARTIALFILL" | "PARTIAL" | "PARTIALLYEXECUTED" => Some(Status::PartiallyFilled),
+            "PENDING" | "WORKING" | "OPEN" | "NEW" | "SUBMITTED" | "ACCEPTED" => Some(Status::Pending),
+            "CANCELLED" | "CANCELED" | "CXL" => Some(Status::Cancelled),
+            "REJECTED" => Some(Status::Rejected),
+            "EXPIRED" => Some(Status::Expired),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Status::Filled => "Filled",
+            Status::PartiallyFilled => "Partially Filled",
+            Status::Pending => "Pending",
+            Status::Cancelled => "Cancelled",
+            Status::Rejected => "Rejected",
+            Status::Expired => "Expired",
+        }
+    }
+}
+
+/// Stored spelling of a status; unknown values are kept (trimmed) so nothing is lost.
+pub fn canonical_status(value: &str) -> String {
+    Status::parse(value).map_or_else(|| value.trim().to_string(), |status| status.as_str().to_string())
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -73,6 +234,8 @@ pub struct Strategy {
     pub color: Option<String>,
     pub display_order: Option<i64>,
     pub author: Option<String>,
+    /// Set when the strategy has been moved to the trash; None for active strategies
+    pub deleted_at: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -85,6 +248,9 @@ pub struct JournalEntry {
     pub updated_at: Option<String>,
     /// JSON array of trade IDs (real trades) linked from Journal page, e.g. "[1,2,3]"
     pub linked_trade_ids: Option<String>,
+    /// Drafted for the user (AI review) and not yet confirmed
+    #[serde(default)]
+    pub is_draft: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -140,9 +306,222 @@ pub struct EconomicEvent {
     pub importance: String,   // "high", "medium", "low"
 }
 
-pub fn init_database(db_path: &Path) -> Result<()> {
-    let conn = Connection::open(db_path)?;
+pub const DB_FILE_NAME: &str = "tradebutler.db";
+/// Same folder name Tauri's app_data_dir uses (the bundle identifier)
+const APP_DIR_NAME: &str = "com.tradebutler.app";
+/// An empty file with this name next to the executable switches on portable mode
+pub const PORTABLE_MARKER: &str = "portable";
+/// Text file in the default app data folder holding a user-chosen data directory (set_data_directory).
+/// It can't live in the database, since it says where the database is.
+const LOCATION_POINTER: &str = "data_location.txt";
+
+/// Why the data directory is where it is
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DataDirSource {
+    CommandLine,
+    Portable,
+    Custom,
+    Default,
+}
+
+/// Per-user app data folder: %APPDATA% (roaming) on Windows like Tauri's app_data_dir, not %LOCALAPPDATA%.
+pub fn default_data_dir() -> PathBuf {
+    dirs::data_dir().expect("Failed to get app data directory").join(APP_DIR_NAME)
+}
+
+fn resolve_data_dir() -> (PathBuf, DataDirSource) {
+    // 1. --data-dir <path> / --data-dir=<path>
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = match arg.strip_prefix("--data-dir") {
+            Some("") => args.next(),
+            Some(rest) => rest.strip_prefix('=').map(str::to_string),
+            None => None,
+        };
+        if let Some(dir) = value.filter(|d| !d.trim().is_empty()) {
+            return (PathBuf::from(dir.trim()), DataDirSource::CommandLine);
+        }
+    }
+    // 2. Portable mode: data folder next to the executable
+    if let Some(exe_dir) = portable_exe_dir() {
+        return (exe_dir.join("data"), DataDirSource::Portable);
+    }
+    // 3. A location chosen in Settings, 4. the per-user app data folder
+    let default_dir = default_data_dir();
+    match std::fs::read_to_string(default_dir.join(LOCATION_POINTER)) {
+        Ok(custom) if !custom.trim().is_empty() => (PathBuf::from(custom.trim()), DataDirSource::Custom),
+        _ => (default_dir, DataDirSource::Default),
+    }
+}
+
+/// Folder of the executable when it has a portable marker file next to it.
+fn portable_exe_dir() -> Option<PathBuf> {
+    let exe_dir = std::env::current_exe().ok()?.parent()?.to_path_buf();
+    exe_dir.join(PORTABLE_MARKER).exists().then_some(exe_dir)
+}
+
+/// True when a `portable` marker file sits next to the executable; data then lives in `data/` beside it
+/// unless --data-dir says otherwise.
+pub fn is_portable() -> bool {
+    portable_exe_dir().is_some()
+}
+
+/// A versioned schema change. Migrations run once each, in order, inside their own transaction, and
+/// PRAGMA user_version records the last one applied. Released migrations must never be edited; schema
+/// changes go in a new entry at the end of MIGRATIONS.
+struct Migration {
+    version: i32,
+    name: &'static str,
+    up: fn(&Connection) -> Result<()>,
+}
+
+const MIGRATIONS: &[Migration] = &[
+    Migration { version: 1, name: "baseline schema", up: migrate_baseline },
+    Migration { version: 2, name: "emotional_states.trade_id ON DELETE SET NULL", up: migrate_emotion_trade_fk },
+    Migration { version: 3, name: "saved_views", up: migrate_saved_views },
+    Migration { version: 4, name: "tags and trade_tags", up: migrate_trade_tags },
+    Migration { version: 5, name: "notes", up: migrate_notes },
+    Migration { version: 6, name: "daily_reviews", up: migrate_daily_reviews },
+    Migration { version: 7, name: "journal templates and entry sections", up: migrate_journal_templates },
+    Migration { version: 8, name: "trade_reviews", up: migrate_trade_reviews },
+    Migration { version: 9, name: "setups, checklist_items and pair_setups", up: migrate_setups },
+    Migration { version: 10, name: "strategy checklist_items and trade_checklist_results", up: migrate_checklist_results },
+    Migration { version: 11, name: "trade_plans", up: migrate_trade_plans },
+    Migration { version: 12, name: "trades.intended_price", up: migrate_intended_price },
+    Migration { version: 13, name: "trade_plans position sizing", up: migrate_trade_plan_sizing },
+    Migration { version: 14, name: "risk_rules and rule_violations", up: migrate_risk_rules },
+    Migration { version: 15, name: "account_balances", up: migrate_account_balances },
+    Migration { version: 16, name: "quote_cache", up: migrate_quote_cache },
+    Migration { version: 17, name: "price_bars and pair_excursions", up: migrate_price_bars },
+    Migration { version: 18, name: "market_events", up: migrate_market_events },
+    Migration { version: 19, name: "instrument_specs -> instruments with sector metadata", up: migrate_instruments },
+    Migration { version: 20, name: "trades.event_type and option_events", up: migrate_option_events },
+    Migration { version: 21, name: "trades.fx_rate", up: migrate_trade_fx_rates },
+    Migration { version: 22, name: "manual_pairs", up: migrate_manual_pairs },
+    Migration { version: 23, name: "pairing_overrides", up: migrate_pairing_overrides },
+    Migration { version: 24, name: "canonical side and status", up: migrate_canonical_side_status },
+    Migration { version: 25, name: "trades.position_effect", up: migrate_trade_position_effect },
+    Migration { version: 26, name: "trades.is_paper", up: migrate_trade_is_paper },
+    Migration { version: 27, name: "trades.deleted_at", up: migrate_trade_deleted_at },
+    Migration { version: 28, name: "operation_log", up: migrate_operation_log },
+];
+
+pub fn init_database(db_path: &Path, source: DataDirSource) -> Result<()> {
+    if let Some(dir) = db_path.parent() {
+        std::fs::create_dir_all(dir).expect("Failed to create app data directory");
+    }
+    // Not pooled: migrations run with foreign keys off, which must not leak into command connections.
+    // Table rebuilds drop and re-create tables, which must not fire ON DELETE actions.
+    let conn = open_connection(db_path, source)?;
+    conn.execute_batch("PRAGMA foreign_keys = OFF")?;
+
+    let current: i32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    let latest = schema_version();
+    if current > latest {
+        eprintln!(
+            "[Database] Schema version {} is newer than this app supports ({}); a newer TradeButler created this database",
+            current, latest
+        );
+    }
+    migrate_to(&conn, latest)?;
+    if current < latest {
+        // An upgrade is when damage from a crash or a half-synced copy surfaces; say so in the log
+        // rather than failing later in an unrelated query. run_integrity_check reports the details.
+        match crate::integrity::database_errors(&conn) {
+            Ok(errors) if !errors.is_empty() => eprintln!("[Database] Integrity check after upgrade failed: {}", errors.join("; ")),
+            Ok(_) => {}
+            Err(e) => eprintln!("[Database] Integrity check after upgrade could not run: {}", e),
+        }
+    }
+    Ok(())
+}
+
+/// Newest schema version (PRAGMA user_version) this build creates.
+pub fn schema_version() -> i32 {
+    MIGRATIONS.last().map_or(0, |m| m.version)
+}
+
+/// Apply the migrations after the database's current version, up to and including `target`. Run with
+/// foreign keys off, like init_database.
+pub fn migrate_to(conn: &Connection, target: i32) -> Result<()> {
+    let current: i32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    for migration in MIGRATIONS.iter().filter(|m| m.version > current && m.version <= target) {
+        let tx = conn.unchecked_transaction()?;
+        (migration.up)(&tx)?;
+        tx.execute_batch(&format!("PRAGMA user_version = {}", migration.version))?;
+        tx.commit()?;
+        eprintln!("[Database] Applied migration {}: {}", migration.version, migration.name);
+    }
+    Ok(())
+}
+
+/// A column value as JSON, for row snapshots and dataset exports. Blobs are not stored in any table and
+/// come out as null.
+pub(crate) fn sql_to_json(value: rusqlite::types::Value) -> serde_json::Value {
+    use rusqlite::types::Value;
+    match value {
+        Value::Null | Value::Blob(_) => serde_json::Value::Null,
+        Value::Integer(i) => i.into(),
+        Value::Real(f) => serde_json::Number::from_f64(f).map(serde_json::Value::Number).unwrap_or(serde_json::Value::Null),
+        Value::Text(s) => s.into(),
+    }
+}
+
+/// Inverse of sql_to_json: whole numbers go back as INTEGER, others as REAL.
+pub(crate) fn json_to_sql(value: &serde_json::Value) -> rusqlite::types::Value {
+    use rusqlite::types::Value;
+    match value {
+        serde_json::Value::Null => Value::Null,
+        serde_json::Value::Bool(b) => Value::Integer(*b as i64),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => Value::Integer(i),
+            None => Value::Real(n.as_f64().unwrap_or(0.0)),
+        },
+        serde_json::Value::String(s) => Value::Text(s.clone()),
+        other => Value::Text(other.to_string()),
+    }
+}
 
+/// Whether `table` has a column named `column`.
+fn has_column(conn: &Connection, table: &str, column: &str) -> Result<bool> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM pragma_table_info(?1) WHERE name = ?2",
+        rusqlite::params![table, column],
+        |row| row.get::<_, i64>(0),
+    )
+    .map(|count| count > 0)
+}
+
+/// Re-create `table` with a new column list (the body of a CREATE TABLE), copying every column the old
+/// and new definitions share. This is SQLite's documented procedure for changes ALTER TABLE can't make:
+/// constraints, column types, dropping columns. Indexes on the table are dropped with the old copy, so
+/// the migration must re-create them.
+fn rebuild_table(conn: &Connection, table: &str, columns_sql: &str) -> Result<()> {
+    let new_table = format!("{}_new", table);
+    conn.execute_batch(&format!("DROP TABLE IF EXISTS {}; CREATE TABLE {} ({})", new_table, new_table, columns_sql))?;
+    let mut shared = Vec::new();
+    {
+        let mut stmt = conn.prepare("SELECT name FROM pragma_table_info(?1)")?;
+        let new_columns = stmt.query_map([&new_table], |row| row.get::<_, String>(0))?;
+        for column in new_columns {
+            let column = column?;
+            if has_column(conn, table, &column)? {
+                shared.push(format!("\"{}\"", column));
+            }
+        }
+    }
+    let shared = shared.join(", ");
+    conn.execute_batch(&format!(
+        "INSERT INTO {new_table} ({shared}) SELECT {shared} FROM {table};
+         DROP TABLE {table};
+         ALTER TABLE {new_table} RENAME TO {table};"
+    ))
+}
+
+// Schema as it stood before versioned migrations. Databases created before then reached different points
+// of it, so every statement here stays idempotent (IF NOT EXISTS, column checks).
+fn migrate_baseline(conn: &Connection) -> Result<()> {
     // Create trades table
     conn.execute(
         "CREATE TABLE IF NOT EXISTS trades (
@@ -205,6 +584,15 @@ pub fn init_database(db_path: &Path) -> Result<()> {
         "ALTER TABLE strategies ADD COLUMN author TEXT",
         [],
     );
+    // Add deleted_at to strategies (trash): deleted strategies keep their trades/journal links so they can be restored
+    let has_strategy_deleted_at: bool = conn.query_row(
+        "SELECT COUNT(*) FROM pragma_table_info('strategies') WHERE name='deleted_at'",
+        [],
+        |row| row.get(0),
+    ).unwrap_or(0) > 0;
+    if !has_strategy_deleted_at {
+        conn.execute("ALTER TABLE strategies ADD COLUMN deleted_at TEXT", [])?;
+    }
     
     // Create index for strategy_id
     conn.execute(
@@ -393,6 +781,16 @@ pub fn init_database(db_path: &Path) -> Result<()> {
         conn.execute("ALTER TABLE journal_entries ADD COLUMN is_paper INTEGER NOT NULL DEFAULT 0", [])?;
     }
 
+    // journal_entries: drafts (e.g. AI-drafted reviews) awaiting the user's edit (0 = final, 1 = draft)
+    let has_je_is_draft: bool = conn.query_row(
+        "SELECT COUNT(*) FROM pragma_table_info('journal_entries') WHERE name='is_draft'",
+        [],
+        |row| row.get(0),
+    ).unwrap_or(0) > 0;
+    if !has_je_is_draft {
+        conn.execute("ALTER TABLE journal_entries ADD COLUMN is_draft INTEGER NOT NULL DEFAULT 0", [])?;
+    }
+
     // emotional_states: separate real vs paper (0 = real, 1 = paper)
     let has_es_is_paper: bool = conn.query_row(
         "SELECT COUNT(*) FROM pragma_table_info('emotional_states') WHERE name='is_paper'",
@@ -651,10 +1049,1101 @@ pub fn init_database(db_path: &Path) -> Result<()> {
         [],
     )?;
 
+    // Structured option fields parsed from OCC symbols (underlying is set for every trade; the
+    // rest are NULL for non-options). Backfilled for existing rows.
+    for (column, column_type) in [("underlying", "TEXT"), ("option_expiration", "TEXT"), ("option_right", "TEXT"), ("option_strike", "REAL")] {
+        let exists: bool = conn.query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('trades') WHERE name = ?1",
+            [column],
+            |row| row.get::<_, i64>(0),
+        ).unwrap_or(0) > 0;
+        if !exists {
+            conn.execute(&format!("ALTER TABLE trades ADD COLUMN {} {}", column, column_type), [])?;
+        }
+    }
+    // Optional greeks captured at entry for option trades (manual or estimated from market data)
+    for (column, column_type) in [("option_delta", "REAL"), ("option_iv", "REAL"), ("option_premium", "REAL"), ("underlying_price_at_entry", "REAL")] {
+        let exists: bool = conn.query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('trades') WHERE name = ?1",
+            [column],
+            |row| row.get::<_, i64>(0),
+        ).unwrap_or(0) > 0;
+        if !exists {
+            conn.execute(&format!("ALTER TABLE trades ADD COLUMN {} {}", column, column_type), [])?;
+        }
+    }
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_trades_underlying ON trades(underlying)",
+        [],
+    )?;
+    crate::options::backfill_option_fields(conn)?;
+
+    // Import batches: every import runs in one transaction together with its batch row, so a
+    // committed batch is always 'completed'.
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS import_batches (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            source TEXT NOT NULL,
+            status TEXT NOT NULL DEFAULT 'pending',
+            trade_count INTEGER NOT NULL DEFAULT 0,
+            started_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            completed_at TEXT
+        )",
+        [],
+    )?;
+    let has_import_batch_id: bool = conn.query_row(
+        "SELECT COUNT(*) FROM pragma_table_info('trades') WHERE name='import_batch_id'",
+        [],
+        |row| row.get(0),
+    ).unwrap_or(0) > 0;
+    if !has_import_batch_id {
+        conn.execute("ALTER TABLE trades ADD COLUMN import_batch_id INTEGER", [])?;
+    }
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_trades_import_batch ON trades(import_batch_id)",
+        [],
+    )?;
+    // Import identity (broker fill ID or numbered content hash) so duplicates are skipped by the UNIQUE
+    // index. Existing trades get content hashes, numbered in id order like an import numbers a file.
+    let has_external_id: bool = conn.query_row(
+        "SELECT COUNT(*) FROM pragma_table_info('trades') WHERE name='external_id'",
+        [],
+        |row| row.get(0),
+    ).unwrap_or(0) > 0;
+    if !has_external_id {
+        conn.execute("ALTER TABLE trades ADD COLUMN external_id TEXT", [])?;
+        let existing: Vec<(i64, String, String, f64, f64, String)> = {
+            let mut stmt = conn.prepare("SELECT id, symbol, side, quantity, price, timestamp FROM trades ORDER BY id")?;
+            let rows = stmt.query_map([], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?))
+            })?;
+            rows.collect::<Result<Vec<_>>>()?
+        };
+        let mut occurrences: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
+        for (id, symbol, side, quantity, price, timestamp) in existing {
+            let hash = trade_content_hash(&symbol, &side, quantity, price, &timestamp);
+            let occurrence = occurrences.entry(hash.clone()).or_insert(0);
+            *occurrence += 1;
+            conn.execute(
+                "UPDATE trades SET external_id = ?1 WHERE id = ?2",
+                rusqlite::params![format!("{}:{}", hash, occurrence), id],
+            )?;
+        }
+    }
+    conn.execute(
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_trades_external_id ON trades(external_id)",
+        [],
+    )?;
+    // Import duplicate check looks trades up by symbol and timestamp
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_trades_symbol_timestamp ON trades(symbol, timestamp)",
+        [],
+    )?;
+    // Imports can be undone (rollback_import); the batch row is kept with status 'rolled_back'
+    let has_rolled_back_at: bool = conn.query_row(
+        "SELECT COUNT(*) FROM pragma_table_info('import_batches') WHERE name='rolled_back_at'",
+        [],
+        |row| row.get(0),
+    ).unwrap_or(0) > 0;
+    if !has_rolled_back_at {
+        conn.execute("ALTER TABLE import_batches ADD COLUMN rolled_back_at TEXT", [])?;
+    }
+    // Recovery: SQLite rolls back an import interrupted by a crash on its own; a batch still marked
+    // 'pending' here was never finished, so remove it and any trades tagged with it.
+    conn.execute(
+        "DELETE FROM trades WHERE import_batch_id IN (SELECT id FROM import_batches WHERE status = 'pending')",
+        [],
+    )?;
+    conn.execute("DELETE FROM import_batches WHERE status = 'pending'", [])?;
+
+    // Journaling/review sessions (time spent reviewing, entries written, trades graded)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS review_sessions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            started_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            ended_at TEXT,
+            duration_seconds INTEGER,
+            entries_written INTEGER NOT NULL DEFAULT 0,
+            trades_graded INTEGER NOT NULL DEFAULT 0,
+            notes TEXT
+        )",
+        [],
+    )?;
+
+    // Goals evaluated per daily/weekly/monthly period from existing trade and journal data
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS goals (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            goal_type TEXT NOT NULL,
+            period TEXT NOT NULL DEFAULT 'monthly',
+            target_value REAL NOT NULL DEFAULT 0,
+            is_active INTEGER NOT NULL DEFAULT 1,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+
+    // Key/value app settings (e.g. last_backup_at)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS app_settings (
+            key TEXT PRIMARY KEY,
+            value TEXT,
+            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+
+    // Reminders fired as OS notifications by the background scheduler.
+    // kind: daily_review, weekly_review, backup_stale, option_expiring, custom
+    // days_of_week: comma-separated ISO weekdays (1 = Monday ... 7 = Sunday), NULL = every day
+    let reminders_exist: bool = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='reminders'",
+        [],
+        |row| row.get::<_, i64>(0),
+    ).unwrap_or(0) > 0;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS reminders (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            kind TEXT NOT NULL,
+            title TEXT NOT NULL,
+            body TEXT,
+            time_of_day TEXT NOT NULL,
+            days_of_week TEXT,
+            threshold_days INTEGER,
+            enabled INTEGER NOT NULL DEFAULT 1,
+            last_run_at TEXT,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    if !reminders_exist {
+        // Seed the default reminders the first time the table is created
+        conn.execute(
+            "INSERT INTO reminders (kind, title, body, time_of_day, days_of_week, threshold_days) VALUES
+                ('daily_review', 'Daily review', 'Markets are closed. Time to journal today''s trades.', '16:30', '1,2,3,4,5', NULL),
+                ('weekly_review', 'Weekly review', 'Review the week: metrics, mistakes and goals for next week.', '18:00', '7', NULL),
+                ('backup_stale', 'Backup reminder', 'Your last TradeButler backup is getting old. Export your data.', '12:00', NULL, 7),
+                ('option_expiring', 'Options expiring', 'You have open option positions expiring soon.', '09:00', '5', 0)",
+            [],
+        )?;
+    }
+
+    // Delivery log for reminders
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS reminder_deliveries (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            reminder_id INTEGER NOT NULL,
+            delivered_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            title TEXT NOT NULL,
+            body TEXT,
+            status TEXT NOT NULL,
+            error TEXT,
+            FOREIGN KEY (reminder_id) REFERENCES reminders(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_reminder_deliveries_reminder ON reminder_deliveries(reminder_id)",
+        [],
+    )?;
+
+    // Funding payments on perpetual futures. amount: + received / - paid, in the account currency
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS funding_payments (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            symbol TEXT NOT NULL,
+            amount REAL NOT NULL,
+            paid_at TEXT NOT NULL,
+            funding_rate REAL,
+            notes TEXT,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_funding_payments_symbol ON funding_payments(symbol, paid_at)",
+        [],
+    )?;
+
+    // Per-instrument contract specs. symbol is an exact contract symbol or an option root
+    // (e.g. adjusted "AAPL1", mini "AAPL7"); multiplier replaces the hard-coded 100x for options.
+    // deliverable_shares = shares delivered per contract on assignment (NULL = same as multiplier)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS instrument_specs (
+            symbol TEXT PRIMARY KEY,
+            instrument_type TEXT NOT NULL DEFAULT 'option',
+            multiplier REAL NOT NULL,
+            deliverable_shares REAL,
+            deliverable TEXT,
+            notes TEXT,
+            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    // Futures contract specs: symbol is the root (ES, MNQ, CL), multiplier is the point value in `currency`
+    let has_futures_columns: bool = conn.query_row(
+        "SELECT COUNT(*) FROM pragma_table_info('instrument_specs') WHERE name = 'tick_size'",
+        [],
+        |row| row.get::<_, i64>(0),
+    ).unwrap_or(0) > 0;
+    if !has_futures_columns {
+        for (column, column_type) in [("exchange", "TEXT"), ("tick_size", "REAL"), ("currency", "TEXT NOT NULL DEFAULT 'USD'"), ("trading_hours", "TEXT")] {
+            conn.execute(&format!("ALTER TABLE instrument_specs ADD COLUMN {} {}", column, column_type), [])?;
+        }
+        // Seed the built-in futures specs once; users can edit or delete them afterwards
+        conn.execute(
+            "INSERT OR IGNORE INTO instrument_specs (symbol, instrument_type, multiplier, exchange, tick_size, currency, trading_hours, notes) VALUES
+                ('ES', 'future', 50, 'CME', 0.25, 'USD', 'Sun-Fri 18:00-17:00 ET', 'E-mini S&P 500'),
+                ('MES', 'future', 5, 'CME', 0.25, 'USD', 'Sun-Fri 18:00-17:00 ET', 'Micro E-mini S&P 500'),
+                ('NQ', 'future', 20, 'CME', 0.25, 'USD', 'Sun-Fri 18:00-17:00 ET', 'E-mini Nasdaq-100'),
+                ('MNQ', 'future', 2, 'CME', 0.25, 'USD', 'Sun-Fri 18:00-17:00 ET', 'Micro E-mini Nasdaq-100'),
+                ('YM', 'future', 5, 'CBOT', 1, 'USD', 'Sun-Fri 18:00-17:00 ET', 'E-mini Dow'),
+                ('MYM', 'future', 0.5, 'CBOT', 1, 'USD', 'Sun-Fri 18:00-17:00 ET', 'Micro E-mini Dow'),
+                ('RTY', 'future', 50, 'CME', 0.1, 'USD', 'Sun-Fri 18:00-17:00 ET', 'E-mini Russell 2000'),
+                ('M2K', 'future', 5, 'CME', 0.1, 'USD', 'Sun-Fri 18:00-17:00 ET', 'Micro E-mini Russell 2000'),
+                ('CL', 'future', 1000, 'NYMEX', 0.01, 'USD', 'Sun-Fri 18:00-17:00 ET', 'Crude Oil'),
+                ('MCL', 'future', 100, 'NYMEX', 0.01, 'USD', 'Sun-Fri 18:00-17:00 ET', 'Micro Crude Oil'),
+                ('NG', 'future', 10000, 'NYMEX', 0.001, 'USD', 'Sun-Fri 18:00-17:00 ET', 'Natural Gas'),
+                ('GC', 'future', 100, 'COMEX', 0.1, 'USD', 'Sun-Fri 18:00-17:00 ET', 'Gold'),
+                ('MGC', 'future', 10, 'COMEX', 0.1, 'USD', 'Sun-Fri 18:00-17:00 ET', 'Micro Gold'),
+                ('SI', 'future', 5000, 'COMEX', 0.005, 'USD', 'Sun-Fri 18:00-17:00 ET', 'Silver'),
+                ('ZB', 'future', 1000, 'CBOT', 0.03125, 'USD', 'Sun-Fri 19:00-18:00 ET', '30-Year T-Bond'),
+                ('ZN', 'future', 1000, 'CBOT', 0.015625, 'USD', 'Sun-Fri 19:00-18:00 ET', '10-Year T-Note'),
+                ('ZC', 'future', 50, 'CBOT', 0.25, 'USD', 'Sun-Fri 20:00-14:20 ET', 'Corn (cents/bushel)'),
+                ('6E', 'future', 125000, 'CME', 0.00005, 'USD', 'Sun-Fri 18:00-17:00 ET', 'Euro FX')",
+            [],
+        )?;
+    }
+    // Forex pair specs: symbol is the 6-letter pair, multiplier is units per 1.0 quantity (100000 when
+    // quantities are in standard lots), currency is the quote currency. fx_rate converts `currency` into
+    // the account currency (NULL = no conversion) and applies to any instrument.
+    let has_forex_columns: bool = conn.query_row(
+        "SELECT COUNT(*) FROM pragma_table_info('instrument_specs') WHERE name = 'pip_size'",
+        [],
+        |row| row.get::<_, i64>(0),
+    ).unwrap_or(0) > 0;
+    if !has_forex_columns {
+        for (column, column_type) in [("pip_size", "REAL"), ("fx_rate", "REAL")] {
+            conn.execute(&format!("ALTER TABLE instrument_specs ADD COLUMN {} {}", column, column_type), [])?;
+        }
+        conn.execute(
+            "INSERT OR IGNORE INTO instrument_specs (symbol, instrument_type, multiplier, pip_size, currency, trading_hours, notes) VALUES
+                ('EURUSD', 'forex', 100000, 0.0001, 'USD', 'Sun-Fri 17:00-17:00 ET', 'Quantity in standard lots'),
+                ('GBPUSD', 'forex', 100000, 0.0001, 'USD', 'Sun-Fri 17:00-17:00 ET', 'Quantity in standard lots'),
+                ('AUDUSD', 'forex', 100000, 0.0001, 'USD', 'Sun-Fri 17:00-17:00 ET', 'Quantity in standard lots'),
+                ('NZDUSD', 'forex', 100000, 0.0001, 'USD', 'Sun-Fri 17:00-17:00 ET', 'Quantity in standard lots'),
+                ('USDJPY', 'forex', 100000, 0.01, 'JPY', 'Sun-Fri 17:00-17:00 ET', 'Quantity in standard lots'),
+                ('USDCHF', 'forex', 100000, 0.0001, 'CHF', 'Sun-Fri 17:00-17:00 ET', 'Quantity in standard lots'),
+                ('USDCAD', 'forex', 100000, 0.0001, 'CAD', 'Sun-Fri 17:00-17:00 ET', 'Quantity in standard lots'),
+                ('EURGBP', 'forex', 100000, 0.0001, 'GBP', 'Sun-Fri 17:00-17:00 ET', 'Quantity in standard lots'),
+                ('EURJPY', 'forex', 100000, 0.01, 'JPY', 'Sun-Fri 17:00-17:00 ET', 'Quantity in standard lots'),
+                ('GBPJPY', 'forex', 100000, 0.01, 'JPY', 'Sun-Fri 17:00-17:00 ET', 'Quantity in standard lots')",
+            [],
+        )?;
+    }
+
+    // AI reviews: LLM analyses of a date range (summary plus structured insights as JSON)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS ai_reviews (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            kind TEXT NOT NULL DEFAULT 'period',
+            start_date TEXT,
+            end_date TEXT,
+            provider TEXT NOT NULL,
+            model TEXT NOT NULL,
+            summary TEXT NOT NULL,
+            insights_json TEXT NOT NULL DEFAULT '{}',
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    // Drafted trade reviews point at the journal entry they created
+    let has_ai_review_entry: bool = conn.query_row(
+        "SELECT COUNT(*) FROM pragma_table_info('ai_reviews') WHERE name='journal_entry_id'",
+        [],
+        |row| row.get::<_, i64>(0),
+    ).unwrap_or(0) > 0;
+    if !has_ai_review_entry {
+        conn.execute("ALTER TABLE ai_reviews ADD COLUMN journal_entry_id INTEGER", [])?;
+    }
+
+    // Behavioral pattern events (revenge re-entry, overtrading days, size escalation after losses).
+    // dedupe_key identifies the occurrence so re-running detection never duplicates or revives dismissed events.
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS pattern_events (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            kind TEXT NOT NULL,
+            dedupe_key TEXT NOT NULL UNIQUE,
+            occurred_at TEXT NOT NULL,
+            symbol TEXT,
+            trade_id INTEGER,
+            related_trade_id INTEGER,
+            pnl REAL,
+            details TEXT NOT NULL,
+            dismissed INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_pattern_events_occurred ON pattern_events(occurred_at)",
+        [],
+    )?;
+
+    // Saved column mappings for importing CSVs from brokers without a built-in parser
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS import_profiles (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE,
+            mapping_json TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+
+    // Asset class per trade (stock, option, future, forex, crypto); inferred from specs/symbol where NULL.
+    // Runs last so the instrument specs it consults exist.
+    let has_asset_class: bool = conn.query_row(
+        "SELECT COUNT(*) FROM pragma_table_info('trades') WHERE name = 'asset_class'",
+        [],
+        |row| row.get::<_, i64>(0),
+    ).unwrap_or(0) > 0;
+    if !has_asset_class {
+        conn.execute("ALTER TABLE trades ADD COLUMN asset_class TEXT", [])?;
+    }
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_trades_asset_class ON trades(asset_class)",
+        [],
+    )?;
+    if let Err(e) = crate::commands::backfill_asset_classes(conn) {
+        eprintln!("[Database] Asset class backfill failed: {}", e);
+    }
+
+    Ok(())
+}
+
+// Deleting a trade that has an emotion logged against it fails once foreign keys are enforced; the
+// emotion should stay and just lose the link.
+fn migrate_emotion_trade_fk(conn: &Connection) -> Result<()> {
+    rebuild_table(
+        conn,
+        "emotional_states",
+        "id INTEGER PRIMARY KEY AUTOINCREMENT,
+         timestamp TEXT NOT NULL,
+         emotion TEXT NOT NULL,
+         intensity INTEGER NOT NULL,
+         notes TEXT,
+         trade_id INTEGER REFERENCES trades(id) ON DELETE SET NULL,
+         is_paper INTEGER NOT NULL DEFAULT 0,
+         journal_entry_id INTEGER REFERENCES journal_entries(id) ON DELETE SET NULL,
+         journal_trade_id INTEGER REFERENCES journal_trades(id) ON DELETE SET NULL,
+         journal_entry_ids TEXT,
+         trade_ids TEXT",
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_emotional_states_timestamp ON emotional_states(timestamp)",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Named query_trades filters; filter_json is a serialized commands::TradeFilter
+fn migrate_saved_views(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS saved_views (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE,
+            filter_json TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Free-form trade tags ("gap-up", "earnings", "revenge"); a trade can carry any number of them
+fn migrate_trade_tags(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS tags (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+            color TEXT,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        );
+        CREATE TABLE IF NOT EXISTS trade_tags (
+            trade_id INTEGER NOT NULL REFERENCES trades(id) ON DELETE CASCADE,
+            tag_id INTEGER NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+            PRIMARY KEY (trade_id, tag_id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_trade_tags_tag ON trade_tags(tag_id);",
+    )?;
+    Ok(())
+}
+
+/// Standalone notebook pages (markdown) not tied to a trade or journal entry
+fn migrate_notes(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS notes (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            title TEXT NOT NULL,
+            content TEXT NOT NULL DEFAULT '',
+            pinned INTEGER NOT NULL DEFAULT 0,
+            display_order INTEGER,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// One pre-market plan / post-market review per trading day. focus_symbols and rule_compliance
+/// are JSON arrays.
+fn migrate_daily_reviews(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS daily_reviews (
+            date TEXT PRIMARY KEY,
+            plan TEXT,
+            bias TEXT,
+            focus_symbols TEXT NOT NULL DEFAULT '[]',
+            rule_compliance TEXT NOT NULL DEFAULT '[]',
+            grade TEXT,
+            lessons TEXT,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Reusable journal templates (sections is a JSON array of {heading, prompt}) and the sections a
+/// template stamps into each entry created from it
+fn migrate_journal_templates(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS templates (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE,
+            title TEXT NOT NULL,
+            sections TEXT NOT NULL DEFAULT '[]',
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        );
+        CREATE TABLE IF NOT EXISTS journal_entry_sections (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            journal_entry_id INTEGER NOT NULL REFERENCES journal_entries(id) ON DELETE CASCADE,
+            heading TEXT NOT NULL,
+            prompt TEXT,
+            content TEXT NOT NULL DEFAULT '',
+            section_order INTEGER NOT NULL DEFAULT 0
+        );
+        CREATE INDEX IF NOT EXISTS idx_journal_entry_sections_entry ON journal_entry_sections(journal_entry_id);",
+    )?;
+    if !has_column(conn, "journal_entries", "template_id")? {
+        conn.execute(
+            "ALTER TABLE journal_entries ADD COLUMN template_id INTEGER REFERENCES templates(id) ON DELETE SET NULL",
+            [],
+        )?;
+    }
+    Ok(())
+}
+
+/// Post-trade review per trade: reviewed flag, A-F grade, mistakes (JSON array) and the lesson
+fn migrate_trade_reviews(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS trade_reviews (
+            trade_id INTEGER PRIMARY KEY REFERENCES trades(id) ON DELETE CASCADE,
+            reviewed INTEGER NOT NULL DEFAULT 0,
+            grade TEXT,
+            mistakes TEXT NOT NULL DEFAULT '[]',
+            lesson TEXT,
+            reviewed_at TEXT,
+            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Playbook setups (distinct from strategies), each setup's checklist, and the setup assigned to a
+/// closed pair (keyed by entry/exit trade like pair_notes). screenshots is a JSON array of paths.
+fn migrate_setups(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS setups (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE,
+            description TEXT,
+            entry_criteria TEXT,
+            invalidation TEXT,
+            target_profile TEXT,
+            screenshots TEXT NOT NULL DEFAULT '[]',
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        );
+        CREATE TABLE IF NOT EXISTS checklist_items (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            setup_id INTEGER REFERENCES setups(id) ON DELETE CASCADE,
+            item_text TEXT NOT NULL,
+            item_order INTEGER NOT NULL DEFAULT 0
+        );
+        CREATE INDEX IF NOT EXISTS idx_checklist_items_setup ON checklist_items(setup_id);
+        CREATE TABLE IF NOT EXISTS pair_setups (
+            entry_trade_id INTEGER NOT NULL REFERENCES trades(id) ON DELETE CASCADE,
+            exit_trade_id INTEGER NOT NULL REFERENCES trades(id) ON DELETE CASCADE,
+            setup_id INTEGER NOT NULL REFERENCES setups(id) ON DELETE CASCADE,
+            PRIMARY KEY (entry_trade_id, exit_trade_id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_pair_setups_setup ON pair_setups(setup_id);",
+    )?;
+    Ok(())
+}
+
+/// Checklist items can belong to a strategy as well as a setup; trade_checklist_results records which
+/// items were satisfied before a trade was taken
+fn migrate_checklist_results(conn: &Connection) -> Result<()> {
+    if !has_column(conn, "checklist_items", "strategy_id")? {
+        conn.execute(
+            "ALTER TABLE checklist_items ADD COLUMN strategy_id INTEGER REFERENCES strategies(id) ON DELETE CASCADE",
+            [],
+        )?;
+    }
+    conn.execute_batch(
+        "CREATE INDEX IF NOT EXISTS idx_checklist_items_strategy ON checklist_items(strategy_id);
+        CREATE TABLE IF NOT EXISTS trade_checklist_results (
+            trade_id INTEGER NOT NULL REFERENCES trades(id) ON DELETE CASCADE,
+            checklist_item_id INTEGER NOT NULL REFERENCES checklist_items(id) ON DELETE CASCADE,
+            satisfied INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY (trade_id, checklist_item_id)
+        );",
+    )?;
+    Ok(())
+}
+
+/// Planned entry, stop and target for a position, keyed by its entry trade; the stop defines 1R
+fn migrate_trade_plans(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS trade_plans (
+            trade_id INTEGER PRIMARY KEY REFERENCES trades(id) ON DELETE CASCADE,
+            planned_entry REAL,
+            stop_price REAL NOT NULL,
+            target_price REAL,
+            notes TEXT,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Price the trader meant to fill at (limit/trigger price or the quote when the order was sent),
+/// compared with the actual fill price for slippage
+fn migrate_intended_price(conn: &Connection) -> Result<()> {
+    if !has_column(conn, "trades", "intended_price")? {
+        conn.execute("ALTER TABLE trades ADD COLUMN intended_price REAL", [])?;
+    }
     Ok(())
 }
 
-pub fn get_connection(db_path: &Path) -> Result<Connection> {
-    Connection::open(db_path)
+/// Inputs and result of the position size calculator when a plan was sized with it
+fn migrate_trade_plan_sizing(conn: &Connection) -> Result<()> {
+    for column in ["account_size", "risk_pct", "planned_quantity"] {
+        if !has_column(conn, "trade_plans", column)? {
+            conn.execute(&format!("ALTER TABLE trade_plans ADD COLUMN {} REAL", column), [])?;
+        }
+    }
+    Ok(())
 }
 
+/// Configurable risk limits (one row per rule kind) and the violations found by evaluating them
+/// against the trade history
+fn migrate_risk_rules(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS risk_rules (
+            kind TEXT PRIMARY KEY,
+            threshold REAL NOT NULL,
+            enabled INTEGER NOT NULL DEFAULT 1,
+            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS rule_violations (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            rule_kind TEXT NOT NULL,
+            dedupe_key TEXT NOT NULL UNIQUE,
+            occurred_at TEXT NOT NULL,
+            symbol TEXT,
+            trade_id INTEGER REFERENCES trades(id) ON DELETE CASCADE,
+            value REAL NOT NULL,
+            threshold REAL NOT NULL,
+            details TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_rule_violations_occurred ON rule_violations(occurred_at)",
+        [],
+    )?;
+    Ok(())
+}
+
+/// End-of-day account balance snapshots, kept separately for live and paper accounts
+fn migrate_account_balances(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS account_balances (
+            date TEXT NOT NULL,
+            is_paper INTEGER NOT NULL DEFAULT 0,
+            balance REAL NOT NULL,
+            notes TEXT,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (date, is_paper)
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn migrate_quote_cache(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS quote_cache (
+            symbol TEXT PRIMARY KEY,
+            price REAL NOT NULL,
+            open REAL,
+            previous_close REAL,
+            currency TEXT,
+            provider TEXT NOT NULL,
+            quoted_at TEXT,
+            fetched_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// OHLC bars keyed by bar start (epoch seconds), and the MAE/MFE computed from them for each pair
+fn migrate_price_bars(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS price_bars (
+            symbol TEXT NOT NULL,
+            interval TEXT NOT NULL,
+            ts INTEGER NOT NULL,
+            open REAL NOT NULL,
+            high REAL NOT NULL,
+            low REAL NOT NULL,
+            close REAL NOT NULL,
+            volume REAL,
+            provider TEXT NOT NULL,
+            PRIMARY KEY (symbol, interval, ts)
+        );
+        CREATE TABLE IF NOT EXISTS pair_excursions (
+            entry_trade_id INTEGER NOT NULL REFERENCES trades(id) ON DELETE CASCADE,
+            exit_trade_id INTEGER NOT NULL REFERENCES trades(id) ON DELETE CASCADE,
+            interval TEXT NOT NULL,
+            bars INTEGER NOT NULL,
+            mae REAL NOT NULL,
+            mfe REAL NOT NULL,
+            mae_amount REAL NOT NULL,
+            mfe_amount REAL NOT NULL,
+            computed_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (entry_trade_id, exit_trade_id)
+        );",
+    )?;
+    Ok(())
+}
+
+/// Earnings and economic releases; symbol is '' for market-wide events so the unique key works
+fn migrate_market_events(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS market_events (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            date TEXT NOT NULL,
+            event_type TEXT NOT NULL,
+            symbol TEXT NOT NULL DEFAULT '',
+            title TEXT NOT NULL,
+            importance TEXT,
+            timing TEXT,
+            details TEXT,
+            source TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            UNIQUE (date, event_type, symbol)
+        );
+        CREATE INDEX IF NOT EXISTS idx_market_events_date ON market_events(date);",
+    )?;
+    Ok(())
+}
+
+// instrument_specs becomes the general per-symbol instruments table: besides contract specs it holds
+// user-maintained or fetched metadata for plain tickers (asset class in instrument_type, sector,
+// industry, name). source is "manual" for rows typed in (and every row that predates it) or the
+// market data provider that filled the record.
+fn migrate_instruments(conn: &Connection) -> Result<()> {
+    conn.execute_batch("ALTER TABLE instrument_specs RENAME TO instruments")?;
+    for (column, column_type) in [("name", "TEXT"), ("sector", "TEXT"), ("industry", "TEXT"), ("source", "TEXT NOT NULL DEFAULT 'manual'")] {
+        if !has_column(conn, "instruments", column)? {
+            conn.execute(&format!("ALTER TABLE instruments ADD COLUMN {} {}", column, column_type), [])?;
+        }
+    }
+    conn.execute_batch("CREATE INDEX IF NOT EXISTS idx_instruments_sector ON instruments(sector)")?;
+    Ok(())
+}
+
+// Option lifecycle events. trades.event_type marks the row that closed an option position by
+// expiration, assignment or exercise (price 0, close-only in the pairing engine); option_events
+// records the events entered in the app and links the closing row to the share trade an assignment
+// or exercise delivered.
+fn migrate_option_events(conn: &Connection) -> Result<()> {
+    if !has_column(conn, "trades", "event_type")? {
+        conn.execute("ALTER TABLE trades ADD COLUMN event_type TEXT", [])?;
+    }
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS option_events (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            event_type TEXT NOT NULL,
+            option_symbol TEXT NOT NULL,
+            quantity REAL NOT NULL,
+            event_date TEXT NOT NULL,
+            option_trade_id INTEGER NOT NULL REFERENCES trades(id) ON DELETE CASCADE,
+            stock_trade_id INTEGER REFERENCES trades(id) ON DELETE SET NULL,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        );
+        CREATE INDEX IF NOT EXISTS idx_option_events_option_trade ON option_events(option_trade_id);",
+    )?;
+    Ok(())
+}
+
+// Conversion rate from the quote currency into the account currency when the fill happened, so
+// forex P&L is booked at the rate of the day instead of whatever rate was fetched last
+fn migrate_trade_fx_rates(conn: &Connection) -> Result<()> {
+    if !has_column(conn, "trades", "fx_rate")? {
+        conn.execute("ALTER TABLE trades ADD COLUMN fx_rate REAL", [])?;
+    }
+    Ok(())
+}
+
+// Specific-lot pairs chosen by the user: `quantity` of the entry fill is closed by the exit fill
+// before FIFO/LIFO matching runs. Deleting either fill drops the pair.
+fn migrate_manual_pairs(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS manual_pairs (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            entry_trade_id INTEGER NOT NULL REFERENCES trades(id) ON DELETE CASCADE,
+            exit_trade_id INTEGER NOT NULL REFERENCES trades(id) ON DELETE CASCADE,
+            quantity REAL NOT NULL,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            UNIQUE(entry_trade_id, exit_trade_id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_manual_pairs_exit ON manual_pairs(exit_trade_id);",
+    )?;
+    Ok(())
+}
+
+// Pairing method per symbol, asset class or account (paper/live) that beats the global default
+fn migrate_pairing_overrides(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS pairing_overrides (
+            scope TEXT NOT NULL CHECK (scope IN ('symbol', 'asset_class', 'account')),
+            key TEXT NOT NULL,
+            method TEXT NOT NULL CHECK (method IN ('FIFO', 'LIFO')),
+            PRIMARY KEY (scope, key)
+        );",
+    )?;
+    Ok(())
+}
+
+// Stored side/status spellings ("Buy"/"BOT" -> BUY, "FILLED" -> Filled) so queries match one value.
+// Sides that aren't a buy or a sell are left for the user to fix.
+fn migrate_canonical_side_status(conn: &Connection) -> Result<()> {
+    let side = |value: &str| Side::parse(value).map(|side| side.as_str().to_string());
+    let status = |value: &str| Status::parse(value).map(|status| status.as_str().to_string());
+    for (column, canonical) in [("side", &side as &dyn Fn(&str) -> Option<String>), ("status", &status)] {
+        let values: Vec<String> = conn
+            .prepare(&format!("SELECT DISTINCT {0} FROM trades WHERE {0} IS NOT NULL", column))?
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<_>>()?;
+        for value in values {
+            if let Some(stored) = canonical(&value).filter(|stored| *stored != value) {
+                conn.execute(&format!("UPDATE trades SET {0} = ?1 WHERE {0} = ?2", column), rusqlite::params![stored, value])?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Open/close intent reported by the broker, so pairing doesn't have to infer it
+fn migrate_trade_position_effect(conn: &Connection) -> Result<()> {
+    if !has_column(conn, "trades", "position_effect")? {
+        conn.execute("ALTER TABLE trades ADD COLUMN position_effect TEXT", [])?;
+    }
+    Ok(())
+}
+
+// Paper trades were marked by a [PAPER] tag in notes; the flag replaces it so filters don't depend
+// on free text
+fn migrate_trade_is_paper(conn: &Connection) -> Result<()> {
+    if !has_column(conn, "trades", "is_paper")? {
+        conn.execute("ALTER TABLE trades ADD COLUMN is_paper INTEGER NOT NULL DEFAULT 0", [])?;
+    }
+    let tagged = {
+        let mut stmt = conn.prepare("SELECT id, notes FROM trades WHERE UPPER(notes) LIKE '%[PAPER]%'")?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?;
+        rows.collect::<Result<Vec<_>>>()?
+    };
+    for (id, notes) in tagged {
+        let rest = strip_paper_tag(&notes).unwrap_or(Some(notes));
+        conn.execute("UPDATE trades SET is_paper = 1, notes = ?1 WHERE id = ?2", rusqlite::params![rest, id])?;
+    }
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_trades_is_paper ON trades(is_paper)", [])?;
+    Ok(())
+}
+
+// Trash for trades: deleted trades keep their row (and tags, plans, reviews) until the trash is purged
+fn migrate_trade_deleted_at(conn: &Connection) -> Result<()> {
+    if !has_column(conn, "trades", "deleted_at")? {
+        conn.execute("ALTER TABLE trades ADD COLUMN deleted_at TEXT", [])?;
+    }
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_trades_deleted_at ON trades(deleted_at)", [])?;
+    Ok(())
+}
+
+// Undo/redo stack of trade edits with before/after snapshots (see undo.rs)
+fn migrate_operation_log(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS operation_log (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            kind TEXT NOT NULL,
+            description TEXT NOT NULL,
+            trade_count INTEGER NOT NULL DEFAULT 0,
+            before_json TEXT NOT NULL,
+            after_json TEXT NOT NULL,
+            undone INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        );",
+    )
+}
+
+/// Fixed-point scale for quantities (10^8, satoshi precision). The pairing engine matches lots in these
+/// integer units so partial fills of tiny crypto sizes close out exactly.
+pub const QUANTITY_SCALE: f64 = 100_000_000.0;
+
+pub fn to_units(value: f64) -> i64 {
+    (value * QUANTITY_SCALE).round() as i64
+}
+
+pub fn from_units(units: i64) -> f64 {
+    units as f64 / QUANTITY_SCALE
+}
+
+/// Stable content hash of a fill ("h:" + FNV-1a of symbol, side, quantity, price and timestamp), the base
+/// of trades.external_id for imports without a broker fill ID. FNV rather than std's hasher because the
+/// value is stored and must not change between builds.
+pub fn trade_content_hash(symbol: &str, side: &str, quantity: f64, price: f64, timestamp: &str) -> String {
+    let key = format!(
+        "{}|{}|{}|{}|{}",
+        symbol.trim().to_uppercase(),
+        side.trim().to_uppercase(),
+        to_units(quantity),
+        price,
+        timestamp.trim()
+    );
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in key.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    format!("h:{:016x}", hash)
+}
+
+const MAX_IDLE_CONNECTIONS: usize = 4;
+
+/// The journal database, registered with `.manage()` at startup. Commands take it as
+/// `State<'_, Database>` and the reminder and broker sync threads hold a clone. Clones share the data
+/// directory (so set_data_directory moves every user at once) and the pool of idle connections kept open
+/// between commands, so dashboard loads (several commands at once) don't each open and configure the file.
+#[derive(Clone)]
+pub struct Database {
+    location: Arc<RwLock<(PathBuf, DataDirSource)>>,
+    // Keyed by path so a moved database never gets a stale connection
+    idle: Arc<Mutex<Vec<(PathBuf, Connection)>>>,
+}
+
+impl Database {
+    /// The database at the resolved data directory: --data-dir, portable folder, the folder chosen in
+    /// Settings, or the per-user app data folder, in that order.
+    pub fn open() -> Database {
+        Database {
+            location: Arc::new(RwLock::new(resolve_data_dir())),
+            idle: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Directory holding the database (and anything stored beside it).
+    pub fn data_dir(&self) -> PathBuf {
+        self.location.read().unwrap_or_else(|poisoned| poisoned.into_inner()).0.clone()
+    }
+
+    pub fn data_dir_source(&self) -> DataDirSource {
+        self.location.read().unwrap_or_else(|poisoned| poisoned.into_inner()).1
+    }
+
+    /// Path of the database file. Cheap: the directory is created when the database is initialized.
+    pub fn path(&self) -> PathBuf {
+        self.data_dir().join(DB_FILE_NAME)
+    }
+
+    /// Switch to `dir` (None = back to the default folder) and remember the choice for future runs.
+    /// The caller is responsible for the database being in place there.
+    pub fn set_data_dir(&self, dir: Option<PathBuf>) -> std::result::Result<(), String> {
+        let default_dir = default_data_dir();
+        std::fs::create_dir_all(&default_dir).map_err(|e| e.to_string())?;
+        let pointer = default_dir.join(LOCATION_POINTER);
+        let new_location = match dir {
+            Some(dir) if dir != default_dir => {
+                std::fs::write(&pointer, dir.to_string_lossy().as_bytes()).map_err(|e| e.to_string())?;
+                (dir, DataDirSource::Custom)
+            }
+            _ => {
+                if pointer.exists() {
+                    std::fs::remove_file(&pointer).map_err(|e| e.to_string())?;
+                }
+                (default_dir, DataDirSource::Default)
+            }
+        };
+        *self.location.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = new_location;
+        self.close_idle_connections();
+        Ok(())
+    }
+
+    /// A configured connection to the current database file, reusing an idle one when there is one.
+    pub fn connection(&self) -> Result<PooledConnection> {
+        let (db_path, source) = {
+            let location = self.location.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+            (location.0.join(DB_FILE_NAME), location.1)
+        };
+        let idle = {
+            let mut pool = self.idle.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            pool.iter().position(|(path, _)| *path == db_path).map(|i| pool.swap_remove(i).1)
+        };
+        let conn = match idle {
+            Some(conn) => conn,
+            None => open_connection(&db_path, source)?,
+        };
+        Ok(PooledConnection { conn: Some(conn), path: db_path, idle: Arc::clone(&self.idle) })
+    }
+
+    /// Close every idle connection, e.g. after the database moved.
+    pub fn close_idle_connections(&self) {
+        self.idle.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clear();
+    }
+}
+
+/// A connection from the pool; derefs to `Connection` and goes back to the pool when dropped.
+pub struct PooledConnection {
+    conn: Option<Connection>,
+    path: PathBuf,
+    idle: Arc<Mutex<Vec<(PathBuf, Connection)>>>,
+}
+
+impl std::ops::Deref for PooledConnection {
+    type Target = Connection;
+    fn deref(&self) -> &Connection {
+        self.conn.as_ref().expect("pooled connection is present until drop")
+    }
+}
+
+impl std::ops::DerefMut for PooledConnection {
+    fn deref_mut(&mut self) -> &mut Connection {
+        self.conn.as_mut().expect("pooled connection is present until drop")
+    }
+}
+
+impl Drop for PooledConnection {
+    fn drop(&mut self) {
+        if let Some(conn) = self.conn.take() {
+            // Never hand out a connection stuck in a transaction
+            if !conn.is_autocommit() {
+                return;
+            }
+            let mut pool = self.idle.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            if pool.len() < MAX_IDLE_CONNECTIONS {
+                pool.push((std::mem::take(&mut self.path), conn));
+            }
+        }
+    }
+}
+
+/// Open and configure a connection: busy retries, foreign keys enforced, and WAL so readers don't block
+/// behind a writer. Folders chosen in Settings are often synced (Dropbox, OneDrive); WAL keeps recent
+/// writes in a side file those tools can copy out of step with the main file, so they use a rollback journal.
+fn open_connection(db_path: &Path, source: DataDirSource) -> Result<Connection> {
+    let conn = Connection::open(db_path)?;
+    // Wait and retry instead of failing immediately with "database is locked"
+    conn.busy_handler(Some(busy_backoff))?;
+    let synced_folder = !db_path.starts_with(default_data_dir())
+        && !matches!(source, DataDirSource::CommandLine | DataDirSource::Portable);
+    let journal_mode = if synced_folder { "DELETE" } else { "WAL" };
+    conn.query_row(&format!("PRAGMA journal_mode = {}", journal_mode), [], |_| Ok(()))?;
+    conn.execute_batch("PRAGMA foreign_keys = ON; PRAGMA synchronous = NORMAL;")?;
+    Ok(conn)
+}
+
+/// A migrated in-memory journal with foreign keys enforced, as open_connection configures command
+/// connections. Shared fixture for module tests.
+#[cfg(test)]
+pub fn test_connection() -> Connection {
+    let conn = Connection::open_in_memory().expect("open in-memory database");
+    migrate_to(&conn, schema_version()).expect("migrate in-memory database");
+    conn.execute_batch("PRAGMA foreign_keys = ON").expect("enable foreign keys");
+    conn
+}
+
+/// SQLite busy handler: exponential backoff (5ms doubling, capped at 250ms) for roughly 15 seconds
+/// before giving up and letting SQLITE_BUSY through.
+fn busy_backoff(attempt: i32) -> bool {
+    if attempt >= 64 {
+        return false;
+    }
+    let delay_ms = (5u64 << attempt.clamp(0, 6)).min(250);
+    std::thread::sleep(Duration::from_millis(delay_ms));
+    true
+}
+
+// Process-wide writer lock. Every command that modifies the database holds it for the duration of the
+// write so concurrent commands (e.g. an import and an edit) run one after another instead of racing.
+static WRITE_LOCK: Mutex<()> = Mutex::new(());
+
+thread_local! {
+    static WRITE_DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Held while writing; released on drop. Re-entrant on the same thread, so a write command may
+/// call another write command without deadlocking.
+pub struct WriteGuard {
+    _guard: Option<MutexGuard<'static, ()>>,
+}
+
+impl Drop for WriteGuard {
+    fn drop(&mut self) {
+        WRITE_DEPTH.with(|d| d.set(d.get().saturating_sub(1)));
+    }
+}
+
+pub fn write_lock() -> WriteGuard {
+    let depth = WRITE_DEPTH.with(|d| {
+        let current = d.get();
+        d.set(current + 1);
+        current
+    });
+    let guard = if depth == 0 {
+        // A panic while holding the lock doesn't leave the database in a bad state, so ignore poisoning
+        Some(WRITE_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner()))
+    } else {
+        None
+    };
+    WriteGuard { _guard: guard }
+}
@@ -0,0 +1,380 @@
+// Whole-journal JSON export/import for moving between machines and app versions. Every user-data table
+// is dumped row by row (SELECT *), tagged with the schema version (PRAGMA user_version) it came from and
+// a SHA-256 checksum per table, so a file that was cut short or edited by hand is refused instead of
+// half-imported. Import rebuilds the file's schema version in a staging database, loads the rows there,
+// runs the newer migrations over them (the same upgrade path a real database takes) and only then
+// replaces the journal's tables in one transaction.
+// API keys and broker tokens in app_settings are never exported; importing keeps the ones already set.
+
+use crate::database::{self, json_to_sql, sql_to_json};
+use crate::integrity;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use serde_json::Map;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+pub const DATASET_FORMAT: &str = "tradebutler-dataset";
+
+/// User data, parents before the tables that reference them
+const DATASET_TABLES: &[&str] = &[
+    "strategies",
+    "strategy_checklists",
+    "strategy_checklist_section_descriptions",
+    "strategy_survey_metrics",
+    "strategy_calculation_presets",
+    "import_batches",
+    "trades",
+    "tags",
+    "trade_tags",
+    "templates",
+    "journal_entries",
+    "journal_entry_sections",
+    "journal_trades",
+    "journal_trade_actual_trades",
+    "journal_entry_pairs",
+    "journal_checklist_responses",
+    "emotional_states",
+    "emotion_surveys",
+    "pair_notes",
+    "notes",
+    "daily_reviews",
+    "review_sessions",
+    "trade_reviews",
+    "trade_plans",
+    "setups",
+    "checklist_items",
+    "pair_setups",
+    "trade_checklist_results",
+    "manual_pairs",
+    "option_events",
+    "funding_payments",
+    "pairing_overrides",
+    "instruments",
+    "market_events",
+    "account_balances",
+    "risk_rules",
+    "goals",
+    "reminders",
+    "saved_views",
+    "import_profiles",
+    "ai_reviews",
+    "app_settings",
+];
+
+/// Rows derived from the trades (or the undo history of the replaced journal); emptied on import and
+/// rebuilt as the app needs them
+const DERIVED_TABLES: &[&str] = &["operation_log", "pair_excursions", "rule_violations", "pattern_events", "reminder_deliveries"];
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Dataset {
+    /// Always DATASET_FORMAT
+    pub format: String,
+    /// PRAGMA user_version of the exporting database
+    pub schema_version: i32,
+    pub app_version: String,
+    pub exported_at: String,
+    /// Rows per table, each row a column -> value object
+    pub tables: BTreeMap<String, Vec<Map<String, serde_json::Value>>>,
+    /// Lowercase hex SHA-256 of each table's rows as compact JSON
+    pub checksums: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TableCount {
+    pub table: String,
+    pub rows: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DatasetImportSummary {
+    /// Schema version of the imported file
+    pub schema_version: i32,
+    pub tables: Vec<TableCount>,
+    /// Orphaned links dropped after the import (see run_integrity_check)
+    pub repaired_rows: usize,
+}
+
+/// API keys, secrets and access tokens stay on the machine they were entered on
+fn is_secret_setting(key: &str) -> bool {
+    let key = key.to_lowercase();
+    ["api_key", "secret", "token", "password"].iter().any(|marker| key.contains(marker))
+}
+
+fn table_exists(conn: &Connection, schema: &str, table: &str) -> Result<bool, String> {
+    conn.query_row(
+        &format!("SELECT COUNT(*) FROM {}.sqlite_master WHERE type = 'table' AND name = ?1", schema),
+        [table],
+        |row| row.get::<_, i64>(0),
+    )
+    .map(|count| count > 0)
+    .map_err(|e| e.to_string())
+}
+
+fn table_columns(conn: &Connection, schema: &str, table: &str) -> Result<Vec<String>, String> {
+    let mut stmt = conn
+        .prepare(&format!("SELECT name FROM {}.pragma_table_info(?1)", schema))
+        .map_err(|e| e.to_string())?;
+    let columns = stmt
+        .query_map([table], |row| row.get::<_, String>(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string());
+    columns
+}
+
+fn checksum(rows: &[Map<String, serde_json::Value>]) -> Result<String, String> {
+    let json = serde_json::to_string(rows).map_err(|e| e.to_string())?;
+    Ok(format!("{:x}", Sha256::digest(json.as_bytes())))
+}
+
+/// Every user-data table of the journal, trash included.
+pub fn export(conn: &Connection) -> Result<Dataset, String> {
+    let schema_version: i32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0)).map_err(|e| e.to_string())?;
+    let mut tables = BTreeMap::new();
+    let mut checksums = BTreeMap::new();
+    for &table in DATASET_TABLES {
+        if !table_exists(conn, "main", table)? {
+            continue;
+        }
+        let mut stmt = conn.prepare(&format!("SELECT * FROM {} ORDER BY rowid", table)).map_err(|e| e.to_string())?;
+        let columns: Vec<String> = stmt.column_names().into_iter().map(String::from).collect();
+        let mut rows = stmt
+            .query_map([], |row| {
+                let mut values = Map::new();
+                for (i, column) in columns.iter().enumerate() {
+                    values.insert(column.clone(), sql_to_json(row.get(i)?));
+                }
+                Ok(values)
+            })
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        if table == "app_settings" {
+            rows.retain(|row| !row.get("key").and_then(|k| k.as_str()).is_some_and(is_secret_setting));
+        }
+        checksums.insert(table.to_string(), checksum(&rows)?);
+        tables.insert(table.to_string(), rows);
+    }
+    Ok(Dataset {
+        format: DATASET_FORMAT.to_string(),
+        schema_version,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        exported_at: chrono::Utc::now().to_rfc3339(),
+        tables,
+        checksums,
+    })
+}
+
+/// Refuse files that aren't datasets, come from a newer schema, or whose rows don't match their checksums.
+pub fn verify(dataset: &Dataset) -> Result<(), String> {
+    if dataset.format != DATASET_FORMAT {
+        return Err(format!("Not a TradeButler dataset export (format '{}')", dataset.format));
+    }
+    if dataset.schema_version > database::schema_version() {
+        return Err(format!(
+            "This export is from a newer TradeButler (schema {}, this version reads up to {}). Update the app first.",
+            dataset.schema_version,
+            database::schema_version()
+        ));
+    }
+    for (table, rows) in &dataset.tables {
+        if !DATASET_TABLES.contains(&table.as_str()) {
+            return Err(format!("Unknown table '{}' in the export", table));
+        }
+        match dataset.checksums.get(table) {
+            None => return Err(format!("Table '{}' has no checksum", table)),
+            Some(expected) if *expected != checksum(rows)? => {
+                return Err(format!("Table '{}' doesn't match its checksum; the file is damaged or was edited", table))
+            }
+            Some(_) => {}
+        }
+    }
+    if let Some(table) = dataset.checksums.keys().find(|t| !dataset.tables.contains_key(*t)) {
+        return Err(format!("Table '{}' is missing from the export", table));
+    }
+    Ok(())
+}
+
+/// Load the dataset into a fresh database at `path`: schema as of the export, rows copied column by
+/// column, then migrated to the current schema.
+fn build_staging(path: &std::path::Path, dataset: &Dataset) -> Result<(), String> {
+    let conn = Connection::open(path).map_err(|e| e.to_string())?;
+    conn.execute_batch("PRAGMA foreign_keys = OFF").map_err(|e| e.to_string())?;
+    database::migrate_to(&conn, dataset.schema_version).map_err(|e| e.to_string())?;
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    for (table, rows) in &dataset.tables {
+        // The export replaces whatever the schema seeded (default reminders and the like)
+        tx.execute(&format!("DELETE FROM {}", table), []).map_err(|e| e.to_string())?;
+        let known = table_columns(&tx, "main", table)?;
+        for row in rows {
+            let columns: Vec<&String> = row.keys().filter(|c| known.contains(c)).collect();
+            if columns.is_empty() {
+                continue;
+            }
+            let names: Vec<String> = columns.iter().map(|c| format!("\"{}\"", c)).collect();
+            let placeholders: Vec<String> = (1..=columns.len()).map(|i| format!("?{}", i)).collect();
+            tx.execute(
+                &format!("INSERT INTO {} ({}) VALUES ({})", table, names.join(", "), placeholders.join(", ")),
+                rusqlite::params_from_iter(columns.iter().map(|c| json_to_sql(&row[c.as_str()]))),
+            )
+            .map_err(|e| format!("{}: {}", table, e))?;
+        }
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    database::migrate_to(&conn, database::schema_version()).map_err(|e| e.to_string())
+}
+
+/// Replace the journal's data with `dataset` (verify it first). The rows are staged and migrated in a
+/// temporary database next to `staging_dir`, then swapped in within one transaction on `conn`.
+pub fn import(conn: &Connection, dataset: &Dataset, staging_dir: &std::path::Path) -> Result<DatasetImportSummary, String> {
+    let staging = staging_dir.join(format!("tradebutler-import-{}.db", std::process::id()));
+    let _ = std::fs::remove_file(&staging);
+    let result = build_staging(&staging, dataset).and_then(|_| swap_in(conn, dataset, &staging));
+    let _ = std::fs::remove_file(&staging);
+    result
+}
+
+fn swap_in(conn: &Connection, dataset: &Dataset, staging: &std::path::Path) -> Result<DatasetImportSummary, String> {
+    // Foreign keys off while tables are emptied and refilled (as in init_database), so ON DELETE actions
+    // don't fire and the order of the copies doesn't matter; orphans are cleaned up by the repair below
+    conn.execute_batch("PRAGMA foreign_keys = OFF").map_err(|e| e.to_string())?;
+    let result = (|| {
+        conn.execute("ATTACH DATABASE ?1 AS staging", [staging.to_string_lossy()]).map_err(|e| e.to_string())?;
+        let copied = (|| {
+            let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+            let mut counts = Vec::new();
+            for &table in DERIVED_TABLES {
+                if table_exists(&tx, "main", table)? {
+                    tx.execute(&format!("DELETE FROM main.{}", table), []).map_err(|e| e.to_string())?;
+                }
+            }
+            for &table in DATASET_TABLES {
+                if !table_exists(&tx, "main", table)? || !table_exists(&tx, "staging", table)? {
+                    continue;
+                }
+                let columns: Vec<String> = table_columns(&tx, "staging", table)?
+                    .into_iter()
+                    .filter(|c| table_columns(&tx, "main", table).is_ok_and(|main| main.contains(c)))
+                    .map(|c| format!("\"{}\"", c))
+                    .collect();
+                let columns = columns.join(", ");
+                if table == "app_settings" {
+                    // Keys and tokens set on this machine survive; everything else comes from the file
+                    let mut stmt = tx.prepare("SELECT key FROM main.app_settings").map_err(|e| e.to_string())?;
+                    let keys = stmt
+                        .query_map([], |row| row.get::<_, String>(0))
+                        .map_err(|e| e.to_string())?
+                        .collect::<Result<Vec<_>, _>>()
+                        .map_err(|e| e.to_string())?;
+                    for key in keys.iter().filter(|k| !is_secret_setting(k)) {
+                        tx.execute("DELETE FROM main.app_settings WHERE key = ?1", [key]).map_err(|e| e.to_string())?;
+                    }
+                    tx.execute(
+                        &format!("INSERT OR REPLACE INTO main.app_settings ({0}) SELECT {0} FROM staging.app_settings", columns),
+                        [],
+                    )
+                    .map_err(|e| e.to_string())?;
+                } else {
+                    tx.execute(&format!("DELETE FROM main.{}", table), []).map_err(|e| e.to_string())?;
+                    tx.execute(&format!("INSERT INTO main.{0} ({1}) SELECT {1} FROM staging.{0}", table, columns), [])
+                        .map_err(|e| format!("{}: {}", table, e))?;
+                }
+                let rows = dataset.tables.get(table).map_or(0, Vec::len);
+                counts.push(TableCount { table: table.to_string(), rows });
+            }
+            let report = integrity::check(&tx, true)?;
+            tx.commit().map_err(|e| e.to_string())?;
+            Ok(DatasetImportSummary { schema_version: dataset.schema_version, tables: counts, repaired_rows: report.repaired_rows })
+        })();
+        let _ = conn.execute("DETACH DATABASE staging", []);
+        copied
+    })();
+    conn.execute_batch("PRAGMA foreign_keys = ON").map_err(|e| e.to_string())?;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trades(conn: &Connection) -> Vec<(i64, String, f64, Option<i64>)> {
+        conn.prepare("SELECT id, symbol, price, strategy_id FROM trades ORDER BY id")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    fn setting(conn: &Connection, key: &str) -> Option<String> {
+        conn.query_row("SELECT value FROM app_settings WHERE key = ?1", [key], |row| row.get(0)).ok()
+    }
+
+    #[test]
+    fn export_then_import_round_trips() {
+        let source = database::test_connection();
+        source
+            .execute_batch(
+                "INSERT INTO strategies (id, name) VALUES (1, 'ORB');
+                 INSERT INTO tags (id, name) VALUES (1, 'fomo');
+                 INSERT INTO trades (id, symbol, side, quantity, price, timestamp, order_type, status, strategy_id)
+                 VALUES (1, 'AAPL', 'BUY', 10, 100.25, '2024-03-05T10:00:00-05:00', 'MARKET', 'Filled', 1),
+                        (2, 'AAPL', 'SELL', 10, 104.5, '2024-03-05T11:00:00-05:00', 'MARKET', 'Filled', 1);
+                 INSERT INTO trade_tags (trade_id, tag_id) VALUES (1, 1);
+                 INSERT INTO app_settings (key, value) VALUES ('theme', 'dark'), ('openai_api_key', 'sk-source');",
+            )
+            .unwrap();
+        let target = database::test_connection();
+        target
+            .execute_batch(
+                "INSERT INTO trades (symbol, side, quantity, price, timestamp, order_type, status)
+                 VALUES ('TSLA', 'BUY', 1, 200, '2024-01-02T09:30:00-05:00', 'MARKET', 'Filled');
+                 INSERT INTO app_settings (key, value) VALUES ('theme', 'light'), ('openai_api_key', 'sk-target');",
+            )
+            .unwrap();
+
+        let exported = export(&source).unwrap();
+        assert!(exported.tables["app_settings"].iter().all(|row| row["key"] != "openai_api_key"));
+        // Through the file format, as import_all_json reads it
+        let dataset: Dataset = serde_json::from_str(&serde_json::to_string(&exported).unwrap()).unwrap();
+        verify(&dataset).unwrap();
+
+        let staging_dir = std::env::temp_dir();
+        let summary = import(&target, &dataset, &staging_dir).unwrap();
+        assert_eq!(summary.schema_version, database::schema_version());
+        assert_eq!(summary.repaired_rows, 0);
+        assert_eq!(summary.tables.iter().find(|t| t.table == "trades").map(|t| t.rows), Some(2));
+
+        assert_eq!(trades(&target), trades(&source));
+        let tagged: i64 = target.query_row("SELECT COUNT(*) FROM trade_tags WHERE trade_id = 1 AND tag_id = 1", [], |row| row.get(0)).unwrap();
+        assert_eq!(tagged, 1);
+        assert_eq!(setting(&target, "theme").as_deref(), Some("dark"));
+        assert_eq!(setting(&target, "openai_api_key").as_deref(), Some("sk-target"));
+        // Exporting the imported journal gives the same rows back
+        assert_eq!(export(&target).unwrap().checksums, exported.checksums);
+    }
+
+    #[test]
+    fn verify_rejects_edited_and_newer_exports() {
+        let conn = database::test_connection();
+        conn.execute(
+            "INSERT INTO trades (symbol, side, quantity, price, timestamp, order_type, status)
+             VALUES ('AAPL', 'BUY', 10, 100, '2024-03-05T10:00:00-05:00', 'MARKET', 'Filled')",
+            [],
+        )
+        .unwrap();
+
+        let mut edited = export(&conn).unwrap();
+        edited.tables.get_mut("trades").unwrap()[0].insert("price".to_string(), 1.0.into());
+        assert!(verify(&edited).unwrap_err().contains("checksum"));
+
+        let mut truncated = export(&conn).unwrap();
+        truncated.tables.remove("trades");
+        assert!(verify(&truncated).unwrap_err().contains("missing"));
+
+        let mut newer = export(&conn).unwrap();
+        newer.schema_version = database::schema_version() + 1;
+        assert!(verify(&newer).unwrap_err().contains("newer"));
+    }
+}
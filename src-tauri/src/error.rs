@@ -0,0 +1,124 @@
+// Error type returned by every Tauri command.
+// Serialized to the frontend as { "code": "...", "message": "..." } so the UI can branch on the
+// code (and translate it) instead of matching message text. Commands let rusqlite and io errors through
+// with `?` and return NotFound/Validation explicitly; helpers that still return Result<_, String>
+// convert with `?` into the Other variant, so don't stringify typed errors on the way out.
+
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+const BUSY_MESSAGE: &str = "The database is busy with another operation. Please try again in a moment.";
+
+#[derive(Debug, thiserror::Error)]
+pub enum TradeButlerError {
+    #[error("{}", database_message(.0))]
+    Database(#[from] rusqlite::Error),
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+    /// Broker, market data, news and update-check requests, including non-success HTTP statuses
+    #[error("{0}")]
+    Network(String),
+    /// Input that could not be read: CSV/JSON files, dates, numbers
+    #[error("{0}")]
+    Parse(String),
+    /// Input that was read but is not acceptable (missing symbol, negative quantity, ...)
+    #[error("{0}")]
+    Validation(String),
+    /// A record the command was asked to act on does not exist
+    #[error("{0}")]
+    NotFound(String),
+    #[error("{}", other_message(.0))]
+    Other(String),
+}
+
+fn is_busy_error(error: &rusqlite::Error) -> bool {
+    matches!(
+        error,
+        rusqlite::Error::SqliteFailure(e, _)
+            if matches!(e.code, rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked)
+    )
+}
+
+/// Busy/locked errors that reached us as text through a Result<_, String> helper
+fn is_busy_message(message: &str) -> bool {
+    ["database is locked", "database table is locked", "database is busy"].iter().any(|m| message.contains(m))
+}
+
+fn database_message(error: &rusqlite::Error) -> String {
+    if is_busy_error(error) {
+        BUSY_MESSAGE.to_string()
+    } else {
+        format!("Database error: {}", error)
+    }
+}
+
+fn other_message(message: &str) -> &str {
+    if is_busy_message(message) {
+        BUSY_MESSAGE
+    } else {
+        message
+    }
+}
+
+impl TradeButlerError {
+    /// Stable code the frontend keys on; never change an existing one. "busy" is a database error
+    /// from another connection holding the lock, worth retrying, whether it came back typed or as the
+    /// text of a String helper.
+    pub fn code(&self) -> &'static str {
+        match self {
+            TradeButlerError::Database(e) if is_busy_error(e) => "busy",
+            TradeButlerError::Other(message) if is_busy_message(message) => "busy",
+            TradeButlerError::Database(_) => "database",
+            TradeButlerError::Io(_) => "io",
+            TradeButlerError::Network(_) => "network",
+            TradeButlerError::Parse(_) => "parse",
+            TradeButlerError::Validation(_) => "validation",
+            TradeButlerError::NotFound(_) => "not_found",
+            TradeButlerError::Other(_) => "other",
+        }
+    }
+}
+
+impl Serialize for TradeButlerError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut error = serializer.serialize_struct("TradeButlerError", 2)?;
+        error.serialize_field("code", self.code())?;
+        error.serialize_field("message", &self.to_string())?;
+        error.end()
+    }
+}
+
+impl From<reqwest::Error> for TradeButlerError {
+    fn from(error: reqwest::Error) -> Self {
+        TradeButlerError::Network(error.to_string())
+    }
+}
+
+impl From<serde_json::Error> for TradeButlerError {
+    fn from(error: serde_json::Error) -> Self {
+        TradeButlerError::Parse(error.to_string())
+    }
+}
+
+impl From<csv::Error> for TradeButlerError {
+    fn from(error: csv::Error) -> Self {
+        TradeButlerError::Parse(error.to_string())
+    }
+}
+
+impl From<chrono::ParseError> for TradeButlerError {
+    fn from(error: chrono::ParseError) -> Self {
+        TradeButlerError::Parse(error.to_string())
+    }
+}
+
+impl From<String> for TradeButlerError {
+    fn from(message: String) -> Self {
+        TradeButlerError::Other(message)
+    }
+}
+
+impl From<&str> for TradeButlerError {
+    fn from(message: &str) -> Self {
+        TradeButlerError::Other(message.to_string())
+    }
+}
@@ -0,0 +1,244 @@
+// Spreadsheet exports of the journal: trades, closed pairs, daily P&L and per-symbol stats as rows of
+// typed cells, written out as CSV or as sheets of one Excel workbook. Numbers stay numbers (no currency
+// symbols or thousands separators in the values) so spreadsheets can sum them; in Excel money and
+// percentages get a number format instead. Timestamps are written exactly as stored.
+
+use crate::commands::{PairedTrade, TradeQueryRow};
+use rust_xlsxwriter::{Format, Workbook};
+use std::collections::{BTreeMap, HashMap};
+
+pub enum Cell {
+    Text(String),
+    Number(f64),
+    /// Currency amount, already rounded to cents
+    Money(f64),
+    /// Fraction (0.25 = 25%)
+    Percent(f64),
+    Empty,
+}
+
+impl Cell {
+    fn text(value: impl Into<String>) -> Cell {
+        Cell::Text(value.into())
+    }
+
+    fn opt_text(value: Option<&str>) -> Cell {
+        value.filter(|v| !v.is_empty()).map_or(Cell::Empty, Cell::text)
+    }
+
+    /// Money rounded to cents
+    fn money(value: f64) -> Cell {
+        Cell::Money((value * 100.0).round() / 100.0)
+    }
+
+    fn opt_money(value: Option<f64>) -> Cell {
+        value.map_or(Cell::Empty, Cell::money)
+    }
+
+    fn as_csv(&self) -> String {
+        match self {
+            Cell::Text(text) => text.clone(),
+            Cell::Number(number) | Cell::Money(number) | Cell::Percent(number) => number.to_string(),
+            Cell::Empty => String::new(),
+        }
+    }
+}
+
+pub struct Table {
+    pub headers: Vec<&'static str>,
+    pub rows: Vec<Vec<Cell>>,
+}
+
+impl Table {
+    pub fn to_csv(&self) -> Result<String, String> {
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        writer.write_record(&self.headers).map_err(|e| e.to_string())?;
+        for row in &self.rows {
+            writer.write_record(row.iter().map(Cell::as_csv)).map_err(|e| e.to_string())?;
+        }
+        let bytes = writer.into_inner().map_err(|e| e.to_string())?;
+        String::from_utf8(bytes).map_err(|e| e.to_string())
+    }
+}
+
+/// One workbook with a sheet per (name, table): bold frozen header row with filters, money as
+/// currency, columns sized to their contents.
+pub fn to_xlsx(sheets: &[(&str, &Table)]) -> Result<Vec<u8>, String> {
+    let header = Format::new().set_bold();
+    let money = Format::new().set_num_format("$#,##0.00;[Red]-$#,##0.00");
+    let percent = Format::new().set_num_format("0.0%");
+    let mut workbook = Workbook::new();
+    for (name, table) in sheets {
+        let sheet = workbook.add_worksheet();
+        sheet.set_name(*name).map_err(|e| e.to_string())?;
+        for (col, title) in table.headers.iter().enumerate() {
+            sheet.write_string_with_format(0, col as u16, *title, &header).map_err(|e| e.to_string())?;
+        }
+        for (i, row) in table.rows.iter().enumerate() {
+            let r = i as u32 + 1;
+            for (col, cell) in row.iter().enumerate() {
+                let c = col as u16;
+                match cell {
+                    Cell::Text(text) => sheet.write_string(r, c, text.as_str()),
+                    Cell::Number(number) => sheet.write_number(r, c, *number),
+                    Cell::Money(amount) => sheet.write_number_with_format(r, c, *amount, &money),
+                    Cell::Percent(fraction) => sheet.write_number_with_format(r, c, *fraction, &percent),
+                    Cell::Empty => continue,
+                }
+                .map_err(|e| e.to_string())?;
+            }
+        }
+        sheet.set_freeze_panes(1, 0).map_err(|e| e.to_string())?;
+        if !table.headers.is_empty() {
+            sheet
+                .autofilter(0, 0, table.rows.len() as u32, table.headers.len() as u16 - 1)
+                .map_err(|e| e.to_string())?;
+        }
+        sheet.autofit();
+    }
+    workbook.save_to_buffer().map_err(|e| e.to_string())
+}
+
+/// One row per fill. `strategies` maps strategy ids to names, `tags` trade ids to tag names.
+pub fn trades_table(rows: &[TradeQueryRow], strategies: &HashMap<i64, String>, tags: &HashMap<i64, Vec<String>>) -> Table {
+    let headers = vec![
+        "Trade ID", "Timestamp", "Symbol", "Side", "Quantity", "Price", "Fees", "Order Type", "Status",
+        "Account", "Strategy", "Tags", "Realized P&L", "Notes", "External ID",
+    ];
+    let rows = rows
+        .iter()
+        .map(|row| {
+            let trade = &row.trade;
+            let id = trade.id.unwrap_or_default();
+            vec![
+                Cell::Number(id as f64),
+                Cell::text(trade.timestamp.as_str()),
+                Cell::text(trade.symbol.as_str()),
+                Cell::text(trade.side.as_str()),
+                Cell::Number(trade.quantity),
+                Cell::Number(trade.price),
+                Cell::opt_money(trade.fees),
+                Cell::text(trade.order_type.as_str()),
+                Cell::text(trade.status.as_str()),
+                Cell::text(if trade.is_paper { "Paper" } else { "Live" }),
+                Cell::opt_text(trade.strategy_id.and_then(|s| strategies.get(&s)).map(String::as_str)),
+                Cell::opt_text(tags.get(&id).map(|t| t.join(", ")).as_deref()),
+                Cell::opt_money(row.realized_pnl),
+                Cell::opt_text(trade.notes.as_deref()),
+                Cell::opt_text(trade.external_id.as_deref()),
+            ]
+        })
+        .collect();
+    Table { headers, rows }
+}
+
+/// One row per closed pair (entry lot matched to an exit). `sides` maps trade ids to "BUY"/"SELL" to
+/// tell longs from shorts.
+pub fn pairs_table(pairs: &[PairedTrade], strategies: &HashMap<i64, String>, sides: &HashMap<i64, String>) -> Table {
+    let headers = vec![
+        "Symbol", "Direction", "Quantity", "Entry Time", "Entry Price", "Exit Time", "Exit Price",
+        "Gross P&L", "Fees", "Funding", "Net P&L", "Holding Period", "Session", "Strategy", "Manual Pair",
+        "Notes", "Entry Trade ID", "Exit Trade ID",
+    ];
+    let rows = pairs
+        .iter()
+        .map(|pair| {
+            let direction = match sides.get(&pair.entry_trade_id).map(String::as_str) {
+                Some("SELL") => "Short",
+                _ => "Long",
+            };
+            vec![
+                Cell::text(pair.symbol.as_str()),
+                Cell::text(direction),
+                Cell::Number(pair.quantity),
+                Cell::text(pair.entry_timestamp.as_str()),
+                Cell::Number(pair.entry_price),
+                Cell::text(pair.exit_timestamp.as_str()),
+                Cell::Number(pair.exit_price),
+                Cell::money(pair.gross_profit_loss),
+                Cell::money(pair.entry_fees + pair.exit_fees),
+                Cell::money(pair.funding),
+                Cell::money(pair.net_profit_loss),
+                Cell::text(pair.holding_period.as_str()),
+                Cell::text(pair.session.as_str()),
+                Cell::opt_text(pair.strategy_id.and_then(|s| strategies.get(&s)).map(String::as_str)),
+                Cell::text(if pair.manual { "Yes" } else { "No" }),
+                Cell::opt_text(pair.notes.as_deref()),
+                Cell::Number(pair.entry_trade_id as f64),
+                Cell::Number(pair.exit_trade_id as f64),
+            ]
+        })
+        .collect();
+    Table { headers, rows }
+}
+
+/// Realized P&L per calendar day a pair closed on (the leading YYYY-MM-DD of the exit), oldest first.
+pub fn daily_table(pairs: &[PairedTrade]) -> Table {
+    let headers = vec!["Date", "Closed Pairs", "Winners", "Losers", "Gross P&L", "Fees", "Net P&L", "Cumulative P&L"];
+    let mut days: BTreeMap<&str, Vec<&PairedTrade>> = BTreeMap::new();
+    for pair in pairs {
+        days.entry(pair.exit_timestamp.get(..10).unwrap_or(&pair.exit_timestamp)).or_default().push(pair);
+    }
+    let mut cumulative = 0.0;
+    let rows = days
+        .into_iter()
+        .map(|(date, pairs)| {
+            let net: f64 = pairs.iter().map(|p| p.net_profit_loss).sum();
+            cumulative += net;
+            vec![
+                Cell::text(date),
+                Cell::Number(pairs.len() as f64),
+                Cell::Number(pairs.iter().filter(|p| p.net_profit_loss > 0.0).count() as f64),
+                Cell::Number(pairs.iter().filter(|p| p.net_profit_loss < 0.0).count() as f64),
+                Cell::money(pairs.iter().map(|p| p.gross_profit_loss).sum()),
+                Cell::money(pairs.iter().map(|p| p.entry_fees + p.exit_fees).sum()),
+                Cell::money(net),
+                Cell::money(cumulative),
+            ]
+        })
+        .collect();
+    Table { headers, rows }
+}
+
+/// Closed-pair stats per symbol, best net P&L first.
+pub fn symbols_table(pairs: &[PairedTrade]) -> Table {
+    let headers = vec![
+        "Symbol", "Closed Pairs", "Winners", "Losers", "Win Rate", "Gross P&L", "Fees", "Net P&L",
+        "Average P&L", "Largest Win", "Largest Loss",
+    ];
+    let mut symbols: HashMap<&str, Vec<&PairedTrade>> = HashMap::new();
+    for pair in pairs {
+        symbols.entry(pair.symbol.as_str()).or_default().push(pair);
+    }
+    let mut symbols: Vec<(&str, Vec<&PairedTrade>, f64)> = symbols
+        .into_iter()
+        .map(|(symbol, pairs)| {
+            let net = pairs.iter().map(|p| p.net_profit_loss).sum();
+            (symbol, pairs, net)
+        })
+        .collect();
+    symbols.sort_by(|a, b| b.2.total_cmp(&a.2).then_with(|| a.0.cmp(b.0)));
+    let rows = symbols
+        .into_iter()
+        .map(|(symbol, pairs, net)| {
+            let count = pairs.len() as f64;
+            let winners = pairs.iter().filter(|p| p.net_profit_loss > 0.0).count() as f64;
+            let largest_win = pairs.iter().map(|p| p.net_profit_loss).filter(|pnl| *pnl > 0.0).reduce(f64::max);
+            let largest_loss = pairs.iter().map(|p| p.net_profit_loss).filter(|pnl| *pnl < 0.0).reduce(f64::min);
+            vec![
+                Cell::text(symbol),
+                Cell::Number(count),
+                Cell::Number(winners),
+                Cell::Number(pairs.iter().filter(|p| p.net_profit_loss < 0.0).count() as f64),
+                Cell::Percent(winners / count),
+                Cell::money(pairs.iter().map(|p| p.gross_profit_loss).sum()),
+                Cell::money(pairs.iter().map(|p| p.entry_fees + p.exit_fees).sum()),
+                Cell::money(net),
+                Cell::money(net / count),
+                Cell::opt_money(largest_win),
+                Cell::opt_money(largest_loss),
+            ]
+        })
+        .collect();
+    Table { headers, rows }
+}
@@ -0,0 +1,75 @@
+// Futures contract code parsing.
+// Format: ROOT + month code + 1-2 digit year, optionally prefixed with "/" (thinkorswim).
+// Examples: "ESZ5", "MNQH6", "CLF25", "/ESZ25", "M2KU5", "6EZ5"
+
+use chrono::{Datelike, NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FuturesContract {
+    pub root: String,
+    /// CME month code (F G H J K M N Q U V X Z)
+    pub month_code: String,
+    pub month: u32,
+    pub year: i32,
+    /// Estimated last trade date, YYYY-MM-DD (see `approx_expiration`)
+    pub approx_expiration: String,
+}
+
+pub(crate) const MONTH_CODES: [char; 12] = ['F', 'G', 'H', 'J', 'K', 'M', 'N', 'Q', 'U', 'V', 'X', 'Z'];
+
+// Energy contracts stop trading in the month before the contract month
+const PRIOR_MONTH_EXPIRY_ROOTS: [&str; 5] = ["CL", "MCL", "QM", "NG", "QG"];
+
+/// Parse a futures contract code. This only checks the shape, so callers should confirm the root
+/// against the futures specs before treating a symbol as a future (plain tickers like "AZ5" would parse).
+/// `today` resolves single-digit years to the nearest matching year.
+pub fn parse_futures_symbol(symbol: &str, today: NaiveDate) -> Option<FuturesContract> {
+    let code = symbol.trim().trim_start_matches('/').to_uppercase();
+    if !code.is_ascii() {
+        return None;
+    }
+    let year_digits = code.chars().rev().take_while(|c| c.is_ascii_digit()).count();
+    if !(1..=2).contains(&year_digits) || code.len() < year_digits + 2 {
+        return None;
+    }
+    let (rest, year_part) = code.split_at(code.len() - year_digits);
+    let (root, month_part) = rest.split_at(rest.len() - 1);
+    let month_code = month_part.chars().next()?;
+    let month = MONTH_CODES.iter().position(|c| *c == month_code)? as u32 + 1;
+
+    if root.is_empty()
+        || root.len() > 4
+        || !root.chars().all(|c| c.is_ascii_alphanumeric())
+        || !root.chars().any(|c| c.is_ascii_alphabetic())
+    {
+        return None;
+    }
+
+    let year_value: i32 = year_part.parse().ok()?;
+    let year = if year_digits == 2 {
+        2000 + year_value
+    } else {
+        // Single digit: the year ending in that digit within [today - 6, today + 3] (ESZ5 in 2026 = 2025, ESH9 = 2029)
+        let start = today.year() - 6;
+        (start..start + 10).find(|y| y.rem_euclid(10) == year_value)?
+    };
+
+    Some(FuturesContract {
+        root: root.to_string(),
+        month_code: month_code.to_string(),
+        month,
+        year,
+        approx_expiration: approx_expiration(root, year, month)?.format("%Y-%m-%d").to_string(),
+    })
+}
+
+/// Estimated last trade date: third Friday of the contract month (equity index, metals, rates close
+/// enough for an expiry warning); energy contracts expire around the 20th of the prior month.
+pub fn approx_expiration(root: &str, year: i32, month: u32) -> Option<NaiveDate> {
+    if PRIOR_MONTH_EXPIRY_ROOTS.contains(&root) {
+        let (y, m) = if month == 1 { (year - 1, 12) } else { (year, month - 1) };
+        return NaiveDate::from_ymd_opt(y, m, 20);
+    }
+    NaiveDate::from_weekday_of_month_opt(year, month, Weekday::Fri, 3)
+}
@@ -0,0 +1,1232 @@
+// Broker-specific CSV formats for import_trades_csv.
+// Each parser turns one broker export into `Trade` rows (oldest first); duplicate checks, the import
+// batch and [PAPER] tagging are handled by the caller like any other CSV import.
+
+use crate::database::{PositionEffect, Side, Trade};
+use crate::futures::MONTH_CODES;
+use crate::options::parse_occ_symbol;
+use chrono::NaiveDate;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+pub type CsvReader<'a> = csv::Reader<&'a [u8]>;
+
+/// Text of a CSV file and the encoding it was read as. A BOM decides (UTF-8, UTF-16LE/BE); without one
+/// the bytes are used as UTF-8 when valid, otherwise as Windows-1252 (Excel's default on Windows).
+pub fn decode_csv_bytes(bytes: &[u8]) -> (String, &'static str) {
+    if let Some((encoding, _)) = encoding_rs::Encoding::for_bom(bytes) {
+        let (text, _, _) = encoding.decode(bytes);
+        return (text.into_owned(), encoding.name());
+    }
+    match std::str::from_utf8(bytes) {
+        Ok(text) => (text.to_string(), encoding_rs::UTF_8.name()),
+        Err(_) => {
+            let (text, _, _) = encoding_rs::WINDOWS_1252.decode(bytes);
+            (text.into_owned(), encoding_rs::WINDOWS_1252.name())
+        }
+    }
+}
+
+/// Built-in broker format of a CSV, if it matches one. Some exports are recognised from the raw text
+/// because their header is not the first line.
+pub fn detect_format(csv_data: &str, headers: &csv::StringRecord) -> Option<&'static str> {
+    if let Some(format) = metatrader_format(csv_data) {
+        Some(format)
+    } else if is_thinkorswim(csv_data) {
+        Some("thinkorswim")
+    } else if is_robinhood(headers) {
+        Some("robinhood")
+    } else if is_fidelity(csv_data) {
+        Some("fidelity")
+    } else if is_tastytrade(headers) {
+        Some("tastytrade")
+    } else if is_etrade(csv_data) {
+        Some("etrade")
+    } else if is_ninjatrader(headers) {
+        Some("ninjatrader")
+    } else if is_tradovate(headers) {
+        Some("tradovate")
+    } else {
+        None
+    }
+}
+
+/// Parse a CSV in one of the formats returned by `detect_format`.
+pub fn parse_format(format: &str, csv_data: &str, reader: &mut CsvReader) -> Result<Vec<Trade>, String> {
+    match format {
+        "robinhood" => parse_robinhood(reader),
+        "thinkorswim" => parse_thinkorswim(csv_data),
+        "fidelity" => parse_fidelity(csv_data),
+        "tastytrade" => parse_tastytrade(reader),
+        "etrade" => parse_etrade(csv_data),
+        "ninjatrader" | "tradovate" => parse_futures_executions(reader),
+        "mt4" | "mt5" => parse_metatrader(csv_data),
+        _ => Err(format!("Unknown import format: {}", format)),
+    }
+}
+
+/// Deserialize every full-width row. Rows with fewer cells than the header (blank lines, the
+/// disclaimer text many brokers append) are skipped instead of failing the import.
+fn read_rows<T: serde::de::DeserializeOwned>(reader: &mut CsvReader) -> Result<Vec<T>, String> {
+    let headers = reader.headers().map_err(|e| e.to_string())?.clone();
+    let mut rows = Vec::new();
+    for result in reader.records() {
+        let record = result.map_err(|e| e.to_string())?;
+        if record.len() < headers.len() {
+            continue;
+        }
+        rows.push(record.deserialize(Some(&headers)).map_err(|e| e.to_string())?);
+    }
+    Ok(rows)
+}
+
+/// Parse a broker money/number cell: "$1,234.56", "($12.00)" and "-$5" (negative).
+pub fn parse_money(value: &str) -> Option<f64> {
+    let s = value.trim();
+    let negative = (s.starts_with('(') && s.ends_with(')')) || s.starts_with('-');
+    let cleaned: String = s.chars().filter(|c| c.is_ascii_digit() || *c == '.').collect();
+    if cleaned.is_empty() {
+        return None;
+    }
+    let n = cleaned.parse::<f64>().ok()?;
+    Some(if negative { -n } else { n })
+}
+
+/// OCC symbol (SPY251218C00600000) from its parts, as stored for option trades.
+pub fn occ_symbol(underlying: &str, expiration: NaiveDate, right: char, strike: f64) -> String {
+    format!(
+        "{}{}{}{:08}",
+        underlying.trim().to_uppercase(),
+        expiration.format("%y%m%d"),
+        right.to_ascii_uppercase(),
+        (strike * 1000.0).round() as u64
+    )
+}
+
+/// "1/15/2025" or "01/15/25" as YYYY-MM-DD
+fn parse_us_date(value: &str) -> Option<NaiveDate> {
+    let parts: Vec<&str> = value.trim().split('/').collect();
+    if parts.len() != 3 {
+        return None;
+    }
+    let year: i32 = parts[2].trim().parse().ok()?;
+    let year = if year < 100 { 2000 + year } else { year };
+    NaiveDate::from_ymd_opt(year, parts[0].trim().parse().ok()?, parts[1].trim().parse().ok()?)
+}
+
+fn iso_timestamp(date: NaiveDate, time: Option<chrono::NaiveTime>) -> String {
+    let time = time.unwrap_or_default();
+    format!("{}T{}Z", date.format("%Y-%m-%d"), time.format("%H:%M:%S"))
+}
+
+/// Zone an import source's naive times are read in until another one is chosen for it (US exchange time).
+pub const DEFAULT_IMPORT_TIMEZONE: chrono_tz::Tz = chrono_tz::America::New_York;
+
+/// Stored form of a localized timestamp: wall time plus its UTC offset ("2025-03-14T09:31:02-04:00").
+const LOCAL_TIMESTAMP_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%:z";
+
+/// A UTC instant (as broker APIs report fills) as US/Eastern exchange time with its offset.
+pub fn utc_to_exchange_time(utc: chrono::DateTime<chrono::Utc>) -> String {
+    utc.with_timezone(&DEFAULT_IMPORT_TIMEZONE).format(LOCAL_TIMESTAMP_FORMAT).to_string()
+}
+
+/// A parsed timestamp as wall time in `tz` with its UTC offset. Explicit offsets keep their instant;
+/// naive and "Z"-stamped values are the file's own wall-clock times (what the parsers produce) and are
+/// read in `tz`. Date-only, unreadable and nonexistent (DST gap) values come back unchanged.
+pub fn localize_timestamp(timestamp: &str, tz: chrono_tz::Tz) -> String {
+    let value = timestamp.trim();
+    if !value.ends_with('Z') {
+        if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(value) {
+            return dt.with_timezone(&tz).format(LOCAL_TIMESTAMP_FORMAT).to_string();
+        }
+    }
+    let naive = value.trim_end_matches('Z');
+    ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"]
+        .iter()
+        .find_map(|format| chrono::NaiveDateTime::parse_from_str(naive, format).ok())
+        .and_then(|dt| dt.and_local_timezone(tz).earliest())
+        .map_or_else(|| timestamp.to_string(), |local| local.format(LOCAL_TIMESTAMP_FORMAT).to_string())
+}
+
+// Exchange fees the broker folded into the cash amount: the gap between |amount| and qty x price
+fn fees_from_amount(amount: Option<f64>, quantity: f64, price: f64, multiplier: f64, is_buy: bool) -> Option<f64> {
+    let amount = amount?.abs();
+    let gross = quantity * price * multiplier;
+    let fees = if is_buy { amount - gross } else { gross - amount };
+    let fees = (fees * 100.0).round() / 100.0;
+    (fees > 0.0).then_some(fees)
+}
+
+// Robinhood activity export ("Activity Date", "Instrument", "Trans Code", ...)
+#[derive(Debug, Deserialize)]
+struct RobinhoodCsvRow {
+    #[serde(rename = "Activity Date")]
+    activity_date: Option<String>,
+    #[serde(rename = "Instrument")]
+    instrument: Option<String>,
+    #[serde(rename = "Description")]
+    description: Option<String>,
+    #[serde(rename = "Trans Code")]
+    trans_code: Option<String>,
+    #[serde(rename = "Quantity")]
+    quantity: Option<String>,
+    #[serde(rename = "Price")]
+    price: Option<String>,
+    #[serde(rename = "Amount")]
+    amount: Option<String>,
+}
+
+pub fn is_robinhood(headers: &csv::StringRecord) -> bool {
+    headers.iter().any(|h| h == "Trans Code") && headers.iter().any(|h| h == "Activity Date")
+}
+
+/// Buys and sells from a Robinhood activity CSV. Dividends, transfers, expirations etc. are skipped.
+/// Option rows are stored under their OCC symbol, built from the description ("SPY 12/18/2025 Call $600.00").
+pub fn parse_robinhood(reader: &mut CsvReader) -> Result<Vec<Trade>, String> {
+    let option_re = Regex::new(r"(?i)^\s*([A-Z][A-Z0-9.]*)\s+(\d{1,2}/\d{1,2}/\d{2,4})\s+(call|put)\s+\$?([\d,]+(?:\.\d+)?)")
+        .expect("valid Robinhood option regex");
+    let mut trades = Vec::new();
+    for row in read_rows::<RobinhoodCsvRow>(reader)? {
+        let code = row.trans_code.as_deref().unwrap_or("").trim().to_uppercase();
+        let side = match code.as_str() {
+            "BUY" | "BTO" | "BTC" => "BUY",
+            "SELL" | "STO" | "STC" => "SELL",
+            // Disclaimer footer, dividends (CDIV), transfers (ACH), expirations (OEXP) ...
+            _ => continue,
+        };
+        let Some(date) = row.activity_date.as_deref().and_then(parse_us_date) else { continue };
+        // Quantity can carry a suffix ("10S" on split adjustments)
+        let quantity = row.quantity.as_deref().and_then(parse_money).map(f64::abs).unwrap_or(0.0);
+        let price = row.price.as_deref().and_then(parse_money).unwrap_or(0.0);
+        if quantity <= 0.0 || price <= 0.0 {
+            continue;
+        }
+        let description = row.description.as_deref().unwrap_or("").lines().next().unwrap_or("").trim().to_string();
+
+        let option = option_re.captures(&description).and_then(|c| {
+            let expiration = parse_us_date(&c[2])?;
+            let right = if c[3].eq_ignore_ascii_case("call") { 'C' } else { 'P' };
+            Some(occ_symbol(&c[1], expiration, right, parse_money(&c[4])?))
+        });
+        let is_option = option.is_some() || matches!(code.as_str(), "BTO" | "BTC" | "STO" | "STC");
+        let symbol = match option {
+            Some(occ) => occ,
+            None => match row.instrument.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+                Some(instrument) => instrument.to_uppercase(),
+                None => continue,
+            },
+        };
+        let multiplier = if is_option { 100.0 } else { 1.0 };
+
+        trades.push(Trade {
+            id: None,
+            symbol,
+            side: side.to_string(),
+            quantity,
+            price,
+            timestamp: iso_timestamp(date, None),
+            order_type: "MARKET".to_string(),
+            status: "Filled".to_string(),
+            fees: fees_from_amount(row.amount.as_deref().and_then(parse_money), quantity, price, multiplier, side == "BUY"),
+            notes: (!description.is_empty()).then_some(description),
+            strategy_id: None,
+            external_id: None,
+            event_type: None,
+            fx_rate: None,
+            // BTO/STO open, BTC/STC close; plain Buy/Sell leave it to pairing
+            position_effect: position_effect_of_side(&code),
+            is_paper: false,
+        });
+    }
+    // Robinhood lists newest activity first
+    trades.reverse();
+    Ok(trades)
+}
+
+// thinkorswim / Schwab "Account Statement" export: several CSV sections in one file; trades are in
+// the "Account Trade History" block (",Exec Time,Spread,Side,Qty,Pos Effect,Symbol,Exp,Strike,Type,Price,...")
+const THINKORSWIM_TRADE_SECTION: &str = "Account Trade History";
+
+pub fn is_thinkorswim(csv_data: &str) -> bool {
+    csv_data.lines().any(|l| l.trim().trim_matches(',') == THINKORSWIM_TRADE_SECTION)
+}
+
+/// Fills from the Account Trade History section. Spread legs after the first row have a blank
+/// Exec Time and Order Type and inherit them from the row above. Option legs are stored under their OCC symbol.
+/// Commissions are not part of this section (they are reported under Cash Balance).
+pub fn parse_thinkorswim(csv_data: &str) -> Result<Vec<Trade>, String> {
+    let section: Vec<&str> = csv_data
+        .lines()
+        .skip_while(|l| l.trim().trim_matches(',') != THINKORSWIM_TRADE_SECTION)
+        .skip(1)
+        .take_while(|l| !l.trim().is_empty())
+        .collect();
+    if section.is_empty() {
+        return Err("The Account Trade History section is empty".to_string());
+    }
+    let text = section.join("\n");
+    let mut reader = csv::ReaderBuilder::new().has_headers(true).flexible(true).from_reader(text.as_bytes());
+    let headers = reader.headers().map_err(|e| e.to_string())?.clone();
+    let column = |name: &str| headers.iter().position(|h| h.trim().eq_ignore_ascii_case(name));
+    let (Some(time_col), Some(side_col), Some(qty_col), Some(symbol_col), Some(price_col)) =
+        (column("Exec Time"), column("Side"), column("Qty"), column("Symbol"), column("Price"))
+    else {
+        return Err("Account Trade History is missing Exec Time, Side, Qty, Symbol or Price".to_string());
+    };
+    let (exp_col, strike_col, type_col, order_type_col, effect_col) =
+        (column("Exp"), column("Strike"), column("Type"), column("Order Type"), column("Pos Effect"));
+
+    let mut trades = Vec::new();
+    let mut last_time: Option<chrono::NaiveDateTime> = None;
+    let mut last_order_type = "MARKET".to_string();
+    for result in reader.records() {
+        let record = result.map_err(|e| e.to_string())?;
+        let cell = |col: Option<usize>| col.and_then(|c| record.get(c)).map(str::trim).unwrap_or("");
+
+        let exec_time = cell(Some(time_col));
+        if !exec_time.is_empty() {
+            last_time = chrono::NaiveDateTime::parse_from_str(exec_time, "%m/%d/%y %H:%M:%S")
+                .or_else(|_| chrono::NaiveDateTime::parse_from_str(exec_time, "%m/%d/%Y %H:%M:%S"))
+                .ok();
+            last_order_type = match cell(order_type_col) {
+                "" => "MARKET".to_string(),
+                order_type => order_type.to_uppercase(),
+            };
+        }
+        let Some(time) = last_time else { continue };
+        let side = match cell(Some(side_col)).to_uppercase().as_str() {
+            "BUY" => "BUY",
+            "SELL" => "SELL",
+            _ => continue,
+        };
+        let quantity = parse_money(cell(Some(qty_col))).map(f64::abs).unwrap_or(0.0);
+        let price = parse_money(cell(Some(price_col))).unwrap_or(0.0);
+        let symbol = cell(Some(symbol_col));
+        if quantity <= 0.0 || price <= 0.0 || symbol.is_empty() {
+            continue;
+        }
+
+        let symbol = match cell(type_col).to_uppercase().as_str() {
+            kind @ ("CALL" | "PUT") => {
+                // "17 JAN 25", sometimes followed by "(Weeklys)"
+                let exp: Vec<&str> = cell(exp_col).split_whitespace().take(3).collect();
+                let expiration = NaiveDate::parse_from_str(&exp.join(" "), "%d %b %y").ok();
+                match (expiration, parse_money(cell(strike_col))) {
+                    (Some(expiration), Some(strike)) => occ_symbol(symbol, expiration, kind.chars().next().unwrap_or('C'), strike),
+                    _ => symbol.to_uppercase(),
+                }
+            }
+            // Futures are shown as "/ESH25"
+            _ => symbol.trim_start_matches('/').to_uppercase(),
+        };
+        let effect = cell(effect_col);
+
+        trades.push(Trade {
+            id: None,
+            symbol,
+            side: side.to_string(),
+            quantity,
+            price,
+            timestamp: iso_timestamp(time.date(), Some(time.time())),
+            order_type: last_order_type.clone(),
+            status: "Filled".to_string(),
+            fees: None,
+            notes: (!effect.is_empty()).then(|| effect.to_string()),
+            strategy_id: None,
+            external_id: None,
+            event_type: None,
+            fx_rate: None,
+            position_effect: PositionEffect::parse(effect).map(|effect| effect.as_str().to_string()),
+            is_paper: false,
+        });
+    }
+    trades.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+    Ok(trades)
+}
+
+// Fidelity "Accounts History" export. The header row ("Run Date,Action,Symbol,...") can be preceded by
+// account title lines, so parsing starts from the header.
+#[derive(Debug, Deserialize)]
+struct FidelityCsvRow {
+    #[serde(rename = "Run Date")]
+    run_date: Option<String>,
+    #[serde(rename = "Action")]
+    action: Option<String>,
+    #[serde(rename = "Symbol")]
+    symbol: Option<String>,
+    #[serde(rename = "Description")]
+    description: Option<String>,
+    #[serde(rename = "Quantity")]
+    quantity: Option<String>,
+    #[serde(rename = "Price ($)", alias = "Price")]
+    price: Option<String>,
+    #[serde(rename = "Commission ($)", alias = "Commission")]
+    commission: Option<String>,
+    #[serde(rename = "Fees ($)", alias = "Fees")]
+    fees: Option<String>,
+}
+
+/// Byte offset of the header line (starting with `first` and containing `other`) in exports that put
+/// account details above the table.
+fn header_offset(csv_data: &str, first: &str, other: &str) -> Option<usize> {
+    let mut offset = 0;
+    for line in csv_data.split_inclusive('\n') {
+        let trimmed = line.trim_start_matches('\u{feff}').trim_start();
+        if trimmed.starts_with(first) && trimmed.contains(other) {
+            return Some(offset);
+        }
+        offset += line.len();
+    }
+    None
+}
+
+pub fn is_fidelity(csv_data: &str) -> bool {
+    header_offset(csv_data, "Run Date", "Action").is_some()
+}
+
+/// Fidelity option symbols look like "-SPY250117C600" or " -AAPL250221P182.5"
+fn fidelity_option_symbol(symbol: &str) -> Option<String> {
+    let re = Regex::new(r"^-?([A-Z]{1,6})(\d{6})([CP])(\d+(?:\.\d+)?)$").expect("valid Fidelity option regex");
+    let c = re.captures(symbol.trim())?;
+    let expiration = NaiveDate::parse_from_str(&c[2], "%y%m%d").ok()?;
+    Some(occ_symbol(&c[1], expiration, c[3].chars().next()?, c[4].parse().ok()?))
+}
+
+/// "YOU BOUGHT" / "YOU SOLD" rows from a Fidelity history CSV. Sells have negative quantities;
+/// commission and fees are summed into `fees`. Dividends, reinvestments and transfers are skipped.
+pub fn parse_fidelity(csv_data: &str) -> Result<Vec<Trade>, String> {
+    let start = header_offset(csv_data, "Run Date", "Action").ok_or("No Fidelity header row (Run Date, Action, ...) found")?;
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(true)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(&csv_data.as_bytes()[start..]);
+
+    let mut trades = Vec::new();
+    for row in read_rows::<FidelityCsvRow>(&mut reader)? {
+        let action = row.action.as_deref().unwrap_or("").to_uppercase();
+        let side = if action.contains("YOU BOUGHT") || action.starts_with("BOUGHT") {
+            "BUY"
+        } else if action.contains("YOU SOLD") || action.starts_with("SOLD") {
+            "SELL"
+        } else {
+            continue;
+        };
+        let position_effect = if action.contains("OPENING TRANSACTION") || action.contains("SHORT SALE") || action.contains("SOLD SHORT") {
+            Some(PositionEffect::Open)
+        } else if action.contains("CLOSING TRANSACTION") {
+            Some(PositionEffect::Close)
+        } else {
+            None
+        };
+        let Some(date) = row.run_date.as_deref().and_then(parse_us_date) else { continue };
+        let quantity = row.quantity.as_deref().and_then(parse_money).map(f64::abs).unwrap_or(0.0);
+        let price = row.price.as_deref().and_then(parse_money).unwrap_or(0.0);
+        let raw_symbol = row.symbol.as_deref().unwrap_or("").trim();
+        if quantity <= 0.0 || price <= 0.0 || raw_symbol.is_empty() {
+            continue;
+        }
+        let symbol = fidelity_option_symbol(raw_symbol).unwrap_or_else(|| raw_symbol.trim_start_matches('-').to_uppercase());
+        let fees: f64 = [&row.commission, &row.fees]
+            .iter()
+            .filter_map(|f| f.as_deref().and_then(parse_money))
+            .map(f64::abs)
+            .sum();
+        let fees = (fees * 100.0).round() / 100.0;
+        let description = row.description.as_deref().unwrap_or("").trim();
+
+        trades.push(Trade {
+            id: None,
+            symbol,
+            side: side.to_string(),
+            quantity,
+            price,
+            timestamp: iso_timestamp(date, None),
+            order_type: "MARKET".to_string(),
+            status: "Filled".to_string(),
+            fees: (fees > 0.0).then_some(fees),
+            notes: (!description.is_empty()).then(|| description.to_string()),
+            strategy_id: None,
+            external_id: None,
+            event_type: None,
+            fx_rate: None,
+            position_effect: position_effect.map(|effect| effect.as_str().to_string()),
+            is_paper: false,
+        });
+    }
+    // Newest first in the export; keep same-day rows in execution order
+    trades.reverse();
+    trades.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+    Ok(trades)
+}
+
+// tastytrade transaction history ("Date,Type,Sub Type,Action,Symbol,Instrument Type,...,Value,Quantity,
+// Average Price,Commissions,Fees,Multiplier,Root Symbol,Underlying Symbol,...")
+#[derive(Debug, Deserialize)]
+struct TastytradeCsvRow {
+    #[serde(rename = "Date")]
+    date: Option<String>,
+    #[serde(rename = "Type")]
+    kind: Option<String>,
+    #[serde(rename = "Sub Type")]
+    sub_type: Option<String>,
+    #[serde(rename = "Action")]
+    action: Option<String>,
+    #[serde(rename = "Symbol")]
+    symbol: Option<String>,
+    #[serde(rename = "Instrument Type")]
+    instrument_type: Option<String>,
+    #[serde(rename = "Description")]
+    description: Option<String>,
+    #[serde(rename = "Value")]
+    value: Option<String>,
+    #[serde(rename = "Quantity")]
+    quantity: Option<String>,
+    #[serde(rename = "Average Price")]
+    average_price: Option<String>,
+    #[serde(rename = "Commissions")]
+    commissions: Option<String>,
+    #[serde(rename = "Fees")]
+    fees: Option<String>,
+    #[serde(rename = "Multiplier")]
+    multiplier: Option<String>,
+}
+
+pub fn is_tastytrade(headers: &csv::StringRecord) -> bool {
+    headers.iter().any(|h| h == "Instrument Type") && headers.iter().any(|h| h == "Underlying Symbol")
+}
+
+// "2025-01-15T10:01:03-0500" (also with milliseconds or "-05:00"); stored as the exchange-local wall time
+fn parse_tastytrade_time(value: &str) -> Option<chrono::NaiveDateTime> {
+    let value = value.trim();
+    ["%Y-%m-%dT%H:%M:%S%z", "%Y-%m-%dT%H:%M:%S%.f%z"]
+        .iter()
+        .find_map(|f| chrono::DateTime::parse_from_str(value, f).ok())
+        .or_else(|| chrono::DateTime::parse_from_rfc3339(value).ok())
+        .map(|dt| dt.naive_local())
+}
+
+/// Trade rows from a tastytrade transactions CSV, one per leg fill. "Sell to Open" / "Buy to Close"
+/// become SELL / BUY so short premium positions pair as shorts. Equity options are stored under their
+/// OCC symbol with the padding removed; the per-share price is Value / (quantity x multiplier).
+/// Receive Deliver rows for expirations, assignments and exercises become the option's price-0
+/// closing row (with `event_type` set) and, for the share leg, an ordinary fill at the strike.
+pub fn parse_tastytrade(reader: &mut CsvReader) -> Result<Vec<Trade>, String> {
+    let mut trades = Vec::new();
+    for row in read_rows::<TastytradeCsvRow>(reader)? {
+        let kind = row.kind.as_deref().unwrap_or("").trim().to_lowercase();
+        let event_type = match (kind.as_str(), row.sub_type.as_deref().unwrap_or("").trim().to_lowercase().as_str()) {
+            ("trade", _) => None,
+            ("receive deliver", "expiration") => Some("expiration"),
+            ("receive deliver", "assignment") => Some("assignment"),
+            ("receive deliver", "exercise") => Some("exercise"),
+            // Money movements and other deliveries (ACATs, symbol changes) are not fills
+            _ => continue,
+        };
+        let action = row.action.as_deref().unwrap_or("").trim().to_uppercase().replace(' ', "_");
+        let side = if action.starts_with("BUY") {
+            "BUY"
+        } else if action.starts_with("SELL") {
+            "SELL"
+        } else {
+            continue;
+        };
+        let Some(time) = row.date.as_deref().and_then(parse_tastytrade_time) else { continue };
+        let quantity = row.quantity.as_deref().and_then(parse_money).map(f64::abs).unwrap_or(0.0);
+        let raw_symbol = row.symbol.as_deref().unwrap_or("").trim();
+        if quantity <= 0.0 || raw_symbol.is_empty() {
+            continue;
+        }
+        let instrument_type = row.instrument_type.as_deref().unwrap_or("").trim().to_string();
+        let symbol = if instrument_type.eq_ignore_ascii_case("Equity Option") && parse_occ_symbol(raw_symbol).is_some() {
+            raw_symbol.replace(' ', "")
+        } else {
+            // Futures ("/ESH5") lose the slash; futures options keep tastytrade's symbol
+            raw_symbol.trim_start_matches('/').to_uppercase()
+        };
+        let multiplier = row
+            .multiplier
+            .as_deref()
+            .and_then(parse_money)
+            .filter(|m| *m > 0.0)
+            .unwrap_or(if instrument_type.eq_ignore_ascii_case("Equity Option") { 100.0 } else { 1.0 });
+        let price = row
+            .value
+            .as_deref()
+            .and_then(parse_money)
+            .map(|v| v.abs() / (quantity * multiplier))
+            .filter(|p| *p > 0.0)
+            .or_else(|| row.average_price.as_deref().and_then(parse_money).map(|p| p.abs() / multiplier))
+            .unwrap_or(0.0);
+        // The option leg of an expiration/assignment/exercise closes at 0; its share leg is a normal fill
+        let option_event = event_type.filter(|_| parse_occ_symbol(&symbol).is_some());
+        if price <= 0.0 && option_event.is_none() {
+            continue;
+        }
+        let fees: f64 = [&row.commissions, &row.fees]
+            .iter()
+            .filter_map(|f| f.as_deref().and_then(parse_money))
+            .map(f64::abs)
+            .sum();
+        let fees = (fees * 100.0).round() / 100.0;
+        let description = row.description.as_deref().unwrap_or("").trim();
+
+        trades.push(Trade {
+            id: None,
+            symbol,
+            side: side.to_string(),
+            quantity,
+            price,
+            timestamp: iso_timestamp(time.date(), Some(time.time())),
+            order_type: "LIMIT".to_string(),
+            status: "Filled".to_string(),
+            fees: (fees > 0.0).then_some(fees),
+            // Keep the open/close intent visible, e.g. "SELL_TO_OPEN: Sold 1 SPY 01/17/25 Call 600.00 @ 1.20"
+            notes: Some(if description.is_empty() { action.clone() } else { format!("{}: {}", action, description) }),
+            strategy_id: None,
+            external_id: None,
+            event_type: option_event.map(str::to_string),
+            fx_rate: None,
+            position_effect: position_effect_of_side(&action),
+            is_paper: false,
+        });
+    }
+    // Newest first in the export
+    trades.reverse();
+    trades.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+    Ok(trades)
+}
+
+// E*TRADE / Power E*TRADE transaction export: "TransactionDate,TransactionType,SecurityType,Symbol,
+// Quantity,Amount,Price,Commission,Description", usually below "For Account:" lines
+#[derive(Debug, Deserialize)]
+struct EtradeCsvRow {
+    #[serde(rename = "TransactionDate")]
+    transaction_date: Option<String>,
+    #[serde(rename = "TransactionType")]
+    transaction_type: Option<String>,
+    #[serde(rename = "SecurityType")]
+    security_type: Option<String>,
+    #[serde(rename = "Symbol")]
+    symbol: Option<String>,
+    #[serde(rename = "Quantity")]
+    quantity: Option<String>,
+    #[serde(rename = "Amount")]
+    amount: Option<String>,
+    #[serde(rename = "Price")]
+    price: Option<String>,
+    #[serde(rename = "Commission")]
+    commission: Option<String>,
+    #[serde(rename = "Description")]
+    description: Option<String>,
+}
+
+pub fn is_etrade(csv_data: &str) -> bool {
+    header_offset(csv_data, "TransactionDate", "TransactionType").is_some()
+}
+
+/// E*TRADE describes options as "SPY Jan 17 '25 $600 Call" or "CALL SPY   01/17/25   600.000"
+fn etrade_option_symbol(text: &str) -> Option<String> {
+    let named = Regex::new(r"(?i)\b([A-Z][A-Z0-9.]*)\s+([A-Z]{3})\s+(\d{1,2})\s+'(\d{2})\s+\$([\d,]+(?:\.\d+)?)\s+(call|put)\b")
+        .expect("valid E*TRADE option regex");
+    if let Some(c) = named.captures(text) {
+        let expiration = NaiveDate::parse_from_str(&format!("{} {} {}", &c[3], &c[2], &c[4]), "%d %b %y").ok()?;
+        let right = if c[6].eq_ignore_ascii_case("call") { 'C' } else { 'P' };
+        return Some(occ_symbol(&c[1], expiration, right, parse_money(&c[5])?));
+    }
+    let compact = Regex::new(r"(?i)\b(call|put)\s+([A-Z][A-Z0-9.]*)\s+(\d{1,2}/\d{1,2}/\d{2,4})\s+([\d,]+(?:\.\d+)?)")
+        .expect("valid E*TRADE option regex");
+    let c = compact.captures(text)?;
+    let right = if c[1].eq_ignore_ascii_case("call") { 'C' } else { 'P' };
+    Some(occ_symbol(&c[2], parse_us_date(&c[3])?, right, parse_money(&c[4])?))
+}
+
+/// Bought / Sold rows (including To Open / To Close and short/cover variants) from an E*TRADE export.
+/// Quantity and Amount are signed, and Commission is its own column, so it becomes `fees` and is
+/// not netted into the price. Price falls back to |Amount| less commission when the column is blank.
+pub fn parse_etrade(csv_data: &str) -> Result<Vec<Trade>, String> {
+    let start = header_offset(csv_data, "TransactionDate", "TransactionType").ok_or("No E*TRADE header row (TransactionDate, TransactionType, ...) found")?;
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(true)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(&csv_data.as_bytes()[start..]);
+
+    let mut trades = Vec::new();
+    for row in read_rows::<EtradeCsvRow>(&mut reader)? {
+        let kind = row.transaction_type.as_deref().unwrap_or("").trim().to_lowercase();
+        let side = if kind.starts_with("bought") || kind == "buy" {
+            "BUY"
+        } else if kind.starts_with("sold") || kind == "sell" {
+            "SELL"
+        } else {
+            // Dividends, interest, transfers, option expirations/assignments
+            continue;
+        };
+        let Some(date) = row.transaction_date.as_deref().and_then(parse_us_date) else { continue };
+        let quantity = row.quantity.as_deref().and_then(parse_money).map(f64::abs).unwrap_or(0.0);
+        if quantity <= 0.0 {
+            continue;
+        }
+        let description = row.description.as_deref().unwrap_or("").trim();
+        let raw_symbol = row.symbol.as_deref().unwrap_or("").trim();
+        let is_option = row.security_type.as_deref().is_some_and(|t| t.trim().eq_ignore_ascii_case("OPTN"));
+        let symbol = if is_option {
+            etrade_option_symbol(description).or_else(|| etrade_option_symbol(raw_symbol))
+        } else {
+            None
+        }
+        .unwrap_or_else(|| raw_symbol.to_uppercase());
+        if symbol.is_empty() {
+            continue;
+        }
+        let commission = row.commission.as_deref().and_then(parse_money).map(f64::abs).unwrap_or(0.0);
+        let multiplier = if parse_occ_symbol(&symbol).is_some() { 100.0 } else { 1.0 };
+        let price = row
+            .price
+            .as_deref()
+            .and_then(parse_money)
+            .map(f64::abs)
+            .filter(|p| *p > 0.0)
+            .or_else(|| {
+                // Buys: Amount = -(gross + commission); sells: Amount = gross - commission
+                let amount = row.amount.as_deref().and_then(parse_money)?.abs();
+                let gross = if side == "BUY" { amount - commission } else { amount + commission };
+                Some(gross / (quantity * multiplier)).filter(|p| *p > 0.0)
+            })
+            .unwrap_or(0.0);
+        if price <= 0.0 {
+            continue;
+        }
+
+        trades.push(Trade {
+            id: None,
+            symbol,
+            side: side.to_string(),
+            quantity,
+            price,
+            timestamp: iso_timestamp(date, None),
+            order_type: "MARKET".to_string(),
+            status: "Filled".to_string(),
+            fees: (commission > 0.0).then_some(commission),
+            notes: (!description.is_empty()).then(|| description.to_string()),
+            strategy_id: None,
+            external_id: None,
+            event_type: None,
+            fx_rate: None,
+            // "Sold Short", "Bought To Cover", "Bought To Open", ...
+            position_effect: position_effect_of_side(&kind),
+            is_paper: false,
+        });
+    }
+    // Newest first in the export; keep same-day rows in execution order
+    trades.reverse();
+    trades.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+    Ok(trades)
+}
+
+// Futures execution exports. NinjaTrader (Executions grid: "Instrument,Action,Quantity,Price,Time,ID,E/X,
+// Position,...,Commission,...") names contracts "MES 12-24"; Tradovate fills ("...,B/S,Quantity,Price,
+// Contract,Product,...") already use codes like "MESZ4".
+pub fn is_ninjatrader(headers: &csv::StringRecord) -> bool {
+    headers.iter().any(|h| h.trim() == "Instrument") && headers.iter().any(|h| h.trim() == "E/X")
+}
+
+pub fn is_tradovate(headers: &csv::StringRecord) -> bool {
+    headers.iter().any(|h| h.trim() == "B/S") && headers.iter().any(|h| h.trim() == "Contract")
+}
+
+/// "MES 12-24" -> "MESZ4", the contract code format parse_futures_symbol (and so the contract
+/// multiplier lookup) understands. Anything else is returned upper-cased without a leading "/".
+pub fn normalize_futures_contract(instrument: &str) -> String {
+    let instrument = instrument.trim().trim_start_matches('/').to_uppercase();
+    let re = Regex::new(r"^([A-Z0-9]{1,4})\s+(\d{1,2})-(\d{2})$").expect("valid NinjaTrader contract regex");
+    if let Some(c) = re.captures(&instrument) {
+        if let (Ok(month), Some(year)) = (c[2].parse::<usize>(), c[3].chars().last()) {
+            if (1..=12).contains(&month) {
+                return format!("{}{}{}", &c[1], MONTH_CODES[month - 1], year);
+            }
+        }
+    }
+    instrument
+}
+
+fn parse_execution_time(value: &str) -> Option<chrono::NaiveDateTime> {
+    let value = value.trim();
+    [
+        "%m/%d/%Y %I:%M:%S %p",
+        "%m/%d/%Y %H:%M:%S",
+        "%m/%d/%Y %H:%M",
+        "%Y-%m-%d %H:%M:%S%.f",
+        "%Y-%m-%dT%H:%M:%S%.fZ",
+    ]
+    .iter()
+    .find_map(|f| chrono::NaiveDateTime::parse_from_str(value, f).ok())
+}
+
+/// Fills from a NinjaTrader or Tradovate execution export. Prices stay in points; P&L is scaled by the
+/// futures spec of the contract root (ES = $50/pt, MES = $5/pt, ...) when trades are paired.
+pub fn parse_futures_executions(reader: &mut CsvReader) -> Result<Vec<Trade>, String> {
+    let headers = reader.headers().map_err(|e| e.to_string())?.clone();
+    let column = |names: &[&str]| headers.iter().position(|h| names.iter().any(|n| h.trim().eq_ignore_ascii_case(n)));
+    let (Some(symbol_col), Some(side_col), Some(qty_col), Some(price_col), Some(time_col)) = (
+        column(&["Instrument", "Contract"]),
+        column(&["Action", "B/S"]),
+        column(&["Quantity", "Qty", "filledQty"]),
+        column(&["Price", "avgPrice"]),
+        column(&["Time", "Timestamp", "Fill Time"]),
+    ) else {
+        return Err("The execution export is missing Instrument/Contract, Action, Quantity, Price or Time".to_string());
+    };
+    let commission_col = column(&["Commission", "commission", "Fees"]);
+    let entry_exit_col = column(&["E/X"]);
+    // Execution ID (NinjaTrader "ID", Tradovate "Fill ID") becomes the external_id used for de-duplication
+    let (fill_id_col, id_prefix) = match entry_exit_col {
+        Some(_) => (column(&["ID"]), "ninjatrader"),
+        None => (column(&["Fill ID", "_id"]), "tradovate"),
+    };
+    let account_col = column(&["Account"]);
+
+    let mut trades = Vec::new();
+    for result in reader.records() {
+        let record = result.map_err(|e| e.to_string())?;
+        let cell = |col: Option<usize>| col.and_then(|c| record.get(c)).map(str::trim).unwrap_or("");
+
+        let raw_side = cell(Some(side_col)).to_uppercase();
+        let side = match raw_side.as_str() {
+            "BUY" | "B" | "BUY TO COVER" => "BUY",
+            "SELL" | "S" | "SELL SHORT" => "SELL",
+            _ => continue,
+        };
+        let Some(time) = parse_execution_time(cell(Some(time_col))) else { continue };
+        let quantity = parse_money(cell(Some(qty_col))).map(f64::abs).unwrap_or(0.0);
+        let price = parse_money(cell(Some(price_col))).unwrap_or(0.0);
+        let symbol = normalize_futures_contract(cell(Some(symbol_col)));
+        if quantity <= 0.0 || price <= 0.0 || symbol.is_empty() {
+            continue;
+        }
+        // Commission is per fill (both sides are charged separately)
+        let fees = parse_money(cell(commission_col)).map(f64::abs).filter(|f| *f > 0.0);
+        let notes: Vec<&str> = [cell(entry_exit_col), cell(account_col)].into_iter().filter(|s| !s.is_empty()).collect();
+
+        trades.push(Trade {
+            id: None,
+            symbol,
+            side: side.to_string(),
+            quantity,
+            price,
+            timestamp: iso_timestamp(time.date(), Some(time.time())),
+            order_type: "MARKET".to_string(),
+            status: "Filled".to_string(),
+            fees,
+            notes: (!notes.is_empty()).then(|| notes.join(" ")),
+            strategy_id: None,
+            external_id: Some(cell(fill_id_col)).filter(|id| !id.is_empty()).map(|id| format!("{}:{}", id_prefix, id)),
+            event_type: None,
+            fx_rate: None,
+            position_effect: position_effect_of_side(&raw_side),
+            is_paper: false,
+        });
+    }
+    trades.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+    Ok(trades)
+}
+
+// MetaTrader account statements. MT4 "Save as (Detailed) Report" writes an HTML statement whose Closed
+// Transactions table has "Ticket, Open Time, Type, Size, Item, Price, S / L, T / P, Close Time, Price,
+// Commission, Taxes, Swap, Profit"; the MT5 history report (HTML, often UTF-16) has a Positions table
+// with "Time, Position, Symbol, Type, Volume, Price, S / L, T / P, Time, Price, Commission, Swap, Profit".
+// Tables copied out of either report into a CSV are read the same way.
+pub fn is_metatrader(csv_data: &str) -> bool {
+    let text = csv_data.to_lowercase();
+    text.contains("profit")
+        && text.contains("swap")
+        && (text.contains("s / l") || text.contains("s/l"))
+        && (text.contains("t / p") || text.contains("t/p"))
+}
+
+/// "mt4" or "mt5" when the text holds a MetaTrader closed positions table (MT4 says Item/Size,
+/// MT5 Symbol/Volume).
+pub fn metatrader_format(csv_data: &str) -> Option<&'static str> {
+    if !is_metatrader(csv_data) {
+        return None;
+    }
+    let (_, header) = metatrader_header(&metatrader_rows(csv_data))?;
+    Some(metatrader_version(&header))
+}
+
+fn metatrader_version(header: &[String]) -> &'static str {
+    if header.iter().any(|h| h == "item" || h == "ticket") {
+        "mt4"
+    } else {
+        "mt5"
+    }
+}
+
+/// Index and lower-cased cells of the closed positions header row.
+fn metatrader_header(rows: &[Vec<String>]) -> Option<(usize, Vec<String>)> {
+    rows.iter()
+        .enumerate()
+        .map(|(i, row)| (i, row.iter().map(|c| c.trim().to_lowercase()).collect::<Vec<String>>()))
+        .find(|(_, header)| {
+            let has = |names: &[&str]| header.iter().any(|h| names.contains(&h.as_str()));
+            has(&["item", "symbol"]) && has(&["size", "volume"]) && has(&["profit"])
+                && header.iter().filter(|h| h.as_str() == "price").count() >= 2
+        })
+}
+
+// Currencies whose six-letter pairs trade in standard lots of 100,000 units of the base currency
+const FOREX_CURRENCIES: [&str; 20] = [
+    "USD", "EUR", "GBP", "JPY", "CHF", "CAD", "AUD", "NZD", "SEK", "NOK",
+    "DKK", "SGD", "HKD", "MXN", "ZAR", "TRY", "PLN", "CNH", "HUF", "CZK",
+];
+const FOREX_LOT_UNITS: f64 = 100_000.0;
+
+/// Whether a MetaTrader symbol (EURUSD, eurusd, EURUSDm, GBPJPY.pro) is a currency pair.
+fn is_currency_pair(symbol: &str) -> bool {
+    let letters: String = symbol.chars().take_while(|c| c.is_ascii_alphabetic()).collect::<String>().to_uppercase();
+    letters.len() >= 6 && FOREX_CURRENCIES.contains(&&letters[..3]) && FOREX_CURRENCIES.contains(&&letters[3..6])
+}
+
+/// Table rows of an HTML report (colspan cells expanded so columns line up) or of a CSV.
+fn metatrader_rows(text: &str) -> Vec<Vec<String>> {
+    if !text.to_lowercase().contains("<tr") {
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .trim(csv::Trim::All)
+            .from_reader(text.as_bytes());
+        return reader
+            .records()
+            .filter_map(|r| r.ok())
+            .map(|record| record.iter().map(str::to_string).collect())
+            .collect();
+    }
+    let row_re = Regex::new(r"(?is)<tr[^>]*>(.*?)</tr>").expect("valid row regex");
+    let cell_re = Regex::new(r"(?is)<t[dh]([^>]*)>(.*?)</t[dh]>").expect("valid cell regex");
+    let colspan_re = Regex::new(r#"(?i)colspan\s*=\s*"?(\d+)"#).expect("valid colspan regex");
+    let tag_re = Regex::new(r"(?s)<[^>]+>").expect("valid tag regex");
+    row_re
+        .captures_iter(text)
+        .map(|row| {
+            let mut cells = Vec::new();
+            for cell in cell_re.captures_iter(&row[1]) {
+                let value = tag_re
+                    .replace_all(&cell[2], "")
+                    .replace("&nbsp;", " ")
+                    .replace("&amp;", "&")
+                    .replace("&lt;", "<")
+                    .replace("&gt;", ">")
+                    .trim()
+                    .to_string();
+                let span = colspan_re.captures(&cell[1]).and_then(|c| c[1].parse::<usize>().ok()).unwrap_or(1).max(1);
+                cells.push(value);
+                cells.extend(std::iter::repeat_n(String::new(), span - 1));
+            }
+            cells
+        })
+        .collect()
+}
+
+fn parse_metatrader_time(value: &str) -> Option<chrono::NaiveDateTime> {
+    let value = value.trim();
+    ["%Y.%m.%d %H:%M:%S", "%Y.%m.%d %H:%M", "%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M"]
+        .iter()
+        .find_map(|f| chrono::NaiveDateTime::parse_from_str(value, f).ok())
+}
+
+/// Closed positions from an MT4/MT5 statement, each as an opening and a closing fill. Quantities stay
+/// in lots (forex specs price a lot at 100,000 units) and times are the broker's server time.
+/// Commission, taxes and swap are netted into the closing fill's fees (a swap credit lowers them), and
+/// for currency pairs the statement's Profit fixes the closing fill's fx_rate, so the journal books the
+/// same account-currency P&L as the broker did. The statement is assumed to be in the account currency.
+pub fn parse_metatrader(csv_data: &str) -> Result<Vec<Trade>, String> {
+    let rows = metatrader_rows(csv_data);
+    let (header_index, header) = metatrader_header(&rows).ok_or("No MetaTrader closed positions table (Item/Symbol, Size/Volume, Price, Profit) found")?;
+    let version = metatrader_version(&header);
+    let column = |names: &[&str]| header.iter().position(|h| names.contains(&h.as_str()));
+    let time_cols: Vec<usize> = header.iter().enumerate().filter(|(_, h)| h.ends_with("time")).map(|(i, _)| i).collect();
+    let (Some(&open_time_col), Some(&close_time_col)) = (time_cols.first(), time_cols.get(1)) else {
+        return Err("The MetaTrader table needs an open and a close time column".to_string());
+    };
+    let price_cols: Vec<usize> = header.iter().enumerate().filter(|(_, h)| h.as_str() == "price").map(|(i, _)| i).collect();
+    let open_price_col = price_cols[0];
+    let close_price_col = price_cols.iter().copied().find(|&c| c > close_time_col).unwrap_or(price_cols[1]);
+    let (Some(symbol_col), Some(type_col), Some(size_col), Some(profit_col)) = (
+        column(&["item", "symbol"]),
+        column(&["type"]),
+        column(&["size", "volume"]),
+        column(&["profit"]),
+    ) else {
+        return Err("The MetaTrader table is missing Type, Size/Volume or Profit".to_string());
+    };
+    let id_col = column(&["ticket", "position"]);
+    let cost_cols: Vec<usize> = ["commission", "taxes", "fee", "swap"].iter().filter_map(|name| column(&[name])).collect();
+
+    let mut trades = Vec::new();
+    for row in &rows[header_index + 1..] {
+        let non_empty: Vec<&String> = row.iter().filter(|c| !c.trim().is_empty()).collect();
+        // Section titles ("Open Trades:", "Orders") end the closed positions table
+        if non_empty.len() == 1 && parse_money(non_empty[0]).is_none() {
+            break;
+        }
+        let cell = |col: usize| row.get(col).map(|c| c.trim()).unwrap_or("");
+        let is_buy = match cell(type_col).to_lowercase().as_str() {
+            "buy" => true,
+            "sell" => false,
+            // Balance/credit rows, cancelled pending orders
+            _ => continue,
+        };
+        let (Some(open_time), Some(close_time)) = (parse_metatrader_time(cell(open_time_col)), parse_metatrader_time(cell(close_time_col))) else {
+            continue;
+        };
+        let lots = parse_money(cell(size_col)).map(f64::abs).unwrap_or(0.0);
+        let open_price = parse_money(cell(open_price_col)).unwrap_or(0.0);
+        let close_price = parse_money(cell(close_price_col)).unwrap_or(0.0);
+        let symbol = cell(symbol_col).to_uppercase();
+        if lots <= 0.0 || open_price <= 0.0 || close_price <= 0.0 || symbol.is_empty() {
+            continue;
+        }
+        let profit = parse_money(cell(profit_col)).unwrap_or(0.0);
+        let costs: f64 = cost_cols.iter().fold(0.0, |sum, &c| sum + parse_money(cell(c)).unwrap_or(0.0));
+        let price_move = if is_buy { close_price - open_price } else { open_price - close_price };
+        let fx_rate = (is_currency_pair(&symbol) && price_move.abs() > f64::EPSILON)
+            // Rounded so a same-currency pair stores exactly 1 rather than 1.0000000000000213
+            .then(|| (profit / (price_move * lots * FOREX_LOT_UNITS) * 1e10).round() / 1e10)
+            .filter(|rate| *rate > 0.0);
+        let ticket = id_col.map(cell).filter(|id| !id.is_empty());
+        let external_id = |leg: &str| ticket.map(|id| format!("{}:{}:{}", version, id, leg));
+        let notes = ticket.map(|id| format!("{} #{}", version.to_uppercase(), id));
+        let (open_side, close_side) = if is_buy { ("BUY", "SELL") } else { ("SELL", "BUY") };
+
+        trades.push(Trade {
+            id: None,
+            symbol: symbol.clone(),
+            side: open_side.to_string(),
+            quantity: lots,
+            price: open_price,
+            timestamp: iso_timestamp(open_time.date(), Some(open_time.time())),
+            order_type: "MARKET".to_string(),
+            status: "Filled".to_string(),
+            fees: None,
+            notes: notes.clone(),
+            strategy_id: None,
+            external_id: external_id("open"),
+            event_type: None,
+            fx_rate: None,
+            position_effect: Some(PositionEffect::Open.as_str().to_string()),
+            is_paper: false,
+        });
+        trades.push(Trade {
+            id: None,
+            symbol,
+            side: close_side.to_string(),
+            quantity: lots,
+            price: close_price,
+            timestamp: iso_timestamp(close_time.date(), Some(close_time.time())),
+            order_type: "MARKET".to_string(),
+            status: "Filled".to_string(),
+            // Costs are negative in the statement
+            fees: (costs.abs() > f64::EPSILON).then_some(-costs),
+            notes,
+            strategy_id: None,
+            external_id: external_id("close"),
+            event_type: None,
+            fx_rate,
+            position_effect: Some(PositionEffect::Close.as_str().to_string()),
+            is_paper: false,
+        });
+    }
+    trades.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+    Ok(trades)
+}
+
+/// User-defined mapping of CSV columns (by header name) onto trade fields.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportColumnMapping {
+    pub symbol: String,
+    pub quantity: String,
+    pub price: String,
+    /// Date or date and time
+    pub timestamp: String,
+    /// Separate time-of-day column when the export splits date and time
+    #[serde(default)]
+    pub time: Option<String>,
+    /// Buy/sell column; when None the sign of the quantity decides (negative = sell)
+    #[serde(default)]
+    pub side: Option<String>,
+    /// Open/close column ("Open", "To Close", "O", "C"); explicit sides like "Sell Short" also set it
+    #[serde(default)]
+    pub position_effect: Option<String>,
+    /// Commission/fee columns, summed
+    #[serde(default)]
+    pub fees: Vec<String>,
+    #[serde(default)]
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub order_type: Option<String>,
+    /// chrono format of the timestamp column (e.g. "%d.%m.%Y %H:%M"); common formats are tried when None
+    #[serde(default)]
+    pub date_format: Option<String>,
+    /// Numbers are written "1.234,56" (European exports)
+    #[serde(default)]
+    pub decimal_comma: bool,
+}
+
+/// BUY / SELL from the many ways brokers write a side (B, Bot, Bought, Buy to Open, S, Sld, Sell Short, ...)
+pub fn normalize_side(value: &str) -> Option<&'static str> {
+    let v = value.trim().to_uppercase();
+    if v == "B" || v.starts_with("BUY") || v.starts_with("BOT") || v.starts_with("BOUGHT") || v == "LONG" {
+        Some("BUY")
+    } else if v == "S" || v.starts_with("SELL") || v.starts_with("SLD") || v.starts_with("SOLD") || v == "SHORT" {
+        Some("SELL")
+    } else {
+        None
+    }
+}
+
+/// Stored position effect ("open" / "close") an explicit side carries: "SELL_SHORT", "Buy to Cover",
+/// "STO", ... None for a plain buy or sell.
+pub fn position_effect_of_side(value: &str) -> Option<String> {
+    Side::parse_with_effect(value)
+        .and_then(|(_, effect)| effect)
+        .map(|effect| effect.as_str().to_string())
+}
+
+/// ISO 8601 timestamp ("2025-01-15T09:31:05Z", exchange wall time) from a date/time cell, trying
+/// `format` first and then the common layouts. Date-only values get midnight.
+pub fn parse_flexible_timestamp(value: &str, format: Option<&str>) -> Option<String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return None;
+    }
+    if let Some(format) = format.filter(|f| !f.trim().is_empty()) {
+        if let Ok(dt) = chrono::NaiveDateTime::parse_from_str(value, format) {
+            return Some(iso_timestamp(dt.date(), Some(dt.time())));
+        }
+        if let Ok(date) = NaiveDate::parse_from_str(value, format) {
+            return Some(iso_timestamp(date, None));
+        }
+    }
+    if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(value) {
+        let local = dt.naive_local();
+        return Some(iso_timestamp(local.date(), Some(local.time())));
+    }
+    let datetime = parse_execution_time(value).or_else(|| {
+        ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M", "%m/%d/%y %H:%M:%S", "%m/%d/%y %I:%M:%S %p", "%d-%b-%Y %H:%M:%S"]
+            .iter()
+            .find_map(|f| chrono::NaiveDateTime::parse_from_str(value, f).ok())
+    });
+    if let Some(dt) = datetime {
+        return Some(iso_timestamp(dt.date(), Some(dt.time())));
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .or_else(|| parse_us_date(value))
+        .or_else(|| NaiveDate::parse_from_str(value, "%d-%b-%Y").ok())
+        .map(|d| iso_timestamp(d, None))
+}
+
+/// Trades from any CSV using a column mapping. Fails on the first row that can't be read, naming the
+/// row and the reason; rows shorter than the header (footers) are skipped.
+pub fn parse_with_mapping(reader: &mut CsvReader, mapping: &ImportColumnMapping) -> Result<Vec<Trade>, String> {
+    let headers = reader.headers().map_err(|e| e.to_string())?.clone();
+    let find = |name: &str| headers.iter().position(|h| h.trim().eq_ignore_ascii_case(name.trim()));
+    let mut missing = Vec::new();
+    let mut require = |name: &str| {
+        let col = find(name);
+        if col.is_none() {
+            missing.push(name.to_string());
+        }
+        col
+    };
+    let symbol_col = require(&mapping.symbol);
+    let quantity_col = require(&mapping.quantity);
+    let price_col = require(&mapping.price);
+    let timestamp_col = require(&mapping.timestamp);
+    let time_col = mapping.time.as_deref().and_then(&mut require);
+    let side_col = mapping.side.as_deref().and_then(&mut require);
+    let fee_cols: Vec<usize> = mapping.fees.iter().filter_map(|f| require(f)).collect();
+    let notes_col = mapping.notes.as_deref().and_then(&mut require);
+    let order_type_col = mapping.order_type.as_deref().and_then(&mut require);
+    let effect_col = mapping.position_effect.as_deref().and_then(&mut require);
+    if !missing.is_empty() {
+        return Err(format!("Columns not found in the file: {}", missing.join(", ")));
+    }
+
+    let number = |value: &str| {
+        if mapping.decimal_comma {
+            parse_money(&value.replace('.', "").replace(',', "."))
+        } else {
+            parse_money(value)
+        }
+    };
+
+    let mut trades = Vec::new();
+    for (index, result) in reader.records().enumerate() {
+        let record = result.map_err(|e| e.to_string())?;
+        if record.len() < headers.len() {
+            continue;
+        }
+        // Header is line 1
+        let row = index + 2;
+        let cell = |col: Option<usize>| col.and_then(|c| record.get(c)).map(str::trim).unwrap_or("");
+
+        let symbol = cell(symbol_col).to_uppercase();
+        if symbol.is_empty() {
+            return Err(format!("Row {}: symbol is empty", row));
+        }
+        let signed_quantity = number(cell(quantity_col)).ok_or_else(|| format!("Row {}: invalid quantity '{}'", row, cell(quantity_col)))?;
+        let side = match side_col {
+            Some(_) => normalize_side(cell(side_col)).ok_or_else(|| format!("Row {}: unrecognised side '{}'", row, cell(side_col)))?,
+            None if signed_quantity < 0.0 => "SELL",
+            None => "BUY",
+        };
+        let position_effect = match cell(effect_col) {
+            "" => position_effect_of_side(cell(side_col)),
+            effect => Some(
+                PositionEffect::parse(effect)
+                    .ok_or_else(|| format!("Row {}: unrecognised position effect '{}'", row, effect))?
+                    .as_str()
+                    .to_string(),
+            ),
+        };
+        let quantity = signed_quantity.abs();
+        let price = number(cell(price_col)).map(f64::abs).ok_or_else(|| format!("Row {}: invalid price '{}'", row, cell(price_col)))?;
+        if quantity == 0.0 {
+            return Err(format!("Row {}: quantity is zero", row));
+        }
+        let raw_time = match time_col {
+            Some(_) => format!("{} {}", cell(timestamp_col), cell(time_col)),
+            None => cell(timestamp_col).to_string(),
+        };
+        let timestamp = parse_flexible_timestamp(&raw_time, mapping.date_format.as_deref())
+            .ok_or_else(|| format!("Row {}: unrecognised date/time '{}'", row, raw_time.trim()))?;
+        let fees: f64 = fee_cols.iter().filter_map(|c| number(cell(Some(*c)))).map(f64::abs).sum();
+        let notes = cell(notes_col);
+
+        trades.push(Trade {
+            id: None,
+            symbol,
+            side: side.to_string(),
+            quantity,
+            price,
+            timestamp,
+            order_type: match cell(order_type_col) {
+                "" => "MARKET".to_string(),
+                order_type => order_type.to_uppercase(),
+            },
+            status: "Filled".to_string(),
+            fees: (fees > 0.0).then_some((fees * 100.0).round() / 100.0),
+            notes: (!notes.is_empty()).then(|| notes.to_string()),
+            strategy_id: None,
+            external_id: None,
+            event_type: None,
+            fx_rate: None,
+            position_effect,
+            is_paper: false,
+        });
+    }
+    trades.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+    Ok(trades)
+}
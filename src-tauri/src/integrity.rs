@@ -0,0 +1,329 @@
+// Database health check. SQLite's own integrity_check finds file-level corruption; the reference checks
+// below find rows pointing at things that no longer exist. Those build up in databases written before
+// foreign keys were enforced (and in copies synced between installed and portable builds): emotional
+// states linked to deleted trades, trades assigned to purged strategies, pair notes for fills that are
+// gone. Repairs unlink optional references and delete link rows that mean nothing without their target;
+// trades, journal entries and emotional states themselves are never deleted.
+
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IntegrityIssue {
+    /// Stable id of the check ("orphan_emotion_trade", "missing_strategy", "foreign_key", ...)
+    pub check: String,
+    pub description: String,
+    pub count: usize,
+    pub repairable: bool,
+    pub repaired: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IntegrityReport {
+    /// PRAGMA integrity_check came back "ok"
+    pub database_ok: bool,
+    /// integrity_check output when it found corruption
+    pub database_messages: Vec<String>,
+    pub issues: Vec<IntegrityIssue>,
+    /// Rows changed by repairs
+    pub repaired_rows: usize,
+}
+
+enum Repair {
+    /// Set this column to NULL on the affected rows
+    Unlink(&'static str),
+    /// Delete the affected rows
+    Delete,
+}
+
+struct ReferenceCheck {
+    check: &'static str,
+    description: &'static str,
+    table: &'static str,
+    condition: &'static str,
+    repair: Repair,
+}
+
+const REFERENCE_CHECKS: &[ReferenceCheck] = &[
+    ReferenceCheck {
+        check: "orphan_emotion_trade",
+        description: "Emotional states linked to a trade that no longer exists",
+        table: "emotional_states",
+        condition: "trade_id IS NOT NULL AND trade_id NOT IN (SELECT id FROM trades)",
+        repair: Repair::Unlink("trade_id"),
+    },
+    ReferenceCheck {
+        check: "orphan_emotion_journal_entry",
+        description: "Emotional states linked to a journal entry that no longer exists",
+        table: "emotional_states",
+        condition: "journal_entry_id IS NOT NULL AND journal_entry_id NOT IN (SELECT id FROM journal_entries)",
+        repair: Repair::Unlink("journal_entry_id"),
+    },
+    ReferenceCheck {
+        check: "orphan_emotion_journal_trade",
+        description: "Emotional states linked to a journal trade that no longer exists",
+        table: "emotional_states",
+        condition: "journal_trade_id IS NOT NULL AND journal_trade_id NOT IN (SELECT id FROM journal_trades)",
+        repair: Repair::Unlink("journal_trade_id"),
+    },
+    ReferenceCheck {
+        check: "missing_strategy",
+        description: "Trades assigned to a strategy that no longer exists",
+        table: "trades",
+        condition: "strategy_id IS NOT NULL AND strategy_id NOT IN (SELECT id FROM strategies)",
+        repair: Repair::Unlink("strategy_id"),
+    },
+    ReferenceCheck {
+        check: "missing_journal_strategy",
+        description: "Journal entries assigned to a strategy that no longer exists",
+        table: "journal_entries",
+        condition: "strategy_id IS NOT NULL AND strategy_id NOT IN (SELECT id FROM strategies)",
+        repair: Repair::Unlink("strategy_id"),
+    },
+    ReferenceCheck {
+        check: "missing_import_batch",
+        description: "Trades pointing at an import batch that no longer exists",
+        table: "trades",
+        condition: "import_batch_id IS NOT NULL AND import_batch_id NOT IN (SELECT id FROM import_batches)",
+        repair: Repair::Unlink("import_batch_id"),
+    },
+    ReferenceCheck {
+        check: "orphan_journal_trade",
+        description: "Journal trades whose journal entry no longer exists",
+        table: "journal_trades",
+        condition: "journal_entry_id NOT IN (SELECT id FROM journal_entries)",
+        repair: Repair::Delete,
+    },
+    ReferenceCheck {
+        check: "orphan_journal_trade_link",
+        description: "Journal trade links to a missing trade or journal trade",
+        table: "journal_trade_actual_trades",
+        condition: "trade_id NOT IN (SELECT id FROM trades) OR journal_trade_id NOT IN (SELECT id FROM journal_trades)",
+        repair: Repair::Delete,
+    },
+    ReferenceCheck {
+        check: "orphan_journal_pair",
+        description: "Journal entries linked to a trade pair whose fills no longer exist",
+        table: "journal_entry_pairs",
+        condition: "entry_trade_id NOT IN (SELECT id FROM trades) OR exit_trade_id NOT IN (SELECT id FROM trades)",
+        repair: Repair::Delete,
+    },
+    ReferenceCheck {
+        check: "orphan_pair_notes",
+        description: "Pair notes for fills that no longer exist",
+        table: "pair_notes",
+        condition: "entry_trade_id NOT IN (SELECT id FROM trades) OR exit_trade_id NOT IN (SELECT id FROM trades)",
+        repair: Repair::Delete,
+    },
+    ReferenceCheck {
+        check: "orphan_pair_setup",
+        description: "Setup assignments for pairs whose fills or setup no longer exist",
+        table: "pair_setups",
+        condition: "entry_trade_id NOT IN (SELECT id FROM trades) OR exit_trade_id NOT IN (SELECT id FROM trades) OR setup_id NOT IN (SELECT id FROM setups)",
+        repair: Repair::Delete,
+    },
+    ReferenceCheck {
+        check: "broken_manual_pair",
+        description: "Manual lot pairs whose entry or exit fill no longer exists",
+        table: "manual_pairs",
+        condition: "entry_trade_id NOT IN (SELECT id FROM trades) OR exit_trade_id NOT IN (SELECT id FROM trades)",
+        repair: Repair::Delete,
+    },
+    ReferenceCheck {
+        check: "orphan_option_event",
+        description: "Option expirations/assignments whose option trade no longer exists",
+        table: "option_events",
+        condition: "option_trade_id NOT IN (SELECT id FROM trades)",
+        repair: Repair::Delete,
+    },
+    ReferenceCheck {
+        check: "orphan_trade_tag",
+        description: "Tag assignments for a missing trade or tag",
+        table: "trade_tags",
+        condition: "trade_id NOT IN (SELECT id FROM trades) OR tag_id NOT IN (SELECT id FROM tags)",
+        repair: Repair::Delete,
+    },
+    ReferenceCheck {
+        check: "orphan_trade_review",
+        description: "Reviews of trades that no longer exist",
+        table: "trade_reviews",
+        condition: "trade_id NOT IN (SELECT id FROM trades)",
+        repair: Repair::Delete,
+    },
+    ReferenceCheck {
+        check: "orphan_trade_plan",
+        description: "Trade plans for trades that no longer exist",
+        table: "trade_plans",
+        condition: "trade_id NOT IN (SELECT id FROM trades)",
+        repair: Repair::Delete,
+    },
+    ReferenceCheck {
+        check: "orphan_checklist_result",
+        description: "Checklist results for a missing trade or checklist item",
+        table: "trade_checklist_results",
+        condition: "trade_id NOT IN (SELECT id FROM trades) OR checklist_item_id NOT IN (SELECT id FROM checklist_items)",
+        repair: Repair::Delete,
+    },
+];
+
+/// Run PRAGMA integrity_check. Returns an empty list when the database file is healthy.
+pub fn database_errors(conn: &Connection) -> Result<Vec<String>, String> {
+    let mut stmt = conn.prepare("PRAGMA integrity_check").map_err(|e| e.to_string())?;
+    let messages = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(messages.into_iter().filter(|m| m != "ok").collect())
+}
+
+/// (setup id, all screenshot paths, missing paths)
+type MissingScreenshots = (i64, Vec<String>, Vec<String>);
+
+/// Setups whose screenshot paths point at files that are gone
+fn missing_screenshots(conn: &Connection) -> Result<Vec<MissingScreenshots>, String> {
+    let mut stmt = conn.prepare("SELECT id, screenshots FROM setups").map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(rows
+        .into_iter()
+        .filter_map(|(id, json)| {
+            let paths: Vec<String> = serde_json::from_str(&json).unwrap_or_default();
+            let missing: Vec<String> = paths.iter().filter(|p| !std::path::Path::new(p).exists()).cloned().collect();
+            (!missing.is_empty()).then_some((id, paths, missing))
+        })
+        .collect())
+}
+
+/// Check the database and, with `repair`, fix every repairable issue. Run repairs inside a transaction.
+pub fn check(conn: &Connection, repair: bool) -> Result<IntegrityReport, String> {
+    let database_messages = database_errors(conn)?;
+    let mut issues = Vec::new();
+    let mut repaired_rows = 0;
+
+    for check in REFERENCE_CHECKS {
+        let count: i64 = conn
+            .query_row(&format!("SELECT COUNT(*) FROM {} WHERE {}", check.table, check.condition), [], |row| row.get(0))
+            .map_err(|e| format!("{}: {}", check.check, e))?;
+        if count == 0 {
+            continue;
+        }
+        if repair {
+            let sql = match check.repair {
+                Repair::Unlink(column) => format!("UPDATE {} SET {} = NULL WHERE {}", check.table, column, check.condition),
+                Repair::Delete => format!("DELETE FROM {} WHERE {}", check.table, check.condition),
+            };
+            repaired_rows += conn.execute(&sql, []).map_err(|e| format!("{}: {}", check.check, e))?;
+        }
+        issues.push(IntegrityIssue {
+            check: check.check.to_string(),
+            description: check.description.to_string(),
+            count: count as usize,
+            repairable: true,
+            repaired: repair,
+        });
+    }
+
+    let screenshots = missing_screenshots(conn)?;
+    if !screenshots.is_empty() {
+        let count = screenshots.iter().map(|(_, _, missing)| missing.len()).sum();
+        if repair {
+            for (id, paths, missing) in &screenshots {
+                let kept: Vec<&String> = paths.iter().filter(|p| !missing.contains(p)).collect();
+                let json = serde_json::to_string(&kept).map_err(|e| e.to_string())?;
+                repaired_rows += conn
+                    .execute("UPDATE setups SET screenshots = ?1 WHERE id = ?2", params![json, id])
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+        issues.push(IntegrityIssue {
+            check: "missing_screenshot".to_string(),
+            description: "Setup screenshots whose image file no longer exists".to_string(),
+            count,
+            repairable: true,
+            repaired: repair,
+        });
+    }
+
+    // Declared foreign keys violated in tables the checks above don't cover
+    let violations = {
+        let mut stmt = conn.prepare("PRAGMA foreign_key_check").map_err(|e| e.to_string())?;
+        let tables = stmt
+            .query_map([], |row| row.get::<_, String>(0))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        let mut counts: std::collections::BTreeMap<String, usize> = std::collections::BTreeMap::new();
+        for table in tables {
+            if !REFERENCE_CHECKS.iter().any(|check| check.table == table) {
+                *counts.entry(table).or_default() += 1;
+            }
+        }
+        counts
+    };
+    for (table, count) in violations {
+        issues.push(IntegrityIssue {
+            check: "foreign_key".to_string(),
+            description: format!("Rows in {} referencing a missing parent row", table),
+            count,
+            repairable: false,
+            repaired: false,
+        });
+    }
+
+    Ok(IntegrityReport {
+        database_ok: database_messages.is_empty(),
+        database_messages,
+        issues,
+        repaired_rows,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database;
+
+    #[test]
+    fn repair_drops_orphaned_tags_and_unlinks_missing_strategies() {
+        let conn = database::test_connection();
+        conn.execute_batch(
+            "PRAGMA foreign_keys = OFF;
+             INSERT INTO strategies (id, name) VALUES (1, 'ORB');
+             INSERT INTO tags (id, name) VALUES (1, 'fomo');
+             INSERT INTO trades (id, symbol, side, quantity, price, timestamp, order_type, status, strategy_id)
+             VALUES (1, 'AAPL', 'BUY', 10, 100, '2024-03-05T10:00:00-05:00', 'MARKET', 'Filled', 1),
+                    (2, 'MSFT', 'BUY', 5, 400, '2024-03-05T10:05:00-05:00', 'MARKET', 'Filled', 99);
+             INSERT INTO trade_tags (trade_id, tag_id) VALUES (1, 1), (1, 42), (77, 1);
+             PRAGMA foreign_keys = ON;",
+        )
+        .unwrap();
+
+        let report = check(&conn, false).unwrap();
+        let count = |report: &IntegrityReport, name: &str| report.issues.iter().find(|i| i.check == name).map_or(0, |i| i.count);
+        assert_eq!(count(&report, "orphan_trade_tag"), 2);
+        assert_eq!(count(&report, "missing_strategy"), 1);
+        assert_eq!(report.repaired_rows, 0);
+
+        let report = check(&conn, true).unwrap();
+        assert_eq!(report.repaired_rows, 3);
+        let tags: Vec<(i64, i64)> = conn
+            .prepare("SELECT trade_id, tag_id FROM trade_tags")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(tags, vec![(1, 1)]);
+        let strategy_of = |id: i64| conn.query_row("SELECT strategy_id FROM trades WHERE id = ?1", [id], |row| row.get::<_, Option<i64>>(0)).unwrap();
+        assert_eq!(strategy_of(1), Some(1));
+        assert_eq!(strategy_of(2), None);
+
+        let report = check(&conn, false).unwrap();
+        assert!(report.database_ok);
+        assert!(report.issues.is_empty());
+    }
+}
@@ -2,35 +2,134 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 mod database;
+mod error;
+mod validation;
 mod commands;
+mod options;
+mod futures;
+mod ai;
+mod ocr;
+mod importers;
+mod nlquery;
+mod reminders;
+mod brokers;
+mod backup;
+mod metrics;
+mod marketdata;
+mod calendar;
+mod tax;
+mod undo;
+mod integrity;
+mod export;
+mod dataset;
+
+use tauri::Manager;
 
 fn main() {
     tauri::Builder::default()
         .setup(|app| {
-            // Initialize database on app startup
+            // Initialize database on app startup. The location (app data, portable folder or
+            // --data-dir) is resolved once here; commands take it as State<Database> and the
+            // background threads get their own handle.
             let app_handle = app.handle();
-            let db_dir = app_handle
-                .path_resolver()
-                .app_data_dir()
-                .expect("Failed to get app data directory");
-            
-            // Create directory if it doesn't exist
-            std::fs::create_dir_all(&db_dir).expect("Failed to create app data directory");
+            let db = database::Database::open();
+            database::init_database(&db.path(), db.data_dir_source()).expect("Failed to initialize database");
+            app.manage(db.clone());
             
-            let db_path = db_dir.join("tradebutler.db");
-            database::init_database(&db_path).expect("Failed to initialize database");
+            // Start the reminder scheduler (fires OS notifications for due reminders)
+            reminders::start_scheduler(app_handle.clone(), db.clone());
+
+            // Start background broker API syncs (only runs brokers with an interval configured)
+            brokers::start_sync_scheduler(db);
             
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             commands::import_trades_csv,
+            commands::import_trades_file,
+            commands::cancel_import,
+            commands::preview_import,
+            commands::get_import_timezone,
+            commands::set_import_timezone,
+            commands::normalize_trade_timestamps,
+            commands::get_import_history,
+            commands::rollback_import,
+            commands::preview_csv_headers,
+            commands::import_with_mapping,
+            commands::get_import_profiles,
+            commands::save_import_profile,
+            commands::delete_import_profile,
+            commands::get_market_data_settings,
+            commands::save_market_data_settings,
+            commands::get_quotes,
+            commands::backfill_excursions,
+            commands::get_excursion_report,
+            commands::get_alpaca_settings,
+            commands::save_alpaca_settings,
+            commands::sync_alpaca_account,
+            commands::get_tradier_settings,
+            commands::save_tradier_settings,
+            commands::sync_tradier,
+            commands::preview_screenshot_import,
+            commands::import_screenshot_fills,
+            commands::get_tesseract_path,
+            commands::set_tesseract_path,
             commands::add_trade_manual,
+            commands::create_trade,
+            commands::parse_quick_entry,
+            commands::parse_option_symbol,
+            commands::get_instrument_specs,
+            commands::save_instrument_spec,
+            commands::delete_instrument_spec,
+            commands::fetch_instrument_profiles,
+            commands::get_nonstandard_option_roots,
+            commands::set_trade_asset_class,
+            commands::reclassify_asset_classes,
+            commands::get_asset_class_breakdown,
+            commands::get_classification_rollups,
+            commands::parse_futures_contract,
+            commands::get_futures_root_stats,
+            commands::get_expiring_futures,
+            commands::get_account_currency,
+            commands::set_account_currency,
+            commands::refresh_fx_rates,
+            commands::backfill_trade_fx_rates,
+            commands::get_manual_pairs,
+            commands::set_manual_pair,
+            commands::clear_manual_pair,
+            commands::get_default_pairing_method,
+            commands::set_default_pairing_method,
+            commands::get_pairing_overrides,
+            commands::set_pairing_override,
+            commands::clear_pairing_override,
+            commands::get_wash_sales,
+            commands::export_tax_report,
+            commands::get_forex_stats,
+            commands::add_funding_payment,
+            commands::get_funding_payments,
+            commands::delete_funding_payment,
+            commands::import_funding_csv,
+            commands::get_funding_summary,
+            commands::find_similar_trades,
+            commands::query_journal,
+            commands::get_ai_settings,
+            commands::save_ai_settings,
+            commands::analyze_period,
+            commands::get_ai_reviews,
+            commands::delete_ai_review,
+            commands::draft_trade_review,
+            commands::set_journal_entry_draft,
             commands::get_trades,
             commands::get_trades_with_pairing,
             commands::get_position_groups,
             commands::get_metrics,
             commands::get_daily_pnl,
             commands::get_paired_trades,
+            commands::query_trades,
+            commands::get_saved_views,
+            commands::save_view,
+            commands::delete_saved_view,
+            commands::run_saved_view,
             commands::get_symbol_pnl,
             commands::add_emotional_state,
             commands::get_emotional_states,
@@ -45,26 +144,122 @@ fn main() {
             commands::get_all_emotion_surveys,
             commands::get_trade_by_id,
             commands::update_trade,
+            commands::set_trades_paper,
             commands::delete_trade,
             commands::create_strategy,
             commands::get_strategies,
             commands::update_strategy,
             commands::update_strategy_order,
             commands::delete_strategy,
+            commands::get_deleted_strategies,
+            commands::restore_strategy,
+            commands::purge_strategy,
+            commands::merge_strategies,
             commands::get_strategy_associated_records,
             commands::update_trade_strategy,
+            commands::get_tags,
+            commands::create_tag,
+            commands::update_tag,
+            commands::delete_tag,
+            commands::get_trade_tags,
+            commands::set_trade_tags,
+            commands::get_tag_performance,
+            commands::set_trade_review,
+            commands::get_trade_review,
+            commands::get_unreviewed_trades,
+            commands::get_setups,
+            commands::save_setup,
+            commands::delete_setup,
+            commands::set_pair_setup,
+            commands::get_pair_setups,
+            commands::get_setup_performance,
+            commands::get_checklist_items,
+            commands::set_strategy_checklist_items,
+            commands::record_trade_checklist,
+            commands::get_trade_checklist,
+            commands::set_trade_plan,
+            commands::get_trade_plan,
+            commands::delete_trade_plan,
+            commands::get_pair_r_multiples,
+            commands::get_default_risk_pct,
+            commands::set_default_risk_pct,
+            commands::calculate_position_size,
+            commands::set_trade_intended_price,
+            commands::get_slippage_report,
+            commands::get_templates,
+            commands::save_template,
+            commands::delete_template,
+            commands::create_entry_from_template,
+            commands::get_journal_entry_sections,
+            commands::update_journal_entry_section,
+            commands::get_notes,
+            commands::create_note,
+            commands::update_note,
+            commands::update_note_order,
+            commands::delete_note,
+            commands::upsert_daily_review,
+            commands::get_daily_reviews,
+            commands::delete_daily_review,
             commands::get_top_symbols,
             commands::get_strategy_performance,
             commands::get_recent_trades,
             commands::get_paired_trades_by_strategy,
             commands::clear_all_trades,
+            commands::bulk_update_trades,
+            commands::bulk_delete_trades,
+            commands::get_deleted_trades,
+            commands::restore_trade,
+            commands::purge_trash,
+            commands::undo_last_operation,
+            commands::redo,
+            commands::get_operation_log,
             commands::fetch_chart_data,
             commands::save_pair_notes,
             commands::get_evaluation_metrics,
             commands::get_equity_curve,
             commands::get_equity_curve_from_trades,
+            commands::get_drawdown_series,
+            commands::get_pnl_calendar,
+            commands::get_periodic_pnl,
+            commands::get_analysis_timezone,
+            commands::set_analysis_timezone,
+            commands::get_time_of_day_stats,
+            commands::get_session_stats,
             commands::get_distribution_concentration,
+            commands::get_pnl_distribution,
             commands::get_tilt_metric,
+            commands::detect_behavior_patterns,
+            commands::get_pattern_events,
+            commands::dismiss_pattern_event,
+            commands::get_behavior_report,
+            commands::get_risk_rules,
+            commands::set_risk_rule,
+            commands::delete_risk_rule,
+            commands::evaluate_risk_rules,
+            commands::get_rule_violations,
+            commands::get_tilt_report,
+            commands::get_emotion_performance,
+            commands::get_mood_timeline,
+            commands::get_mood_streaks,
+            commands::set_account_balance,
+            commands::get_account_balances,
+            commands::delete_account_balance,
+            commands::get_risk_free_rate,
+            commands::set_risk_free_rate,
+            commands::get_dte_stats,
+            commands::get_option_greeks,
+            commands::set_option_greeks,
+            commands::estimate_option_greeks,
+            commands::get_delta_bucket_stats,
+            commands::get_premium_capture,
+            commands::get_options_seller_metrics,
+            commands::get_moneyness_stats,
+            commands::get_options_stats,
+            commands::record_option_event,
+            commands::get_option_events,
+            commands::delete_option_event,
+            commands::close_expired_options,
+            commands::get_option_campaigns,
             commands::fetch_stock_quote,
             commands::get_strategy_checklist,
             commands::get_strategy_checklist_section_descriptions,
@@ -99,6 +294,16 @@ fn main() {
             commands::get_journal_trade_actual_trade_ids,
             commands::save_journal_trade_actual_trades,
             commands::get_journal_trade_performance,
+            commands::start_review_session,
+            commands::end_review_session,
+            commands::get_review_sessions,
+            commands::delete_review_session,
+            commands::get_review_consistency,
+            commands::save_goal,
+            commands::get_goals,
+            commands::delete_goal,
+            commands::evaluate_goals,
+            commands::get_goal_history,
             commands::get_strategy_checklist_item_metrics,
             commands::get_strategy_checklist_item_metrics_by_outcome,
             commands::save_journal_checklist_responses,
@@ -107,9 +312,24 @@ fn main() {
             commands::set_journal_entry_pairs,
             commands::get_journal_entries_for_pair,
             commands::get_all_symbols,
+            commands::get_reminders,
+            commands::save_reminder,
+            commands::delete_reminder,
+            commands::get_reminder_deliveries,
             commands::clear_all_data,
+            commands::export_trades_csv,
+            commands::export_paired_trades_csv,
+            commands::export_xlsx,
             commands::export_data,
             commands::import_data,
+            commands::export_all_json,
+            commands::import_all_json,
+            commands::get_data_location,
+            commands::set_data_directory,
+            commands::run_integrity_check,
+            commands::encrypt_backup,
+            commands::decrypt_backup,
+            commands::is_encrypted_backup,
             commands::get_app_version,
             commands::check_version,
             commands::download_portable_update,
@@ -122,6 +342,12 @@ fn main() {
             commands::fetch_calendar_events_batch,
             commands::get_economic_calendar,
             commands::get_economic_calendar_range,
+            commands::get_market_events,
+            commands::save_market_event,
+            commands::delete_market_event,
+            commands::import_market_events_csv,
+            commands::sync_market_events,
+            commands::get_event_performance,
             // Finnhub API commands
             commands::test_finnhub_connection,
             commands::fetch_finnhub_earnings,
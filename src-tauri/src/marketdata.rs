@@ -0,0 +1,704 @@
+// Market data: latest quotes from a configurable provider (Yahoo Finance without a key, Polygon or
+// Alpha Vantage with one). The provider and API keys live in app_settings; every quote fetched is
+// written to the quote_cache table so repeated lookups within the cache window don't hit the network.
+// Historical OHLC bars (for MAE/MFE) are fetched the same way and kept in price_bars.
+// Ticker profiles (name, sector, industry, exchange) fill in records in the instruments table.
+
+use crate::commands::get_app_setting;
+use chrono::{DateTime, Datelike, Utc};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+pub const MARKET_DATA_PROVIDER: &str = "market_data_provider";
+pub const POLYGON_API_KEY: &str = "polygon_api_key";
+pub const ALPHA_VANTAGE_API_KEY: &str = "alpha_vantage_api_key";
+pub const QUOTE_CACHE_SECONDS: &str = "quote_cache_seconds";
+
+pub const DEFAULT_QUOTE_CACHE_SECONDS: i64 = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Provider {
+    Yahoo,
+    Polygon,
+    AlphaVantage,
+}
+
+impl Provider {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Provider::Yahoo => "yahoo",
+            Provider::Polygon => "polygon",
+            Provider::AlphaVantage => "alpha_vantage",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Provider, String> {
+        match value.trim().to_lowercase().replace(['-', ' '], "_").as_str() {
+            "yahoo" | "" => Ok(Provider::Yahoo),
+            "polygon" => Ok(Provider::Polygon),
+            "alpha_vantage" | "alphavantage" => Ok(Provider::AlphaVantage),
+            other => Err(format!("Unknown market data provider '{}' (expected yahoo, polygon or alpha_vantage)", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MarketDataConfig {
+    pub provider: Provider,
+    pub api_key: Option<String>,
+}
+
+impl MarketDataConfig {
+    /// Selected provider and its key, or an error when a keyed provider has no key stored.
+    pub fn load(conn: &Connection) -> Result<MarketDataConfig, String> {
+        let provider = Provider::parse(&get_app_setting(conn, MARKET_DATA_PROVIDER).unwrap_or_default())?;
+        let key_setting = match provider {
+            Provider::Yahoo => return Ok(MarketDataConfig { provider, api_key: None }),
+            Provider::Polygon => POLYGON_API_KEY,
+            Provider::AlphaVantage => ALPHA_VANTAGE_API_KEY,
+        };
+        match get_app_setting(conn, key_setting).filter(|k| !k.trim().is_empty()) {
+            Some(key) => Ok(MarketDataConfig { provider, api_key: Some(key.trim().to_string()) }),
+            None => Err(format!("No {} API key configured. Add it in Settings > Market Data.", provider.as_str())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Quote {
+    pub symbol: String,
+    pub price: f64,
+    pub open: Option<f64>,
+    pub previous_close: Option<f64>,
+    /// price - previous_close, and the same as a percentage of previous_close
+    pub change: Option<f64>,
+    pub change_pct: Option<f64>,
+    pub currency: Option<String>,
+    pub provider: String,
+    /// Time of the last trade or bar behind the price (RFC 3339, UTC) when the provider reports it;
+    /// Alpha Vantage only gives the trading day (YYYY-MM-DD)
+    pub quoted_at: Option<String>,
+    pub fetched_at: String,
+    /// Served from quote_cache instead of a fresh request
+    pub cached: bool,
+}
+
+impl Quote {
+    fn new(symbol: &str, price: f64, open: Option<f64>, previous_close: Option<f64>, provider: Provider) -> Quote {
+        let change = previous_close.map(|prev| price - prev);
+        Quote {
+            symbol: symbol.to_string(),
+            price,
+            open,
+            previous_close,
+            change,
+            change_pct: previous_close.filter(|prev| *prev != 0.0).map(|prev| (price - prev) / prev * 100.0),
+            currency: None,
+            provider: provider.as_str().to_string(),
+            quoted_at: None,
+            fetched_at: Utc::now().to_rfc3339(),
+            cached: false,
+        }
+    }
+}
+
+fn http_client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")
+        .timeout(Duration::from_secs(20))
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))
+}
+
+fn from_unix(seconds: i64) -> Option<String> {
+    DateTime::<Utc>::from_timestamp(seconds, 0).map(|dt| dt.to_rfc3339())
+}
+
+/// Latest quote for `symbol` from the configured provider.
+pub async fn fetch_quote(config: &MarketDataConfig, symbol: &str) -> Result<Quote, String> {
+    let client = http_client()?;
+    match config.provider {
+        Provider::Yahoo => fetch_yahoo(&client, symbol).await,
+        Provider::Polygon => fetch_polygon(&client, symbol, config.api_key.as_deref().unwrap_or_default()).await,
+        Provider::AlphaVantage => fetch_alpha_vantage(&client, symbol, config.api_key.as_deref().unwrap_or_default()).await,
+    }
+}
+
+async fn fetch_yahoo(client: &reqwest::Client, symbol: &str) -> Result<Quote, String> {
+    let url = format!("https://query1.finance.yahoo.com/v8/finance/chart/{}?interval=1d&range=1d", symbol);
+    let response = client
+        .get(&url)
+        .header("Accept", "application/json")
+        .header("Referer", "https://finance.yahoo.com/")
+        .send()
+        .await
+        .map_err(|e| format!("Could not reach Yahoo Finance: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("Yahoo Finance returned {} for {}", response.status(), symbol));
+    }
+    let data: serde_json::Value = response.json().await.map_err(|e| format!("Invalid response from Yahoo Finance: {}", e))?;
+    let meta = &data["chart"]["result"][0]["meta"];
+    let price = meta["regularMarketPrice"]
+        .as_f64()
+        .ok_or_else(|| format!("Yahoo Finance has no price for {}", symbol))?;
+    let previous_close = meta["previousClose"].as_f64().or_else(|| meta["chartPreviousClose"].as_f64());
+    let mut quote = Quote::new(symbol, price, meta["regularMarketOpen"].as_f64(), previous_close, Provider::Yahoo);
+    quote.currency = meta["currency"].as_str().map(str::to_string);
+    quote.quoted_at = meta["regularMarketTime"].as_i64().and_then(from_unix);
+    Ok(quote)
+}
+
+/// Snapshot endpoint first; plans without snapshot access fall back to the previous day's bar.
+async fn fetch_polygon(client: &reqwest::Client, symbol: &str, api_key: &str) -> Result<Quote, String> {
+    let url = format!("https://api.polygon.io/v2/snapshot/locale/us/markets/stocks/tickers/{}", symbol);
+    let response = client
+        .get(&url)
+        .query(&[("apiKey", api_key)])
+        .send()
+        .await
+        .map_err(|e| format!("Could not reach Polygon: {}", e))?;
+    let status = response.status();
+    if status == reqwest::StatusCode::UNAUTHORIZED {
+        return Err("Polygon rejected the API key. Check it in Settings.".to_string());
+    }
+    if status.is_success() {
+        let data: serde_json::Value = response.json().await.map_err(|e| format!("Invalid response from Polygon: {}", e))?;
+        let ticker = &data["ticker"];
+        if let Some(price) = ticker["lastTrade"]["p"].as_f64().or_else(|| ticker["day"]["c"].as_f64()).filter(|p| *p > 0.0) {
+            let mut quote = Quote::new(symbol, price, ticker["day"]["o"].as_f64(), ticker["prevDay"]["c"].as_f64(), Provider::Polygon);
+            quote.currency = Some("USD".to_string());
+            // Nanoseconds since the epoch
+            quote.quoted_at = ticker["updated"].as_i64().and_then(|ns| from_unix(ns / 1_000_000_000));
+            return Ok(quote);
+        }
+    } else if status != reqwest::StatusCode::FORBIDDEN && status != reqwest::StatusCode::NOT_FOUND {
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!("Polygon returned {}: {}", status, body.trim()));
+    }
+
+    let url = format!("https://api.polygon.io/v2/aggs/ticker/{}/prev", symbol);
+    let response = client
+        .get(&url)
+        .query(&[("adjusted", "true"), ("apiKey", api_key)])
+        .send()
+        .await
+        .map_err(|e| format!("Could not reach Polygon: {}", e))?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!("Polygon returned {}: {}", status, body.trim()));
+    }
+    let data: serde_json::Value = response.json().await.map_err(|e| format!("Invalid response from Polygon: {}", e))?;
+    let bar = &data["results"][0];
+    let price = bar["c"].as_f64().ok_or_else(|| format!("Polygon has no price for {}", symbol))?;
+    let mut quote = Quote::new(symbol, price, bar["o"].as_f64(), None, Provider::Polygon);
+    quote.currency = Some("USD".to_string());
+    // Milliseconds since the epoch (start of the bar)
+    quote.quoted_at = bar["t"].as_i64().and_then(|ms| from_unix(ms / 1000));
+    Ok(quote)
+}
+
+async fn fetch_alpha_vantage(client: &reqwest::Client, symbol: &str, api_key: &str) -> Result<Quote, String> {
+    let response = client
+        .get("https://www.alphavantage.co/query")
+        .query(&[("function", "GLOBAL_QUOTE"), ("symbol", symbol), ("apikey", api_key)])
+        .send()
+        .await
+        .map_err(|e| format!("Could not reach Alpha Vantage: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("Alpha Vantage returned {} for {}", response.status(), symbol));
+    }
+    let data: serde_json::Value = response.json().await.map_err(|e| format!("Invalid response from Alpha Vantage: {}", e))?;
+    // Rate limits and bad keys come back as 200 with a message instead of a quote
+    if let Some(message) = data["Note"].as_str().or_else(|| data["Information"].as_str()).or_else(|| data["Error Message"].as_str()) {
+        return Err(format!("Alpha Vantage: {}", message));
+    }
+    let fields = &data["Global Quote"];
+    let number = |key: &str| fields[key].as_str().and_then(|v| v.trim().parse::<f64>().ok());
+    let price = number("05. price").ok_or_else(|| format!("Alpha Vantage has no price for {}", symbol))?;
+    let mut quote = Quote::new(symbol, price, number("02. open"), number("08. previous close"), Provider::AlphaVantage);
+    quote.currency = Some("USD".to_string());
+    quote.quoted_at = fields["07. latest trading day"].as_str().map(str::to_string);
+    Ok(quote)
+}
+
+/// Cached quote for `symbol` if it was fetched within `max_age_seconds`.
+pub fn cached_quote(conn: &Connection, symbol: &str, max_age_seconds: i64) -> Result<Option<Quote>, String> {
+    let result = conn.query_row(
+        "SELECT symbol, price, open, previous_close, currency, provider, quoted_at, fetched_at FROM quote_cache WHERE symbol = ?1",
+        [symbol],
+        |row| {
+            Ok(Quote {
+                symbol: row.get(0)?,
+                price: row.get(1)?,
+                open: row.get(2)?,
+                previous_close: row.get(3)?,
+                change: None,
+                change_pct: None,
+                currency: row.get(4)?,
+                provider: row.get(5)?,
+                quoted_at: row.get(6)?,
+                fetched_at: row.get(7)?,
+                cached: true,
+            })
+        },
+    );
+    let mut quote = match result {
+        Ok(quote) => quote,
+        Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(None),
+        Err(e) => return Err(e.to_string()),
+    };
+    let fresh = DateTime::parse_from_rfc3339(&quote.fetched_at)
+        .is_ok_and(|fetched| (Utc::now() - fetched.with_timezone(&Utc)).num_seconds() <= max_age_seconds);
+    if !fresh {
+        return Ok(None);
+    }
+    quote.change = quote.previous_close.map(|prev| quote.price - prev);
+    quote.change_pct = quote.previous_close.filter(|prev| *prev != 0.0).map(|prev| (quote.price - prev) / prev * 100.0);
+    Ok(Some(quote))
+}
+
+pub fn store_quote(conn: &Connection, quote: &Quote) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO quote_cache (symbol, price, open, previous_close, currency, provider, quoted_at, fetched_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+         ON CONFLICT(symbol) DO UPDATE SET price = excluded.price, open = excluded.open,
+             previous_close = excluded.previous_close, currency = excluded.currency, provider = excluded.provider,
+             quoted_at = excluded.quoted_at, fetched_at = excluded.fetched_at",
+        params![
+            quote.symbol,
+            quote.price,
+            quote.open,
+            quote.previous_close,
+            quote.currency,
+            quote.provider,
+            quote.quoted_at,
+            quote.fetched_at
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+pub fn cache_seconds(conn: &Connection) -> i64 {
+    get_app_setting(conn, QUOTE_CACHE_SECONDS)
+        .and_then(|s| s.trim().parse::<i64>().ok())
+        .filter(|s| *s >= 0)
+        .unwrap_or(DEFAULT_QUOTE_CACHE_SECONDS)
+}
+
+/// Bar sizes every provider can serve
+pub const BAR_INTERVALS: [&str; 5] = ["1m", "5m", "15m", "1h", "1d"];
+
+/// Length of a bar in seconds.
+pub fn interval_seconds(interval: &str) -> Result<i64, String> {
+    match interval {
+        "1m" => Ok(60),
+        "5m" => Ok(300),
+        "15m" => Ok(900),
+        "1h" => Ok(3600),
+        "1d" => Ok(86_400),
+        other => Err(format!("Unknown bar interval '{}' (expected one of {})", other, BAR_INTERVALS.join(", "))),
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bar {
+    /// Bar start, seconds since the epoch
+    pub ts: i64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: Option<f64>,
+}
+
+/// OHLC bars of `interval` covering [start, end] (epoch seconds), oldest first.
+pub async fn fetch_bars(config: &MarketDataConfig, symbol: &str, interval: &str, start: i64, end: i64) -> Result<Vec<Bar>, String> {
+    interval_seconds(interval)?;
+    let client = http_client()?;
+    let mut bars = match config.provider {
+        Provider::Yahoo => fetch_yahoo_bars(&client, symbol, interval, start, end).await?,
+        Provider::Polygon => fetch_polygon_bars(&client, symbol, interval, start, end, config.api_key.as_deref().unwrap_or_default()).await?,
+        Provider::AlphaVantage => {
+            fetch_alpha_vantage_bars(&client, symbol, interval, start, end, config.api_key.as_deref().unwrap_or_default()).await?
+        }
+    };
+    bars.retain(|bar| bar.high >= bar.low);
+    bars.sort_by_key(|bar| bar.ts);
+    bars.dedup_by_key(|bar| bar.ts);
+    Ok(bars)
+}
+
+/// Yahoo keeps 1m bars for about 7 days, 5m/15m for 60 days and hourly for 730 days.
+async fn fetch_yahoo_bars(client: &reqwest::Client, symbol: &str, interval: &str, start: i64, end: i64) -> Result<Vec<Bar>, String> {
+    let yahoo_interval = if interval == "1h" { "60m" } else { interval };
+    let url = format!(
+        "https://query1.finance.yahoo.com/v8/finance/chart/{}?period1={}&period2={}&interval={}",
+        symbol, start, end, yahoo_interval
+    );
+    let response = client
+        .get(&url)
+        .header("Accept", "application/json")
+        .header("Referer", "https://finance.yahoo.com/")
+        .send()
+        .await
+        .map_err(|e| format!("Could not reach Yahoo Finance: {}", e))?;
+    if !response.status().is_success() {
+        let status = response.status();
+        // Out-of-range requests (e.g. 1m bars older than a week) explain themselves in the body
+        let body: serde_json::Value = response.json().await.unwrap_or_default();
+        return Err(match body["chart"]["error"]["description"].as_str() {
+            Some(detail) => format!("Yahoo Finance returned {} for {} bars: {}", status, symbol, detail),
+            None => format!("Yahoo Finance returned {} for {} bars", status, symbol),
+        });
+    }
+    let data: serde_json::Value = response.json().await.map_err(|e| format!("Invalid response from Yahoo Finance: {}", e))?;
+    let result = &data["chart"]["result"][0];
+    let quote = &result["indicators"]["quote"][0];
+    let empty = Vec::new();
+    let timestamps = result["timestamp"].as_array().unwrap_or(&empty);
+    // Bars with no trades come back as nulls
+    Ok(timestamps
+        .iter()
+        .enumerate()
+        .filter_map(|(i, ts)| {
+            Some(Bar {
+                ts: ts.as_i64()?,
+                open: quote["open"][i].as_f64()?,
+                high: quote["high"][i].as_f64()?,
+                low: quote["low"][i].as_f64()?,
+                close: quote["close"][i].as_f64()?,
+                volume: quote["volume"][i].as_f64(),
+            })
+        })
+        .collect())
+}
+
+async fn fetch_polygon_bars(
+    client: &reqwest::Client,
+    symbol: &str,
+    interval: &str,
+    start: i64,
+    end: i64,
+    api_key: &str,
+) -> Result<Vec<Bar>, String> {
+    let (multiplier, timespan) = match interval {
+        "1m" => (1, "minute"),
+        "5m" => (5, "minute"),
+        "15m" => (15, "minute"),
+        "1h" => (1, "hour"),
+        _ => (1, "day"),
+    };
+    let url = format!(
+        "https://api.polygon.io/v2/aggs/ticker/{}/range/{}/{}/{}/{}",
+        symbol,
+        multiplier,
+        timespan,
+        start * 1000,
+        end * 1000
+    );
+    let response = client
+        .get(&url)
+        .query(&[("adjusted", "true"), ("sort", "asc"), ("limit", "50000"), ("apiKey", api_key)])
+        .send()
+        .await
+        .map_err(|e| format!("Could not reach Polygon: {}", e))?;
+    if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+        return Err("Polygon rejected the API key. Check it in Settings.".to_string());
+    }
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!("Polygon returned {}: {}", status, body.trim()));
+    }
+    let data: serde_json::Value = response.json().await.map_err(|e| format!("Invalid response from Polygon: {}", e))?;
+    let empty = Vec::new();
+    Ok(data["results"]
+        .as_array()
+        .unwrap_or(&empty)
+        .iter()
+        .filter_map(|bar| {
+            Some(Bar {
+                ts: bar["t"].as_i64()? / 1000,
+                open: bar["o"].as_f64()?,
+                high: bar["h"].as_f64()?,
+                low: bar["l"].as_f64()?,
+                close: bar["c"].as_f64()?,
+                volume: bar["v"].as_f64(),
+            })
+        })
+        .collect())
+}
+
+/// Intraday series are requested one month at a time; bar times come back in US/Eastern.
+async fn fetch_alpha_vantage_bars(
+    client: &reqwest::Client,
+    symbol: &str,
+    interval: &str,
+    start: i64,
+    end: i64,
+    api_key: &str,
+) -> Result<Vec<Bar>, String> {
+    let eastern = chrono_tz::America::New_York;
+    let to_eastern = |ts: i64| DateTime::<Utc>::from_timestamp(ts, 0).map(|dt| dt.with_timezone(&eastern).date_naive());
+    let (Some(first_day), Some(last_day)) = (to_eastern(start), to_eastern(end)) else {
+        return Err("Invalid bar range".to_string());
+    };
+
+    let mut requests: Vec<Vec<(&str, String)>> = Vec::new();
+    if interval == "1d" {
+        requests.push(vec![("function", "TIME_SERIES_DAILY".to_string()), ("outputsize", "full".to_string())]);
+    } else {
+        let av_interval = match interval {
+            "1m" => "1min",
+            "5m" => "5min",
+            "15m" => "15min",
+            _ => "60min",
+        };
+        let mut month = first_day.with_day(1).unwrap_or(first_day);
+        while month <= last_day {
+            requests.push(vec![
+                ("function", "TIME_SERIES_INTRADAY".to_string()),
+                ("interval", av_interval.to_string()),
+                ("month", month.format("%Y-%m").to_string()),
+                ("outputsize", "full".to_string()),
+                ("extended_hours", "true".to_string()),
+            ]);
+            match month.checked_add_months(chrono::Months::new(1)) {
+                Some(next) => month = next,
+                None => break,
+            }
+        }
+    }
+
+    let mut bars = Vec::new();
+    for mut query in requests {
+        query.push(("symbol", symbol.to_string()));
+        query.push(("apikey", api_key.to_string()));
+        let response = client
+            .get("https://www.alphavantage.co/query")
+            .query(&query)
+            .send()
+            .await
+            .map_err(|e| format!("Could not reach Alpha Vantage: {}", e))?;
+        if !response.status().is_success() {
+            return Err(format!("Alpha Vantage returned {} for {} bars", response.status(), symbol));
+        }
+        let data: serde_json::Value = response.json().await.map_err(|e| format!("Invalid response from Alpha Vantage: {}", e))?;
+        if let Some(message) = data["Note"].as_str().or_else(|| data["Information"].as_str()).or_else(|| data["Error Message"].as_str()) {
+            return Err(format!("Alpha Vantage: {}", message));
+        }
+        let Some(series) = data.as_object().and_then(|o| o.iter().find(|(key, _)| key.starts_with("Time Series"))).and_then(|(_, v)| v.as_object())
+        else {
+            continue;
+        };
+        for (time, fields) in series {
+            let naive = chrono::NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M:%S")
+                .or_else(|_| chrono::NaiveDate::parse_from_str(time, "%Y-%m-%d").map(|d| d.and_hms_opt(0, 0, 0).unwrap_or_default()));
+            let Some(ts) = naive.ok().and_then(|n| n.and_local_timezone(eastern).earliest()).map(|dt| dt.timestamp()) else {
+                continue;
+            };
+            let number = |key: &str| fields[key].as_str().and_then(|v| v.trim().parse::<f64>().ok());
+            // Intraday stamps mark the bar's close; store the start like the other providers
+            let ts = if interval == "1d" { ts } else { ts - interval_seconds(interval)? };
+            if let (Some(open), Some(high), Some(low), Some(close)) = (number("1. open"), number("2. high"), number("3. low"), number("4. close")) {
+                bars.push(Bar { ts, open, high, low, close, volume: number("5. volume") });
+            }
+        }
+    }
+    let interval_len = interval_seconds(interval)?;
+    bars.retain(|bar| bar.ts + interval_len > start && bar.ts <= end);
+    Ok(bars)
+}
+
+pub fn store_bars(conn: &Connection, symbol: &str, interval: &str, provider: Provider, bars: &[Bar]) -> Result<(), String> {
+    let mut stmt = conn
+        .prepare(
+            "INSERT OR REPLACE INTO price_bars (symbol, interval, ts, open, high, low, close, volume, provider)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        )
+        .map_err(|e| e.to_string())?;
+    for bar in bars {
+        stmt.execute(params![symbol, interval, bar.ts, bar.open, bar.high, bar.low, bar.close, bar.volume, provider.as_str()])
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Cached bars whose span overlaps [start, end), oldest first (the bar holding `start` when start == end).
+pub fn load_bars(conn: &Connection, symbol: &str, interval: &str, start: i64, end: i64) -> Result<Vec<Bar>, String> {
+    let interval_len = interval_seconds(interval)?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT ts, open, high, low, close, volume FROM price_bars
+             WHERE symbol = ?1 AND interval = ?2 AND ts > ?3 AND (ts < ?4 OR ts <= ?5) ORDER BY ts",
+        )
+        .map_err(|e| e.to_string())?;
+    let bars = stmt
+        .query_map(params![symbol, interval, start - interval_len, end, start], |row| {
+            Ok(Bar {
+                ts: row.get(0)?,
+                open: row.get(1)?,
+                high: row.get(2)?,
+                low: row.get(3)?,
+                close: row.get(4)?,
+                volume: row.get(5)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<_, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(bars)
+}
+
+/// Descriptive metadata for a ticker, used to fill in instrument records (sector rollups).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstrumentProfile {
+    pub symbol: String,
+    pub name: Option<String>,
+    /// One of commands::ASSET_CLASSES ("stock" for equities and ETFs)
+    pub asset_class: String,
+    pub sector: Option<String>,
+    pub industry: Option<String>,
+    pub exchange: Option<String>,
+    pub currency: Option<String>,
+    pub provider: String,
+}
+
+/// Name, sector, industry and exchange for `symbol` from the configured provider. Polygon has no
+/// sector field, so its SIC description stands in as the industry.
+pub async fn fetch_profile(config: &MarketDataConfig, symbol: &str) -> Result<InstrumentProfile, String> {
+    let client = http_client()?;
+    match config.provider {
+        Provider::Yahoo => fetch_yahoo_profile(&client, symbol).await,
+        Provider::Polygon => fetch_polygon_profile(&client, symbol, config.api_key.as_deref().unwrap_or_default()).await,
+        Provider::AlphaVantage => fetch_alpha_vantage_profile(&client, symbol, config.api_key.as_deref().unwrap_or_default()).await,
+    }
+}
+
+fn non_empty(value: &serde_json::Value) -> Option<String> {
+    value.as_str().map(str::trim).filter(|v| !v.is_empty() && *v != "None" && *v != "-").map(str::to_string)
+}
+
+/// Asset class for a provider's security type ("EQUITY", "ETF", "CS", "CRYPTOCURRENCY", ...).
+fn profile_asset_class(security_type: &str) -> &'static str {
+    match security_type.trim().to_lowercase().as_str() {
+        "cryptocurrency" | "crypto" => "crypto",
+        "currency" | "fx" => "forex",
+        "future" | "futures" => "future",
+        "option" => "option",
+        _ => "stock",
+    }
+}
+
+/// The search endpoint carries sector and industry without the cookie/crumb quoteSummary needs.
+async fn fetch_yahoo_profile(client: &reqwest::Client, symbol: &str) -> Result<InstrumentProfile, String> {
+    let response = client
+        .get("https://query2.finance.yahoo.com/v1/finance/search")
+        .query(&[("q", symbol), ("quotesCount", "5"), ("newsCount", "0")])
+        .header("Accept", "application/json")
+        .header("Referer", "https://finance.yahoo.com/")
+        .send()
+        .await
+        .map_err(|e| format!("Could not reach Yahoo Finance: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("Yahoo Finance returned {} for {}", response.status(), symbol));
+    }
+    let data: serde_json::Value = response.json().await.map_err(|e| format!("Invalid response from Yahoo Finance: {}", e))?;
+    let quote = data["quotes"]
+        .as_array()
+        .and_then(|quotes| quotes.iter().find(|q| q["symbol"].as_str().is_some_and(|s| s.eq_ignore_ascii_case(symbol))))
+        .ok_or_else(|| format!("Yahoo Finance has no profile for {}", symbol))?;
+    Ok(InstrumentProfile {
+        symbol: symbol.to_string(),
+        name: non_empty(&quote["longname"]).or_else(|| non_empty(&quote["shortname"])),
+        asset_class: profile_asset_class(quote["quoteType"].as_str().unwrap_or_default()).to_string(),
+        sector: non_empty(&quote["sectorDisp"]).or_else(|| non_empty(&quote["sector"])),
+        industry: non_empty(&quote["industryDisp"]).or_else(|| non_empty(&quote["industry"])),
+        exchange: non_empty(&quote["exchDisp"]).or_else(|| non_empty(&quote["exchange"])),
+        currency: None,
+        provider: Provider::Yahoo.as_str().to_string(),
+    })
+}
+
+async fn fetch_polygon_profile(client: &reqwest::Client, symbol: &str, api_key: &str) -> Result<InstrumentProfile, String> {
+    let url = format!("https://api.polygon.io/v3/reference/tickers/{}", symbol);
+    let response = client
+        .get(&url)
+        .query(&[("apiKey", api_key)])
+        .send()
+        .await
+        .map_err(|e| format!("Could not reach Polygon: {}", e))?;
+    let status = response.status();
+    if status == reqwest::StatusCode::UNAUTHORIZED {
+        return Err("Polygon rejected the API key. Check it in Settings.".to_string());
+    }
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!("Polygon returned {}: {}", status, body.trim()));
+    }
+    let data: serde_json::Value = response.json().await.map_err(|e| format!("Invalid response from Polygon: {}", e))?;
+    let result = &data["results"];
+    if result.is_null() {
+        return Err(format!("Polygon has no profile for {}", symbol));
+    }
+    Ok(InstrumentProfile {
+        symbol: symbol.to_string(),
+        name: non_empty(&result["name"]),
+        asset_class: profile_asset_class(result["market"].as_str().unwrap_or_default()).to_string(),
+        sector: None,
+        industry: non_empty(&result["sic_description"]),
+        exchange: non_empty(&result["primary_exchange"]),
+        currency: non_empty(&result["currency_name"]).map(|c| c.to_uppercase()),
+        provider: Provider::Polygon.as_str().to_string(),
+    })
+}
+
+async fn fetch_alpha_vantage_profile(client: &reqwest::Client, symbol: &str, api_key: &str) -> Result<InstrumentProfile, String> {
+    let response = client
+        .get("https://www.alphavantage.co/query")
+        .query(&[("function", "OVERVIEW"), ("symbol", symbol), ("apikey", api_key)])
+        .send()
+        .await
+        .map_err(|e| format!("Could not reach Alpha Vantage: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("Alpha Vantage returned {} for {}", response.status(), symbol));
+    }
+    let data: serde_json::Value = response.json().await.map_err(|e| format!("Invalid response from Alpha Vantage: {}", e))?;
+    if let Some(message) = data["Note"].as_str().or_else(|| data["Information"].as_str()).or_else(|| data["Error Message"].as_str()) {
+        return Err(format!("Alpha Vantage: {}", message));
+    }
+    // Unknown symbols (and ETFs, which OVERVIEW doesn't cover) come back as an empty object
+    if data["Symbol"].is_null() {
+        return Err(format!("Alpha Vantage has no profile for {}", symbol));
+    }
+    Ok(InstrumentProfile {
+        symbol: symbol.to_string(),
+        name: non_empty(&data["Name"]),
+        asset_class: profile_asset_class(data["AssetType"].as_str().unwrap_or_default()).to_string(),
+        // OVERVIEW reports sectors in upper case ("TECHNOLOGY")
+        sector: non_empty(&data["Sector"]).map(|s| title_case(&s)),
+        industry: non_empty(&data["Industry"]).map(|s| title_case(&s)),
+        exchange: non_empty(&data["Exchange"]),
+        currency: non_empty(&data["Currency"]),
+        provider: Provider::AlphaVantage.as_str().to_string(),
+    })
+}
+
+fn title_case(value: &str) -> String {
+    value
+        .split_whitespace()
+        .map(|word| {
+            let lower = word.to_lowercase();
+            let mut chars = lower.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
@@ -0,0 +1,2475 @@
+// Metrics engine for get_metrics.
+// Reads the journal's trades once, pairs them once, groups them into positions once, and derives
+// every dashboard aggregate from that snapshot in a single pass over the pairs. get_metrics used to
+// go through get_paired_trades, get_position_groups and get_daily_pnl, each of which re-read and
+// re-paired every trade.
+
+use crate::commands::{
+    emotional_states_paper_clause, group_positions, infer_asset_class, instrument_sector, load_asset_classes,
+    load_instrument_specs, load_risk_rules, multiplier_for_asset_class, pair_trades, pairing_is_fifo, paper_only_and_clause, parse_trade_datetime, position_strategies, resolve_pair_strategy, risk_free_rate,
+    timestamp_in_range, trade_ids_with_tags, ASSET_CLASSES, LONG_TERM, ChecklistBucket, ChecklistCompliance, DailyPnL, HistogramBin, InstrumentSpec, Metrics, PairedTrade, PairingContext,
+    ProfitConcentration, ProfitShare, RBucket, RiskOfRuin, RiskOfRuinAssumptions, StrategyPerformance,
+};
+use crate::database::{Status, Trade};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use chrono::{Datelike, Timelike};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::ops::Bound;
+
+/// Which trades get_metrics covers. Dates bound the exit time of pairs and the time of trades.
+pub struct MetricsFilter {
+    pub pairing_method: Option<String>,
+    pub start_date: Option<String>,
+    pub end_date: Option<String>,
+    pub paper_only: Option<bool>,
+    pub strategy_id: Option<i64>,
+    pub asset_class: Option<String>,
+    /// Pairs whose entry or exit trade carries any of these tags
+    pub tags: Vec<String>,
+}
+
+impl MetricsFilter {
+    fn in_range(&self, timestamp: &str) -> bool {
+        timestamp_in_range(timestamp, self.start_date.as_deref(), self.end_date.as_deref())
+    }
+
+    fn has_trade_filter(&self) -> bool {
+        self.strategy_id.is_some() || self.asset_class.is_some() || !self.tags.is_empty()
+    }
+}
+
+pub(crate) fn is_filled(trade: &Trade) -> bool {
+    Status::parse(&trade.status) == Some(Status::Filled)
+}
+
+/// Every trade (any status) matching the paper filter, oldest first.
+pub(crate) fn load_trades(conn: &Connection, paper_only: Option<bool>) -> Result<Vec<Trade>, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT id, symbol, side, quantity, price, timestamp, order_type, status, fees, notes, strategy_id, is_paper FROM trades WHERE 1=1{} ORDER BY timestamp ASC",
+            paper_only_and_clause(paper_only)
+        ))
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok(Trade {
+                id: Some(row.get(0)?),
+                symbol: row.get(1)?,
+                side: row.get(2)?,
+                quantity: row.get(3)?,
+                price: row.get(4)?,
+                timestamp: row.get(5)?,
+                order_type: row.get(6)?,
+                status: row.get(7)?,
+                fees: row.get(8)?,
+                notes: row.get(9)?,
+                strategy_id: row.get(10)?,
+                external_id: None,
+                event_type: None,
+                fx_rate: None,
+                position_effect: None,
+                is_paper: row.get(11)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())
+}
+
+fn reviewed_trade_ids(conn: &Connection) -> Result<HashSet<i64>, String> {
+    let mut stmt = conn.prepare("SELECT trade_id FROM trade_reviews WHERE reviewed = 1").map_err(|e| e.to_string())?;
+    let rows = stmt.query_map([], |row| row.get::<_, i64>(0)).map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+/// Compliance of `pairs` with the checklist recorded on their entry trade.
+fn checklist_compliance(conn: &Connection, pairs: &[PairedTrade]) -> Result<ChecklistCompliance, String> {
+    // Entry trade id -> (items satisfied, items recorded)
+    let mut recorded: HashMap<i64, (i64, i64)> = HashMap::new();
+    let mut stmt = conn
+        .prepare("SELECT trade_id, SUM(satisfied != 0), COUNT(*) FROM trade_checklist_results GROUP BY trade_id")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, (row.get::<_, i64>(1)?, row.get::<_, i64>(2)?))))
+        .map_err(|e| e.to_string())?;
+    for row in rows {
+        let (trade_id, counts) = row.map_err(|e| e.to_string())?;
+        recorded.insert(trade_id, counts);
+    }
+
+    let mut score_sum = 0.0;
+    let mut followed = PairStats::default();
+    let mut broken = PairStats::default();
+    for pair in pairs {
+        let Some(&(satisfied, total)) = recorded.get(&pair.entry_trade_id) else {
+            continue;
+        };
+        score_sum += ratio(satisfied as f64, total as f64);
+        if satisfied == total {
+            followed.record(pair.net_profit_loss);
+        } else {
+            broken.record(pair.net_profit_loss);
+        }
+    }
+    let bucket = |stats: PairStats| ChecklistBucket {
+        trades: stats.count,
+        win_rate: stats.win_rate(),
+        average_pnl: ratio(stats.net_pnl, stats.count as f64),
+    };
+    let checked_trades = followed.count + broken.count;
+    Ok(ChecklistCompliance {
+        score: ratio(score_sum, checked_trades as f64),
+        checked_trades,
+        followed: bucket(followed),
+        broken: bucket(broken),
+    })
+}
+
+/// Risk of one unit is the distance from the planned entry (or the actual entry) to the stop, so
+/// R = net P&L / (that distance x quantity x contract multiplier). Pairs without a plan are skipped.
+fn r_multiples<'a>(conn: &Connection, pairs: &'a [PairedTrade]) -> Result<Vec<(&'a PairedTrade, f64)>, String> {
+    let mut plans: HashMap<i64, (Option<f64>, f64)> = HashMap::new();
+    let mut stmt = conn.prepare("SELECT trade_id, planned_entry, stop_price FROM trade_plans").map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, (row.get::<_, Option<f64>>(1)?, row.get::<_, f64>(2)?))))
+        .map_err(|e| e.to_string())?;
+    for row in rows {
+        let (trade_id, plan) = row.map_err(|e| e.to_string())?;
+        plans.insert(trade_id, plan);
+    }
+    if plans.is_empty() {
+        return Ok(Vec::new());
+    }
+    let specs = load_instrument_specs(conn)?;
+    let asset_classes = load_asset_classes(conn)?;
+
+    let mut r_values = Vec::new();
+    for pair in pairs {
+        let Some(&(planned_entry, stop)) = plans.get(&pair.entry_trade_id) else {
+            continue;
+        };
+        let multiplier = multiplier_for_asset_class(&pair.symbol, asset_classes.get(&pair.entry_trade_id).map(String::as_str), &specs);
+        let risk = (planned_entry.unwrap_or(pair.entry_price) - stop).abs() * pair.quantity * multiplier;
+        if risk > 0.0 {
+            r_values.push((pair, pair.net_profit_loss / risk));
+        }
+    }
+    Ok(r_values)
+}
+
+fn r_distribution(r_values: &[f64]) -> Vec<RBucket> {
+    const EDGES: [f64; 6] = [-2.0, -1.0, 0.0, 1.0, 2.0, 3.0];
+    let mut buckets: Vec<RBucket> = Vec::new();
+    for i in 0..=EDGES.len() {
+        let min = if i == 0 { None } else { Some(EDGES[i - 1]) };
+        let max = EDGES.get(i).copied();
+        let label = match (min, max) {
+            (None, Some(max)) => format!("< {}R", max),
+            (Some(min), Some(max)) => format!("{}R to {}R", min, max),
+            (Some(min), None) => format!(">= {}R", min),
+            (None, None) => unreachable!(),
+        };
+        let count = r_values
+            .iter()
+            .filter(|r| min.is_none_or(|min| **r >= min) && max.is_none_or(|max| **r < max))
+            .count() as i64;
+        buckets.push(RBucket { label, min, max, count });
+    }
+    buckets
+}
+
+fn profit_concentration(pairs: &[PairedTrade], daily_pnl: &[DailyPnL], net_profit: f64) -> ProfitConcentration {
+    let mut wins: Vec<f64> = pairs.iter().map(|p| p.net_profit_loss).filter(|pnl| *pnl > 0.0).collect();
+    let mut green_days: Vec<f64> = daily_pnl.iter().map(|d| d.profit_loss).filter(|pnl| *pnl > 0.0).collect();
+    let descending = |a: &f64, b: &f64| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal);
+    wins.sort_by(descending);
+    green_days.sort_by(descending);
+
+    let top_shares = |sorted: &[f64]| {
+        let total: f64 = sorted.iter().sum();
+        [1, 3, 5, 10]
+            .iter()
+            .map(|&n| ProfitShare {
+                n: n as i64,
+                profit_pct: ratio(sorted.iter().take(n).sum::<f64>(), total) * 100.0,
+            })
+            .collect::<Vec<_>>()
+    };
+    let best_day = green_days.first().copied();
+    ProfitConcentration {
+        top_trades: top_shares(&wins),
+        top_days: top_shares(&green_days),
+        best_day_pct_of_net: best_day.filter(|_| net_profit > 0.0).map(|best| best / net_profit * 100.0),
+        consistency_score: best_day.map_or(0.0, |best| 100.0 - best / green_days.iter().sum::<f64>() * 100.0),
+    }
+}
+
+const TRADING_DAYS_PER_YEAR: f64 = 252.0;
+
+#[derive(Default)]
+struct RiskAdjustedRatios {
+    sharpe: f64,
+    sortino: f64,
+    calmar: f64,
+}
+
+/// Start-of-day balance for `date`: the latest snapshot before it plus the P&L realized since, or,
+/// for days before the first snapshot, that snapshot minus the P&L realized up to it.
+fn start_of_day_balance(date: &str, snapshots: &BTreeMap<String, f64>, account_daily_pnl: &BTreeMap<String, f64>) -> Option<f64> {
+    if let Some((snapshot_date, balance)) = snapshots.range::<str, _>((Bound::Unbounded, Bound::Excluded(date))).next_back() {
+        let since: f64 = account_daily_pnl
+            .range::<str, _>((Bound::Excluded(snapshot_date.as_str()), Bound::Excluded(date)))
+            .map(|(_, pnl)| pnl)
+            .sum();
+        return Some(balance + since);
+    }
+    let (snapshot_date, balance) = snapshots.iter().next()?;
+    let through_snapshot: f64 = account_daily_pnl
+        .range::<str, _>((Bound::Included(date), Bound::Included(snapshot_date.as_str())))
+        .map(|(_, pnl)| pnl)
+        .sum();
+    Some(balance - through_snapshot)
+}
+
+/// account_balances snapshots for the real or paper account, by date.
+fn balance_snapshots(conn: &Connection, paper_only: Option<bool>) -> Result<BTreeMap<String, f64>, String> {
+    let mut stmt = conn
+        .prepare("SELECT date, balance FROM account_balances WHERE is_paper = ?1")
+        .map_err(|e| e.to_string())?;
+    let snapshots = stmt
+        .query_map([paper_only == Some(true)], |row| Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<_, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(snapshots)
+}
+
+/// Drawdown (% of the account) treated as ruin
+const RUIN_DRAWDOWN_PCT: f64 = 50.0;
+
+/// Kelly fraction and risk of ruin. Ruin is the gambler's-ruin probability for a walk that gains the
+/// payoff ratio with the win rate and loses one unit otherwise: r^units, where r is the root in (0, 1)
+/// of p*r^(b+1) - r + q = 0 and units is the ruin drawdown in average losses.
+fn risk_of_ruin(
+    conn: &Connection,
+    paper_only: Option<bool>,
+    trade_count: i64,
+    win_rate: f64,
+    average_profit: f64,
+    average_loss: f64,
+    account_daily_pnl: &BTreeMap<String, f64>,
+) -> Result<RiskOfRuin, String> {
+    let snapshots = balance_snapshots(conn, paper_only)?;
+    let account_size = start_of_day_balance("9999-12-31", &snapshots, account_daily_pnl).filter(|balance| *balance > 0.0);
+    let payoff_ratio = ratio(average_profit, average_loss);
+    let risk_units = account_size
+        .filter(|_| average_loss > 0.0)
+        .map(|balance| balance * RUIN_DRAWDOWN_PCT / 100.0 / average_loss);
+
+    let kelly_fraction = (average_profit > 0.0 && average_loss > 0.0).then(|| win_rate - (1.0 - win_rate) / payoff_ratio);
+    let probability = match (trade_count, account_size) {
+        (0, _) | (_, None) => None,
+        _ if average_loss <= 0.0 => Some(0.0),
+        _ if win_rate * payoff_ratio <= 1.0 - win_rate => Some(1.0),
+        _ => {
+            let (p, q) = (win_rate, 1.0 - win_rate);
+            let f = |r: f64| p * r.powf(payoff_ratio + 1.0) - r + q;
+            let (mut lo, mut hi) = (0.0, 1.0 - 1e-9);
+            if f(hi) >= 0.0 {
+                Some(1.0)
+            } else {
+                for _ in 0..100 {
+                    let mid = (lo + hi) / 2.0;
+                    if f(mid) > 0.0 {
+                        lo = mid;
+                    } else {
+                        hi = mid;
+                    }
+                }
+                risk_units.map(|units| hi.powf(units).clamp(0.0, 1.0))
+            }
+        }
+    };
+
+    Ok(RiskOfRuin {
+        kelly_fraction,
+        half_kelly: kelly_fraction.map(|k| k / 2.0),
+        probability,
+        assumptions: RiskOfRuinAssumptions {
+            trade_count,
+            win_rate,
+            payoff_ratio,
+            risk_per_trade: average_loss,
+            account_size,
+            account_size_date: account_size.and_then(|_| snapshots.keys().next_back().cloned()),
+            ruin_drawdown_pct: RUIN_DRAWDOWN_PCT,
+            risk_units,
+        },
+    })
+}
+
+/// Sharpe, Sortino and Calmar from the daily series, annualized over 252 trading days. Daily returns
+/// are P&L over the start-of-day balance from account_balances snapshots, net of the configured
+/// risk-free rate. Without snapshots the ratios fall back to raw daily P&L (risk-free rate ignored),
+/// which keeps Sharpe/Sortino comparable but makes Calmar annual P&L over max drawdown.
+fn risk_adjusted_ratios(
+    conn: &Connection,
+    paper_only: Option<bool>,
+    daily_pnl: &[DailyPnL],
+    account_daily_pnl: &BTreeMap<String, f64>,
+) -> Result<RiskAdjustedRatios, String> {
+    let mut days: Vec<&DailyPnL> = daily_pnl.iter().collect();
+    days.sort_by(|a, b| a.date.cmp(&b.date));
+    if days.len() < 2 {
+        return Ok(RiskAdjustedRatios::default());
+    }
+
+    let snapshots = balance_snapshots(conn, paper_only)?;
+    let (returns, daily_rf): (Vec<f64>, f64) = if snapshots.is_empty() {
+        (days.iter().map(|d| d.profit_loss).collect(), 0.0)
+    } else {
+        let returns = days
+            .iter()
+            .filter_map(|d| {
+                start_of_day_balance(&d.date, &snapshots, account_daily_pnl)
+                    .filter(|balance| *balance > 0.0)
+                    .map(|balance| d.profit_loss / balance)
+            })
+            .collect();
+        (returns, risk_free_rate(conn) / 100.0 / TRADING_DAYS_PER_YEAR)
+    };
+    let n = returns.len() as f64;
+    if n < 2.0 {
+        return Ok(RiskAdjustedRatios::default());
+    }
+
+    let mean = returns.iter().sum::<f64>() / n;
+    let excess = mean - daily_rf;
+    let std_dev = (returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (n - 1.0)).sqrt();
+    let downside_dev = (returns.iter().map(|r| (r - daily_rf).min(0.0).powi(2)).sum::<f64>() / n).sqrt();
+
+    // Max drawdown of the compounded return curve, or of cumulative P&L in the fallback
+    let mut equity = if snapshots.is_empty() { 0.0 } else { 1.0 };
+    let mut peak = equity;
+    let mut max_drawdown = 0.0;
+    for r in &returns {
+        if snapshots.is_empty() {
+            equity += r;
+            max_drawdown = f64::max(max_drawdown, peak - equity);
+        } else {
+            equity *= 1.0 + r;
+            max_drawdown = f64::max(max_drawdown, (peak - equity) / peak);
+        }
+        peak = f64::max(peak, equity);
+    }
+
+    let annualization = TRADING_DAYS_PER_YEAR.sqrt();
+    Ok(RiskAdjustedRatios {
+        sharpe: ratio(excess, std_dev) * annualization,
+        sortino: ratio(excess, downside_dev) * annualization,
+        calmar: ratio(mean * TRADING_DAYS_PER_YEAR, max_drawdown),
+    })
+}
+
+fn date_of(timestamp: &str) -> &str {
+    timestamp.split('T').next().unwrap_or(timestamp)
+}
+
+fn ratio(numerator: f64, denominator: f64) -> f64 {
+    if denominator > 0.0 {
+        numerator / denominator
+    } else {
+        0.0
+    }
+}
+
+/// Win/loss streak counter over a sequence of P&Ls; breakeven results leave streaks untouched.
+#[derive(Default)]
+struct Streaks {
+    current_wins: i64,
+    current_losses: i64,
+    max_wins: i64,
+    max_losses: i64,
+}
+
+impl Streaks {
+    fn record(&mut self, pnl: f64) {
+        if pnl > 0.0 {
+            self.current_losses = 0;
+            self.current_wins += 1;
+            self.max_wins = self.max_wins.max(self.current_wins);
+        } else if pnl < 0.0 {
+            self.current_wins = 0;
+            self.current_losses += 1;
+            self.max_losses = self.max_losses.max(self.current_losses);
+        }
+    }
+}
+
+/// Realized net P&L of each closing trade (the sum of the pairs it closes), by trade id. Opening
+/// trades have no entry.
+pub fn realized_pnl_by_trade(conn: &Connection, pairing_method: Option<&str>, paper_only: Option<bool>) -> Result<HashMap<i64, f64>, String> {
+    let filled: Vec<Trade> = load_trades(conn, paper_only)?.into_iter().filter(is_filled).collect();
+    let pairing_context = PairingContext::load(conn)?;
+    let (pairs, _open_trades) = pair_trades(filled, pairing_is_fifo(conn, pairing_method), &pairing_context);
+    let mut pnl_by_trade = HashMap::new();
+    for pair in &pairs {
+        *pnl_by_trade.entry(pair.exit_trade_id).or_insert(0.0) += pair.net_profit_loss;
+    }
+    Ok(pnl_by_trade)
+}
+
+pub fn compute_metrics(conn: &Connection, filter: &MetricsFilter) -> Result<Metrics, String> {
+    let all_trades = load_trades(conn, filter.paper_only)?;
+    let filled: Vec<Trade> = all_trades.iter().filter(|t| is_filled(t)).cloned().collect();
+    let use_fifo = pairing_is_fifo(conn, filter.pairing_method.as_deref());
+    let pairing_context = PairingContext::load(conn)?;
+    let (all_pairs, _open_trades) = pair_trades(filled.clone(), use_fifo, &pairing_context);
+
+    // Positions are built from the trades inside the date range, like get_position_groups, so the
+    // largest win/loss group ids match what that command returns for the same range
+    let in_range_filled: Vec<Trade> = filled.iter().filter(|t| filter.in_range(&t.timestamp)).cloned().collect();
+    let mut position_groups = group_positions(&in_range_filled, &all_pairs);
+
+    let days_since_last_tilt = tilt_days(conn, &all_pairs, filter.paper_only, DEFAULT_TILT_INTENSITY)?
+        .iter()
+        .filter(|day| day.is_tilt)
+        .filter_map(|day| chrono::NaiveDate::parse_from_str(&day.date, "%Y-%m-%d").ok())
+        .max()
+        .map(|last| (chrono::Local::now().date_naive() - last).num_days());
+
+    // Account-level realized P&L per day, for turning balance snapshots into start-of-day balances
+    let mut account_daily_pnl: BTreeMap<String, f64> = BTreeMap::new();
+    for pair in &all_pairs {
+        *account_daily_pnl.entry(date_of(&pair.exit_timestamp).to_string()).or_insert(0.0) += pair.net_profit_loss;
+    }
+
+    let mut pairs: Vec<PairedTrade> = all_pairs.into_iter().filter(|p| filter.in_range(&p.exit_timestamp)).collect();
+
+    if filter.strategy_id.is_some() {
+        let trade_to_position_strategy = position_strategies(&position_groups);
+        let entry_trade_strategies: HashMap<i64, Option<i64>> =
+            all_trades.iter().filter_map(|t| t.id.map(|id| (id, t.strategy_id))).collect();
+        pairs.retain(|p| resolve_pair_strategy(p, &trade_to_position_strategy, &entry_trade_strategies) == filter.strategy_id);
+    }
+    if let Some(class) = &filter.asset_class {
+        let classes = load_asset_classes(conn)?;
+        pairs.retain(|p| classes.get(&p.entry_trade_id).is_some_and(|c| c.eq_ignore_ascii_case(class)));
+    }
+    if !filter.tags.is_empty() {
+        let tagged = trade_ids_with_tags(conn, &filter.tags)?;
+        pairs.retain(|p| tagged.contains(&p.entry_trade_id) || tagged.contains(&p.exit_trade_id));
+    }
+    if filter.has_trade_filter() {
+        let entry_ids: HashSet<i64> = pairs.iter().map(|p| p.entry_trade_id).collect();
+        position_groups.retain(|g| g.entry_trade.id.is_some_and(|id| entry_ids.contains(&id)));
+    }
+
+    // Volume covers every trade in range (any status) unless a strategy/asset/tag filter narrows it to pairs
+    let total_volume: f64 = if filter.has_trade_filter() {
+        pairs.iter().map(|p| p.quantity * p.entry_price).sum()
+    } else {
+        all_trades.iter().filter(|t| filter.in_range(&t.timestamp)).map(|t| t.quantity * t.price).sum()
+    };
+
+    // Largest win/loss per position (complete positions, not individual pairs)
+    let mut largest_win = 0.0;
+    let mut largest_loss: Option<f64> = None;
+    let mut largest_win_group_id: Option<i64> = None;
+    let mut largest_loss_group_id: Option<i64> = None;
+    for group in &position_groups {
+        if group.total_pnl > largest_win {
+            largest_win = group.total_pnl;
+            largest_win_group_id = group.entry_trade.id;
+        } else if group.total_pnl < 0.0 && largest_loss.is_none_or(|loss| group.total_pnl < loss) {
+            largest_loss = Some(group.total_pnl);
+            largest_loss_group_id = group.entry_trade.id;
+        }
+    }
+
+    // Max drawdown over the equity curve of positions in entry order
+    let mut max_drawdown = 0.0;
+    let mut peak_equity = 0.0;
+    let mut running_equity = 0.0;
+    for group in position_groups.iter().rev() {
+        running_equity += group.total_pnl;
+        if running_equity > peak_equity {
+            peak_equity = running_equity;
+        }
+        max_drawdown = f64::max(max_drawdown, peak_equity - running_equity);
+    }
+
+    // Trading days: days with a fill plus days a pair closed, unless a strategy/asset/tag filter limits
+    // them to the filtered pairs' exit days
+    let mut daily: HashMap<String, (f64, i64)> = HashMap::new();
+    if !filter.has_trade_filter() {
+        for trade in &filled {
+            daily.entry(date_of(&trade.timestamp).to_string()).or_insert((0.0, 0)).1 += 1;
+        }
+    }
+
+    // Single pass over the pairs for the P&L aggregates
+    let mut winning_trades = 0;
+    let mut losing_trades = 0;
+    let mut total_profit_loss = 0.0;
+    let mut total_profit = 0.0;
+    let mut total_loss = 0.0;
+    let mut total_fees = 0.0;
+    let mut streaks = Streaks::default();
+    let mut strategy_streaks = Streaks::default();
+    let mut strategy_winning = 0;
+    let mut strategy_losing = 0;
+    let mut strategy_pnl = 0.0;
+    let mut total_holding_time_seconds = 0.0;
+    let mut holding_time_count = 0;
+    let mut winning_pct_gains = Vec::new();
+    let mut losing_pct_losses = Vec::new();
+    let mut largest_win_pct = 0.0;
+    let mut largest_loss_pct = 0.0;
+    let mut short_term = PairStats::default();
+    let mut long_term = PairStats::default();
+
+    for paired in &pairs {
+        let pnl = paired.net_profit_loss;
+        total_profit_loss += pnl;
+        total_fees += paired.entry_fees + paired.exit_fees;
+        if paired.holding_period == LONG_TERM { &mut long_term } else { &mut short_term }.record(pnl);
+        if pnl > 0.0 {
+            winning_trades += 1;
+            total_profit += pnl;
+        } else if pnl < 0.0 {
+            losing_trades += 1;
+            total_loss += pnl.abs();
+        }
+        streaks.record(pnl);
+
+        // Pairs carrying a strategy (all of them when filtering by strategy)
+        if filter.strategy_id.is_some() || paired.strategy_id.is_some() {
+            strategy_streaks.record(pnl);
+            if pnl > 0.0 {
+                strategy_winning += 1;
+            } else if pnl < 0.0 {
+                strategy_losing += 1;
+            }
+            strategy_pnl += pnl;
+        }
+
+        let (day_pnl, day_count) = daily.entry(date_of(&paired.exit_timestamp).to_string()).or_insert((0.0, 0));
+        *day_pnl += pnl;
+        if filter.has_trade_filter() {
+            *day_count += 1;
+        }
+
+        // Both ends in exchange time, so legacy naive stamps and offset-stamped ones measure alike
+        if let (Some(entry), Some(exit)) = (
+            trade_time_in(&paired.entry_timestamp, EXCHANGE_TIMEZONE),
+            trade_time_in(&paired.exit_timestamp, EXCHANGE_TIMEZONE),
+        ) {
+            let seconds = exit.signed_duration_since(entry).num_seconds();
+            if seconds >= 0 {
+                total_holding_time_seconds += seconds as f64;
+                holding_time_count += 1;
+            }
+        }
+
+        // Percentage move from entry to exit price, not P&L
+        if paired.entry_price > 0.0 {
+            let pct = ((paired.exit_price - paired.entry_price) / paired.entry_price) * 100.0;
+            if pnl > 0.0 {
+                winning_pct_gains.push(pct);
+                if pct > largest_win_pct {
+                    largest_win_pct = pct;
+                }
+            } else if pnl < 0.0 {
+                losing_pct_losses.push(pct);
+                if pct < largest_loss_pct {
+                    largest_loss_pct = pct;
+                }
+            }
+        }
+    }
+
+    let reviewed = reviewed_trade_ids(conn)?;
+    let reviewed_pairs = pairs
+        .iter()
+        .filter(|p| reviewed.contains(&p.entry_trade_id) || reviewed.contains(&p.exit_trade_id))
+        .count();
+
+    let checklist = checklist_compliance(conn, &pairs)?;
+    let r_values: Vec<f64> = r_multiples(conn, &pairs)?.into_iter().map(|(_, r)| r).collect();
+    let mut r_stats = PairStats::default();
+    for r in &r_values {
+        r_stats.record(*r);
+    }
+
+    let daily_pnl: Vec<DailyPnL> = daily
+        .into_iter()
+        .filter(|(date, _)| filter.in_range(date))
+        .map(|(date, (profit_loss, trade_count))| DailyPnL { date, profit_loss, trade_count })
+        .collect();
+    let ratios = risk_adjusted_ratios(conn, filter.paper_only, &daily_pnl, &account_daily_pnl)?;
+    let by_pnl = |a: &&DailyPnL, b: &&DailyPnL| a.profit_loss.partial_cmp(&b.profit_loss).unwrap_or(std::cmp::Ordering::Equal);
+    let best_day = daily_pnl.iter().max_by(by_pnl);
+    let worst_day = daily_pnl.iter().min_by(by_pnl);
+
+    let total_trades = pairs.len() as i64;
+    let pair_count = pairs.len() as f64;
+    let win_rate = ratio(winning_trades as f64, pair_count);
+    let average_profit = ratio(total_profit, winning_trades as f64);
+    let average_loss = ratio(total_loss, losing_trades as f64);
+    let loss_rate = ratio(losing_trades as f64, pair_count);
+
+    // Profit factor and risk/reward are reported as 0 when there are no losses to divide by
+    Ok(Metrics {
+        total_trades,
+        winning_trades,
+        losing_trades,
+        total_profit_loss,
+        win_rate,
+        average_profit,
+        average_loss,
+        largest_win,
+        largest_loss: largest_loss.unwrap_or(0.0),
+        total_volume,
+        trades_by_symbol: vec![],
+        consecutive_wins: streaks.max_wins,
+        consecutive_losses: streaks.max_losses,
+        current_win_streak: streaks.current_wins,
+        current_loss_streak: streaks.current_losses,
+        strategy_win_rate: ratio(strategy_winning as f64, (strategy_winning + strategy_losing) as f64),
+        strategy_winning_trades: strategy_winning,
+        strategy_losing_trades: strategy_losing,
+        strategy_profit_loss: strategy_pnl,
+        strategy_consecutive_wins: strategy_streaks.max_wins,
+        strategy_consecutive_losses: strategy_streaks.max_losses,
+        expectancy: (win_rate * average_profit) - (loss_rate * average_loss),
+        profit_factor: ratio(total_profit, total_loss),
+        average_trade: ratio(total_profit_loss, pair_count),
+        total_fees,
+        // net_profit_loss already has fees taken out
+        net_profit: total_profit_loss,
+        max_drawdown,
+        sharpe_ratio: ratios.sharpe,
+        sortino_ratio: ratios.sortino,
+        calmar_ratio: ratios.calmar,
+        risk_reward_ratio: ratio(average_profit, average_loss),
+        trades_per_day: ratio(total_trades as f64, daily_pnl.len() as f64),
+        best_day: best_day.map_or(0.0, |d| d.profit_loss),
+        worst_day: worst_day.map_or(0.0, |d| d.profit_loss),
+        best_day_date: best_day.map(|d| d.date.clone()),
+        worst_day_date: worst_day.map(|d| d.date.clone()),
+        largest_win_group_id,
+        largest_loss_group_id,
+        average_holding_time_seconds: ratio(total_holding_time_seconds, holding_time_count as f64),
+        average_gain_pct: ratio(winning_pct_gains.iter().sum::<f64>(), winning_pct_gains.len() as f64),
+        average_loss_pct: ratio(losing_pct_losses.iter().sum::<f64>(), losing_pct_losses.len() as f64),
+        largest_win_pct,
+        largest_loss_pct,
+        review_rate: ratio(reviewed_pairs as f64, pair_count),
+        checklist,
+        r_trades: r_stats.count,
+        average_r: ratio(r_stats.net_pnl, r_stats.count as f64),
+        r_expectancy: r_stats.expectancy(),
+        r_distribution: r_distribution(&r_values),
+        days_since_last_tilt,
+        profit_concentration: profit_concentration(&pairs, &daily_pnl, total_profit_loss),
+        risk_of_ruin: risk_of_ruin(conn, filter.paper_only, total_trades, win_rate, average_profit, average_loss, &account_daily_pnl)?,
+        short_term_trades: short_term.count,
+        short_term_pnl: short_term.net_pnl,
+        long_term_trades: long_term.count,
+        long_term_pnl: long_term.net_pnl,
+    })
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TagPerformance {
+    pub tag_id: i64,
+    pub tag: String,
+    pub color: Option<String>,
+    /// Closed pairs whose entry or exit trade carries the tag
+    pub trade_count: i64,
+    pub winning_trades: i64,
+    pub losing_trades: i64,
+    pub win_rate: f64,
+    pub expectancy: f64,
+    /// Average P&L per pair in R, where 1R is the average losing pair across the whole period (not
+    /// just this tag); None when the period has no losses
+    pub average_r: Option<f64>,
+    pub net_pnl: f64,
+}
+
+/// Win/loss totals over a subset of pairs (one tag, one setup, ...).
+#[derive(Default, Clone, Copy)]
+struct PairStats {
+    count: i64,
+    wins: i64,
+    losses: i64,
+    gross_profit: f64,
+    gross_loss: f64,
+    net_pnl: f64,
+}
+
+impl PairStats {
+    fn record(&mut self, pnl: f64) {
+        self.count += 1;
+        if pnl > 0.0 {
+            self.wins += 1;
+            self.gross_profit += pnl;
+        } else if pnl < 0.0 {
+            self.losses += 1;
+            self.gross_loss += -pnl;
+        }
+        self.net_pnl += pnl;
+    }
+
+    fn win_rate(&self) -> f64 {
+        ratio(self.wins as f64, self.count as f64)
+    }
+
+    /// (Win rate x average win) - (loss rate x average loss), as in get_metrics
+    fn expectancy(&self) -> f64 {
+        self.win_rate() * ratio(self.gross_profit, self.wins as f64)
+            - ratio(self.losses as f64, self.count as f64) * ratio(self.gross_loss, self.losses as f64)
+    }
+}
+
+/// Pairs closed inside the filter's date range (dates, paper and pairing method only).
+pub(crate) fn closed_pairs(conn: &Connection, filter: &MetricsFilter) -> Result<Vec<PairedTrade>, String> {
+    let filled: Vec<Trade> = load_trades(conn, filter.paper_only)?.into_iter().filter(is_filled).collect();
+    let pairing_context = PairingContext::load(conn)?;
+    let (all_pairs, _open_trades) = pair_trades(filled, pairing_is_fifo(conn, filter.pairing_method.as_deref()), &pairing_context);
+    Ok(all_pairs.into_iter().filter(|p| filter.in_range(&p.exit_timestamp)).collect())
+}
+
+/// Per-tag results over the pairs closed in the filter's date range. A pair counts toward every tag
+/// on its entry or exit trade. Sorted by net P&L, worst first, so the costly habits lead.
+pub fn tag_performance(conn: &Connection, filter: &MetricsFilter) -> Result<Vec<TagPerformance>, String> {
+    let pairs = closed_pairs(conn, filter)?;
+
+    let losses: Vec<f64> = pairs.iter().map(|p| p.net_profit_loss).filter(|pnl| *pnl < 0.0).collect();
+    let one_r = if losses.is_empty() { None } else { Some(-losses.iter().sum::<f64>() / losses.len() as f64) };
+
+    let mut tags_of_trade: HashMap<i64, Vec<i64>> = HashMap::new();
+    let mut stmt = conn.prepare("SELECT trade_id, tag_id FROM trade_tags").map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)))
+        .map_err(|e| e.to_string())?;
+    for row in rows {
+        let (trade_id, tag_id) = row.map_err(|e| e.to_string())?;
+        tags_of_trade.entry(trade_id).or_default().push(tag_id);
+    }
+
+    let mut totals: HashMap<i64, PairStats> = HashMap::new();
+    for pair in &pairs {
+        let mut pair_tags: Vec<i64> = [pair.entry_trade_id, pair.exit_trade_id]
+            .iter()
+            .filter_map(|id| tags_of_trade.get(id))
+            .flatten()
+            .copied()
+            .collect();
+        pair_tags.sort_unstable();
+        pair_tags.dedup();
+        for tag_id in pair_tags {
+            totals.entry(tag_id).or_default().record(pair.net_profit_loss);
+        }
+    }
+
+    let mut stmt = conn.prepare("SELECT id, name, color FROM tags").map_err(|e| e.to_string())?;
+    let tags = stmt
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, Option<String>>(2)?)))
+        .map_err(|e| e.to_string())?;
+    let mut performance = Vec::new();
+    for tag in tags {
+        let (tag_id, tag, color) = tag.map_err(|e| e.to_string())?;
+        let Some(stats) = totals.get(&tag_id) else {
+            continue;
+        };
+        performance.push(TagPerformance {
+            tag_id,
+            tag,
+            color,
+            trade_count: stats.count,
+            winning_trades: stats.wins,
+            losing_trades: stats.losses,
+            win_rate: stats.win_rate(),
+            expectancy: stats.expectancy(),
+            average_r: one_r.map(|r| stats.net_pnl / stats.count as f64 / r),
+            net_pnl: stats.net_pnl,
+        });
+    }
+    performance.sort_by(|a, b| a.net_pnl.partial_cmp(&b.net_pnl).unwrap_or(std::cmp::Ordering::Equal));
+    Ok(performance)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SetupPerformance {
+    pub setup_id: i64,
+    pub setup: String,
+    /// Closed pairs assigned to the setup
+    pub trade_count: i64,
+    pub winning_trades: i64,
+    pub losing_trades: i64,
+    pub win_rate: f64,
+    pub expectancy: f64,
+    pub average_pnl: f64,
+    pub net_pnl: f64,
+}
+
+/// Per-setup results over the pairs closed in the filter's date range, best expectancy first.
+/// Setups with no assigned pairs in the range are listed with zero counts.
+pub fn setup_performance(conn: &Connection, filter: &MetricsFilter) -> Result<Vec<SetupPerformance>, String> {
+    let pairs = closed_pairs(conn, filter)?;
+
+    let mut setup_of_pair: HashMap<(i64, i64), i64> = HashMap::new();
+    let mut stmt = conn
+        .prepare("SELECT entry_trade_id, exit_trade_id, setup_id FROM pair_setups")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| Ok(((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?), row.get::<_, i64>(2)?)))
+        .map_err(|e| e.to_string())?;
+    for row in rows {
+        let (pair_key, setup_id) = row.map_err(|e| e.to_string())?;
+        setup_of_pair.insert(pair_key, setup_id);
+    }
+
+    let mut totals: HashMap<i64, PairStats> = HashMap::new();
+    for pair in &pairs {
+        if let Some(setup_id) = setup_of_pair.get(&(pair.entry_trade_id, pair.exit_trade_id)) {
+            totals.entry(*setup_id).or_default().record(pair.net_profit_loss);
+        }
+    }
+
+    let mut stmt = conn.prepare("SELECT id, name FROM setups").map_err(|e| e.to_string())?;
+    let setups = stmt
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))
+        .map_err(|e| e.to_string())?;
+    let mut performance = Vec::new();
+    for setup in setups {
+        let (setup_id, setup) = setup.map_err(|e| e.to_string())?;
+        let stats = totals.get(&setup_id).copied().unwrap_or_default();
+        performance.push(SetupPerformance {
+            setup_id,
+            setup,
+            trade_count: stats.count,
+            winning_trades: stats.wins,
+            losing_trades: stats.losses,
+            win_rate: stats.win_rate(),
+            expectancy: stats.expectancy(),
+            average_pnl: ratio(stats.net_pnl, stats.count as f64),
+            net_pnl: stats.net_pnl,
+        });
+    }
+    performance.sort_by(|a, b| b.expectancy.partial_cmp(&a.expectancy).unwrap_or(std::cmp::Ordering::Equal));
+    Ok(performance)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PairRMultiple {
+    pub entry_trade_id: i64,
+    pub exit_trade_id: i64,
+    pub symbol: String,
+    pub exit_timestamp: String,
+    pub net_profit_loss: f64,
+    pub r_multiple: f64,
+}
+
+/// R-multiples of the pairs closed in the filter's date range that have a plan, oldest first.
+pub fn pair_r_multiples(conn: &Connection, filter: &MetricsFilter) -> Result<Vec<PairRMultiple>, String> {
+    let pairs = closed_pairs(conn, filter)?;
+    let mut result: Vec<PairRMultiple> = r_multiples(conn, &pairs)?
+        .into_iter()
+        .map(|(pair, r_multiple)| PairRMultiple {
+            entry_trade_id: pair.entry_trade_id,
+            exit_trade_id: pair.exit_trade_id,
+            symbol: pair.symbol.clone(),
+            exit_timestamp: pair.exit_timestamp.clone(),
+            net_profit_loss: pair.net_profit_loss,
+            r_multiple,
+        })
+        .collect();
+    result.sort_by(|a, b| a.exit_timestamp.cmp(&b.exit_timestamp));
+    Ok(result)
+}
+
+/// Slippage of a group of fills. Slippage is signed so that positive is adverse: paying more than
+/// intended on a buy or receiving less on a sell.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SlippageBucket {
+    pub key: String,
+    pub fills: i64,
+    /// Average slippage per unit, in price points
+    pub average_slippage: f64,
+    /// Average slippage as a percentage of the intended price
+    pub average_slippage_pct: f64,
+    /// Slippage in account currency (points x quantity x multiplier)
+    pub total_cost: f64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct StopAdherence {
+    /// Closed pairs whose entry trade had a planned stop
+    pub planned_trades: i64,
+    /// Pairs that exited beyond the planned stop
+    pub stop_overruns: i64,
+    pub adherence_rate: f64,
+    /// Loss beyond the stop across overruns, in account currency
+    pub overrun_cost: f64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SlippageReport {
+    pub overall: SlippageBucket,
+    pub by_symbol: Vec<SlippageBucket>,
+    /// Keyed by the hour of the fill timestamp, e.g. "09:00-09:59"
+    pub by_hour: Vec<SlippageBucket>,
+    pub by_order_type: Vec<SlippageBucket>,
+    pub stop_adherence: StopAdherence,
+}
+
+#[derive(Default)]
+struct SlippageTotals {
+    fills: i64,
+    points: f64,
+    pct: f64,
+    cost: f64,
+}
+
+impl SlippageTotals {
+    fn record(&mut self, points: f64, pct: f64, cost: f64) {
+        self.fills += 1;
+        self.points += points;
+        self.pct += pct;
+        self.cost += cost;
+    }
+
+    fn bucket(&self, key: String) -> SlippageBucket {
+        SlippageBucket {
+            key,
+            fills: self.fills,
+            average_slippage: ratio(self.points, self.fills as f64),
+            average_slippage_pct: ratio(self.pct, self.fills as f64),
+            total_cost: self.cost,
+        }
+    }
+}
+
+pub fn slippage_report(conn: &Connection, filter: &MetricsFilter) -> Result<SlippageReport, String> {
+    let specs = load_instrument_specs(conn)?;
+    let asset_classes = load_asset_classes(conn)?;
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT id, symbol, side, quantity, price, timestamp, order_type, intended_price FROM trades
+             WHERE intended_price IS NOT NULL AND intended_price > 0 AND status = 'Filled'{}
+             ORDER BY timestamp ASC",
+            paper_only_and_clause(filter.paper_only)
+        ))
+        .map_err(|e| e.to_string())?;
+    let fills = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, f64>(3)?,
+                row.get::<_, f64>(4)?,
+                row.get::<_, String>(5)?,
+                row.get::<_, String>(6)?,
+                row.get::<_, f64>(7)?,
+            ))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut overall = SlippageTotals::default();
+    let mut by_symbol: BTreeMap<String, SlippageTotals> = BTreeMap::new();
+    let mut by_hour: BTreeMap<u32, SlippageTotals> = BTreeMap::new();
+    let mut by_order_type: BTreeMap<String, SlippageTotals> = BTreeMap::new();
+    for (id, symbol, side, quantity, price, timestamp, order_type, intended) in fills {
+        if !filter.in_range(&timestamp) {
+            continue;
+        }
+        let points = if side.to_uppercase() == "BUY" { price - intended } else { intended - price };
+        let pct = points / intended * 100.0;
+        let multiplier = multiplier_for_asset_class(&symbol, asset_classes.get(&id).map(String::as_str), &specs);
+        let cost = points * quantity * multiplier;
+        overall.record(points, pct, cost);
+        by_symbol.entry(symbol).or_default().record(points, pct, cost);
+        if let Some(time) = trade_time_in(&timestamp, EXCHANGE_TIMEZONE) {
+            by_hour.entry(time.hour()).or_default().record(points, pct, cost);
+        }
+        by_order_type.entry(order_type.to_uppercase()).or_default().record(points, pct, cost);
+    }
+
+    Ok(SlippageReport {
+        overall: overall.bucket("All".to_string()),
+        by_symbol: by_symbol.into_iter().map(|(symbol, totals)| totals.bucket(symbol)).collect(),
+        by_hour: by_hour
+            .into_iter()
+            .map(|(hour, totals)| totals.bucket(format!("{:02}:00-{:02}:59", hour, hour)))
+            .collect(),
+        by_order_type: by_order_type.into_iter().map(|(order_type, totals)| totals.bucket(order_type)).collect(),
+        stop_adherence: stop_adherence(conn, filter, &specs, &asset_classes)?,
+    })
+}
+
+/// A long pair overran its stop when it exited below it, a short pair when it exited above it.
+fn stop_adherence(
+    conn: &Connection,
+    filter: &MetricsFilter,
+    specs: &HashMap<String, InstrumentSpec>,
+    asset_classes: &HashMap<i64, String>,
+) -> Result<StopAdherence, String> {
+    let mut stmt = conn
+        .prepare("SELECT p.trade_id, p.stop_price, t.side FROM trade_plans p JOIN trades t ON t.id = p.trade_id")
+        .map_err(|e| e.to_string())?;
+    let stops: HashMap<i64, (f64, bool)> = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, i64>(0)?, (row.get::<_, f64>(1)?, row.get::<_, String>(2)?.to_uppercase() == "BUY")))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<_, _>>()
+        .map_err(|e| e.to_string())?;
+    if stops.is_empty() {
+        return Ok(StopAdherence::default());
+    }
+
+    let mut adherence = StopAdherence::default();
+    for pair in closed_pairs(conn, filter)? {
+        let Some(&(stop, is_long)) = stops.get(&pair.entry_trade_id) else {
+            continue;
+        };
+        adherence.planned_trades += 1;
+        let overrun = if is_long { stop - pair.exit_price } else { pair.exit_price - stop };
+        if overrun > 0.0 {
+            let multiplier = multiplier_for_asset_class(&pair.symbol, asset_classes.get(&pair.entry_trade_id).map(String::as_str), specs);
+            adherence.stop_overruns += 1;
+            adherence.overrun_cost += overrun * pair.quantity * multiplier;
+        }
+    }
+    adherence.adherence_rate = ratio((adherence.planned_trades - adherence.stop_overruns) as f64, adherence.planned_trades as f64);
+    Ok(adherence)
+}
+
+/// Emotions that count toward tilt when logged at high intensity
+const NEGATIVE_EMOTIONS: [&str; 5] = ["Anxious", "Frustrated", "Fearful", "Greedy", "Pessimistic"];
+pub const DEFAULT_TILT_INTENSITY: i32 = 7;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TiltDay {
+    pub date: String,
+    /// Realized P&L of pairs closed that day
+    pub pnl: f64,
+    pub loss_limit_breached: bool,
+    /// Negative emotions logged that day at or above the intensity threshold
+    pub negative_emotions: Vec<String>,
+    pub max_negative_intensity: i32,
+    /// Loss limit breached and high-intensity negative emotions logged
+    pub is_tilt: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TiltReport {
+    /// Threshold of the enabled max_daily_loss rule; without one no day can be a tilt day
+    pub daily_loss_limit: Option<f64>,
+    pub min_intensity: i32,
+    /// Days that breached the limit or had high-intensity negative emotions, newest first
+    pub days: Vec<TiltDay>,
+    pub tilt_days: i64,
+    /// Average P&L of the tilt days in the range
+    pub average_tilt_day_pnl: f64,
+    pub last_tilt_date: Option<String>,
+    pub days_since_last_tilt: Option<i64>,
+}
+
+fn daily_loss_limit(conn: &Connection) -> Result<Option<f64>, String> {
+    Ok(load_risk_rules(conn)?
+        .into_iter()
+        .find(|rule| rule.kind == "max_daily_loss" && rule.enabled)
+        .map(|rule| rule.threshold))
+}
+
+/// Every day with a loss-limit breach or a high-intensity negative emotion, oldest first.
+fn tilt_days(conn: &Connection, pairs: &[PairedTrade], paper_only: Option<bool>, min_intensity: i32) -> Result<Vec<TiltDay>, String> {
+    let limit = daily_loss_limit(conn)?;
+
+    let mut day_pnl: BTreeMap<String, f64> = BTreeMap::new();
+    for pair in pairs {
+        *day_pnl.entry(date_of(&pair.exit_timestamp).to_string()).or_insert(0.0) += pair.net_profit_loss;
+    }
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT timestamp, emotion, intensity FROM emotional_states{}",
+            emotional_states_paper_clause(conn, paper_only)
+        ))
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, i32>(2)?)))
+        .map_err(|e| e.to_string())?;
+    let mut emotions: BTreeMap<String, (Vec<String>, i32)> = BTreeMap::new();
+    for row in rows {
+        let (timestamp, emotion, intensity) = row.map_err(|e| e.to_string())?;
+        let negative = NEGATIVE_EMOTIONS.iter().any(|e| e.eq_ignore_ascii_case(emotion.trim()));
+        if !negative || intensity < min_intensity {
+            continue;
+        }
+        let entry = emotions.entry(date_of(&timestamp).to_string()).or_default();
+        if !entry.0.contains(&emotion) {
+            entry.0.push(emotion);
+        }
+        entry.1 = entry.1.max(intensity);
+    }
+
+    let dates: BTreeSet<&String> = day_pnl.keys().chain(emotions.keys()).collect();
+    let mut days = Vec::new();
+    for date in dates {
+        let pnl = day_pnl.get(date).copied().unwrap_or(0.0);
+        let loss_limit_breached = limit.is_some_and(|limit| -pnl >= limit);
+        let (negative_emotions, max_negative_intensity) = emotions.get(date).cloned().unwrap_or_default();
+        if !loss_limit_breached && negative_emotions.is_empty() {
+            continue;
+        }
+        days.push(TiltDay {
+            date: date.clone(),
+            pnl,
+            loss_limit_breached,
+            is_tilt: loss_limit_breached && !negative_emotions.is_empty(),
+            negative_emotions,
+            max_negative_intensity,
+        });
+    }
+    Ok(days)
+}
+
+pub fn tilt_report(conn: &Connection, filter: &MetricsFilter, min_intensity: i32) -> Result<TiltReport, String> {
+    let filled: Vec<Trade> = load_trades(conn, filter.paper_only)?.into_iter().filter(is_filled).collect();
+    let use_fifo = pairing_is_fifo(conn, filter.pairing_method.as_deref());
+    let pairing_context = PairingContext::load(conn)?;
+    let (pairs, _open_trades) = pair_trades(filled, use_fifo, &pairing_context);
+    let all_days = tilt_days(conn, &pairs, filter.paper_only, min_intensity)?;
+
+    let last_tilt_date = all_days.iter().rev().find(|day| day.is_tilt).map(|day| day.date.clone());
+    let days_since_last_tilt = last_tilt_date
+        .as_deref()
+        .and_then(|date| chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+        .map(|last| (chrono::Local::now().date_naive() - last).num_days());
+
+    let start = filter.start_date.as_deref().map(date_of);
+    let end = filter.end_date.as_deref().map(date_of);
+    let mut days: Vec<TiltDay> = all_days
+        .into_iter()
+        .filter(|day| start.is_none_or(|s| day.date.as_str() >= s) && end.is_none_or(|e| day.date.as_str() <= e))
+        .collect();
+    days.reverse();
+    let tilt_pnl: Vec<f64> = days.iter().filter(|day| day.is_tilt).map(|day| day.pnl).collect();
+
+    Ok(TiltReport {
+        daily_loss_limit: daily_loss_limit(conn)?,
+        min_intensity,
+        tilt_days: tilt_pnl.len() as i64,
+        average_tilt_day_pnl: ratio(tilt_pnl.iter().sum(), tilt_pnl.len() as f64),
+        days,
+        last_tilt_date,
+        days_since_last_tilt,
+    })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalendarTrade {
+    pub entry_trade_id: i64,
+    pub exit_trade_id: i64,
+    pub symbol: String,
+    pub net_pnl: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CalendarDay {
+    pub date: String,
+    pub net_pnl: f64,
+    /// Pairs closed that day
+    pub trade_count: i64,
+    pub win_rate: f64,
+    pub best_trade: Option<CalendarTrade>,
+    pub worst_trade: Option<CalendarTrade>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PnlCalendar {
+    pub year: i32,
+    pub month: u32,
+    /// Every day of the month in order; days without closed pairs have a zero count
+    pub days: Vec<CalendarDay>,
+    pub net_pnl: f64,
+    pub trading_days: i64,
+    pub green_days: i64,
+    pub red_days: i64,
+}
+
+/// Per-day results for one month, by the exit date of each pair.
+pub fn pnl_calendar(conn: &Connection, filter: &MetricsFilter, year: i32, month: u32) -> Result<PnlCalendar, String> {
+    let first = chrono::NaiveDate::from_ymd_opt(year, month, 1).ok_or_else(|| format!("Invalid month {}-{}", year, month))?;
+    let prefix = first.format("%Y-%m").to_string();
+    let pairs: Vec<PairedTrade> = closed_pairs(conn, filter)?
+        .into_iter()
+        .filter(|p| p.exit_timestamp.starts_with(&prefix))
+        .collect();
+
+    let mut by_day: HashMap<&str, Vec<&PairedTrade>> = HashMap::new();
+    for pair in &pairs {
+        by_day.entry(date_of(&pair.exit_timestamp)).or_default().push(pair);
+    }
+    let to_calendar_trade = |pair: &PairedTrade| CalendarTrade {
+        entry_trade_id: pair.entry_trade_id,
+        exit_trade_id: pair.exit_trade_id,
+        symbol: pair.symbol.clone(),
+        net_pnl: pair.net_profit_loss,
+    };
+    let by_pnl = |a: &&&PairedTrade, b: &&&PairedTrade| {
+        a.net_profit_loss.partial_cmp(&b.net_profit_loss).unwrap_or(std::cmp::Ordering::Equal)
+    };
+
+    let mut calendar = PnlCalendar {
+        year,
+        month,
+        days: Vec::new(),
+        net_pnl: 0.0,
+        trading_days: 0,
+        green_days: 0,
+        red_days: 0,
+    };
+    let mut day = first;
+    while day.format("%Y-%m").to_string() == prefix {
+        let date = day.format("%Y-%m-%d").to_string();
+        let day_pairs = by_day.get(date.as_str()).map(Vec::as_slice).unwrap_or(&[]);
+        let mut stats = PairStats::default();
+        for pair in day_pairs {
+            stats.record(pair.net_profit_loss);
+        }
+        if stats.count > 0 {
+            calendar.trading_days += 1;
+            calendar.net_pnl += stats.net_pnl;
+            if stats.net_pnl > 0.0 {
+                calendar.green_days += 1;
+            } else if stats.net_pnl < 0.0 {
+                calendar.red_days += 1;
+            }
+        }
+        calendar.days.push(CalendarDay {
+            date,
+            net_pnl: stats.net_pnl,
+            trade_count: stats.count,
+            win_rate: stats.win_rate(),
+            best_trade: day_pairs.iter().max_by(by_pnl).map(|p| to_calendar_trade(p)),
+            worst_trade: day_pairs.iter().min_by(by_pnl).map(|p| to_calendar_trade(p)),
+        });
+        day = day.succ_opt().ok_or("Date out of range")?;
+    }
+    Ok(calendar)
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum PnlGranularity {
+    #[serde(alias = "week")]
+    Week,
+    #[serde(alias = "month")]
+    Month,
+    #[serde(alias = "quarter")]
+    Quarter,
+    #[serde(alias = "year")]
+    Year,
+}
+
+impl PnlGranularity {
+    /// Label and first day of the period containing `date`; weeks start on Monday.
+    fn period_of(self, date: chrono::NaiveDate) -> (String, chrono::NaiveDate) {
+        let first_of_month = |month: u32| chrono::NaiveDate::from_ymd_opt(date.year(), month, 1).unwrap_or(date);
+        match self {
+            PnlGranularity::Week => {
+                let monday = date - chrono::Duration::days(date.weekday().num_days_from_monday() as i64);
+                let week = date.iso_week();
+                (format!("{}-W{:02}", week.year(), week.week()), monday)
+            }
+            PnlGranularity::Month => (date.format("%Y-%m").to_string(), first_of_month(date.month())),
+            PnlGranularity::Quarter => {
+                let quarter = (date.month() - 1) / 3 + 1;
+                (format!("{}-Q{}", date.year(), quarter), first_of_month((quarter - 1) * 3 + 1))
+            }
+            PnlGranularity::Year => (date.year().to_string(), first_of_month(1)),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PeriodPnl {
+    /// "2025-W03", "2025-01", "2025-Q1" or "2025"
+    pub period: String,
+    pub start_date: String,
+    pub gross_pnl: f64,
+    pub fees: f64,
+    pub net_pnl: f64,
+    pub trade_count: i64,
+    pub winning_trades: i64,
+    pub losing_trades: i64,
+    pub win_rate: f64,
+}
+
+/// Pairs closed in the filter's date range rolled up by the period of their exit date, oldest first.
+pub fn periodic_pnl(conn: &Connection, filter: &MetricsFilter, granularity: PnlGranularity) -> Result<Vec<PeriodPnl>, String> {
+    let mut periods: BTreeMap<chrono::NaiveDate, (String, PairStats, f64, f64)> = BTreeMap::new();
+    for pair in closed_pairs(conn, filter)? {
+        let Ok(date) = chrono::NaiveDate::parse_from_str(date_of(&pair.exit_timestamp), "%Y-%m-%d") else {
+            continue;
+        };
+        let (label, start) = granularity.period_of(date);
+        let (_, stats, gross, fees) = periods.entry(start).or_insert_with(|| (label, PairStats::default(), 0.0, 0.0));
+        stats.record(pair.net_profit_loss);
+        *gross += pair.gross_profit_loss;
+        *fees += pair.entry_fees + pair.exit_fees;
+    }
+    Ok(periods
+        .into_iter()
+        .map(|(start, (period, stats, gross_pnl, fees))| PeriodPnl {
+            period,
+            start_date: start.format("%Y-%m-%d").to_string(),
+            gross_pnl,
+            fees,
+            net_pnl: stats.net_pnl,
+            trade_count: stats.count,
+            winning_trades: stats.wins,
+            losing_trades: stats.losses,
+            win_rate: stats.win_rate(),
+        })
+        .collect())
+}
+
+/// Wall-clock zone of stored timestamps that carry no real offset: naive times and the "Z"-stamped
+/// exchange times imports wrote before timestamps were localized are US/Eastern
+pub(crate) const EXCHANGE_TIMEZONE: chrono_tz::Tz = chrono_tz::America::New_York;
+
+/// A stored trade timestamp as a time in `tz`. Explicit non-UTC offsets are honored.
+pub(crate) fn trade_time_in(timestamp: &str, tz: chrono_tz::Tz) -> Option<chrono::DateTime<chrono_tz::Tz>> {
+    if !timestamp.ends_with('Z') {
+        if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(timestamp) {
+            return Some(dt.with_timezone(&tz));
+        }
+    }
+    let naive = parse_trade_datetime(timestamp)?;
+    let exchange = naive.and_local_timezone(EXCHANGE_TIMEZONE).earliest()?;
+    Some(exchange.with_timezone(&tz))
+}
+
+pub const PRE_MARKET: &str = "pre_market";
+pub const REGULAR_HOURS: &str = "regular";
+pub const AFTER_HOURS: &str = "after_hours";
+pub const TRADING_SESSIONS: [&str; 3] = [PRE_MARKET, REGULAR_HOURS, AFTER_HOURS];
+
+/// US equity session of a fill by New York exchange time: pre-market before the 9:30 open, regular
+/// hours until the 16:00 close, after-hours from then on. Date-only stamps count as regular hours.
+pub(crate) fn trading_session(timestamp: &str) -> &'static str {
+    let Some(time) = trade_time_in(timestamp, EXCHANGE_TIMEZONE) else {
+        return REGULAR_HOURS;
+    };
+    let minutes = time.hour() * 60 + time.minute();
+    if minutes < 9 * 60 + 30 {
+        PRE_MARKET
+    } else if minutes < 16 * 60 {
+        REGULAR_HOURS
+    } else {
+        AFTER_HOURS
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TimeBucket {
+    /// "Monday", "14:00-14:59" or "Friday 14:00-14:59"
+    pub label: String,
+    /// ISO weekday (1 = Monday) for weekday buckets
+    pub weekday: Option<u32>,
+    pub hour: Option<u32>,
+    pub trade_count: i64,
+    pub winning_trades: i64,
+    pub losing_trades: i64,
+    pub win_rate: f64,
+    pub net_pnl: f64,
+    pub average_pnl: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TimeOfDayStats {
+    pub timezone: String,
+    /// All seven weekdays, Monday first
+    pub by_weekday: Vec<TimeBucket>,
+    /// All 24 hours
+    pub by_hour: Vec<TimeBucket>,
+    /// Weekday x hour cells that had trades, e.g. to spot "Friday afternoons"
+    pub by_weekday_hour: Vec<TimeBucket>,
+}
+
+fn time_bucket(label: String, weekday: Option<u32>, hour: Option<u32>, stats: Option<&PairStats>) -> TimeBucket {
+    let empty = PairStats::default();
+    let stats = stats.unwrap_or(&empty);
+    TimeBucket {
+        label,
+        weekday,
+        hour,
+        trade_count: stats.count,
+        winning_trades: stats.wins,
+        losing_trades: stats.losses,
+        win_rate: stats.win_rate(),
+        net_pnl: stats.net_pnl,
+        average_pnl: ratio(stats.net_pnl, stats.count as f64),
+    }
+}
+
+/// Pairs closed in the filter's date range, bucketed by the weekday and hour of their entry in `tz`.
+pub fn time_of_day_stats(conn: &Connection, filter: &MetricsFilter, tz: chrono_tz::Tz) -> Result<TimeOfDayStats, String> {
+    let mut by_weekday: HashMap<u32, PairStats> = HashMap::new();
+    let mut by_hour: HashMap<u32, PairStats> = HashMap::new();
+    let mut by_weekday_hour: BTreeMap<(u32, u32), PairStats> = BTreeMap::new();
+    for pair in closed_pairs(conn, filter)? {
+        let Some(entry) = trade_time_in(&pair.entry_timestamp, tz) else {
+            continue;
+        };
+        let weekday = entry.weekday().number_from_monday();
+        let hour = entry.hour();
+        by_weekday.entry(weekday).or_default().record(pair.net_profit_loss);
+        by_hour.entry(hour).or_default().record(pair.net_profit_loss);
+        by_weekday_hour.entry((weekday, hour)).or_default().record(pair.net_profit_loss);
+    }
+
+    let weekday_name = |weekday: u32| {
+        ["Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday", "Sunday"][(weekday - 1) as usize]
+    };
+    let hour_label = |hour: u32| format!("{:02}:00-{:02}:59", hour, hour);
+    Ok(TimeOfDayStats {
+        timezone: tz.name().to_string(),
+        by_weekday: (1..=7)
+            .map(|d| time_bucket(weekday_name(d).to_string(), Some(d), None, by_weekday.get(&d)))
+            .collect(),
+        by_hour: (0..24).map(|h| time_bucket(hour_label(h), None, Some(h), by_hour.get(&h))).collect(),
+        by_weekday_hour: by_weekday_hour
+            .iter()
+            .map(|(&(d, h), stats)| time_bucket(format!("{} {}", weekday_name(d), hour_label(h)), Some(d), Some(h), Some(stats)))
+            .collect(),
+    })
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SessionStats {
+    /// "pre_market", "regular" or "after_hours"
+    pub session: String,
+    pub trade_count: i64,
+    pub winning_trades: i64,
+    pub losing_trades: i64,
+    pub win_rate: f64,
+    pub expectancy: f64,
+    pub gross_profit: f64,
+    pub gross_loss: f64,
+    pub profit_factor: f64,
+    pub net_pnl: f64,
+    pub average_pnl: f64,
+}
+
+/// Pairs closed in the filter's date range by the session their entry was taken in, all three
+/// sessions in trading-day order (empty ones included).
+pub fn session_stats(conn: &Connection, filter: &MetricsFilter) -> Result<Vec<SessionStats>, String> {
+    let mut by_session: HashMap<String, PairStats> = HashMap::new();
+    for pair in closed_pairs(conn, filter)? {
+        by_session.entry(pair.session.clone()).or_default().record(pair.net_profit_loss);
+    }
+    Ok(TRADING_SESSIONS
+        .iter()
+        .map(|session| {
+            let stats = by_session.get(*session).copied().unwrap_or_default();
+            SessionStats {
+                session: session.to_string(),
+                trade_count: stats.count,
+                winning_trades: stats.wins,
+                losing_trades: stats.losses,
+                win_rate: stats.win_rate(),
+                expectancy: stats.expectancy(),
+                gross_profit: stats.gross_profit,
+                gross_loss: stats.gross_loss,
+                profit_factor: ratio(stats.gross_profit, stats.gross_loss),
+                net_pnl: stats.net_pnl,
+                average_pnl: ratio(stats.net_pnl, stats.count as f64),
+            }
+        })
+        .collect())
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PnlDistribution {
+    pub bucket_size: f64,
+    /// Buckets [bin_start, bin_end) aligned to multiples of bucket_size, lowest first, including empty
+    /// ones between the extremes
+    pub buckets: Vec<HistogramBin>,
+    pub trade_count: i64,
+    pub mean: f64,
+    pub p5: f64,
+    pub p25: f64,
+    pub median: f64,
+    pub p75: f64,
+    pub p95: f64,
+}
+
+/// Linear-interpolated percentile of sorted values, `pct` in 0-100.
+fn percentile(sorted: &[f64], pct: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = pct / 100.0 * (sorted.len() - 1) as f64;
+    let (lower, upper) = (rank.floor() as usize, rank.ceil() as usize);
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f64)
+}
+
+/// Round a raw bucket width up to 1, 2 or 5 x 10^n so bucket edges are readable.
+fn nice_bucket_size(raw: f64) -> f64 {
+    if raw <= 0.0 || !raw.is_finite() {
+        return 1.0;
+    }
+    let magnitude = 10f64.powf(raw.log10().floor());
+    [1.0, 2.0, 5.0, 10.0].iter().map(|step| step * magnitude).find(|size| *size >= raw).unwrap_or(10.0 * magnitude)
+}
+
+/// Histogram and percentiles of net P&L over the pairs closed in the filter's date range. Without a
+/// bucket size the range is split into about 20 buckets of a round width.
+pub fn pnl_distribution(conn: &Connection, filter: &MetricsFilter, bucket_size: Option<f64>) -> Result<PnlDistribution, String> {
+    if let Some(size) = bucket_size {
+        if !size.is_finite() || size <= 0.0 {
+            return Err("Bucket size must be a positive number".to_string());
+        }
+    }
+    let mut pnls: Vec<f64> = closed_pairs(conn, filter)?.iter().map(|p| p.net_profit_loss).collect();
+    pnls.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    let (min, max) = (pnls.first().copied().unwrap_or(0.0), pnls.last().copied().unwrap_or(0.0));
+    let bucket_size = bucket_size.unwrap_or_else(|| nice_bucket_size((max - min) / 20.0));
+
+    let mut buckets: Vec<HistogramBin> = Vec::new();
+    if !pnls.is_empty() {
+        let first = (min / bucket_size).floor() as i64;
+        let last = (max / bucket_size).floor() as i64;
+        if last - first > 10_000 {
+            return Err("Bucket size is too small for the P&L range".to_string());
+        }
+        buckets = (first..=last)
+            .map(|i| HistogramBin {
+                bin_start: i as f64 * bucket_size,
+                bin_end: (i + 1) as f64 * bucket_size,
+                count: 0,
+                total_pnl: 0.0,
+            })
+            .collect();
+        for pnl in &pnls {
+            let bucket = &mut buckets[((pnl / bucket_size).floor() as i64 - first) as usize];
+            bucket.count += 1;
+            bucket.total_pnl += pnl;
+        }
+    }
+
+    Ok(PnlDistribution {
+        bucket_size,
+        buckets,
+        trade_count: pnls.len() as i64,
+        mean: ratio(pnls.iter().sum(), pnls.len() as f64),
+        p5: percentile(&pnls, 5.0),
+        p25: percentile(&pnls, 25.0),
+        median: percentile(&pnls, 50.0),
+        p75: percentile(&pnls, 75.0),
+        p95: percentile(&pnls, 95.0),
+    })
+}
+
+/// Running totals of one strategy's pairs in strategy_performance
+#[derive(Default)]
+struct StrategyTotals {
+    stats: PairStats,
+    volume: f64,
+    equity: f64,
+    peak: f64,
+    max_drawdown: f64,
+    /// R multiples of the pairs that have a planned risk
+    r_stats: PairStats,
+}
+
+/// Pairs closed in the filter's date range grouped by resolved strategy, unassigned pairs last.
+pub fn strategy_performance(conn: &Connection, filter: &MetricsFilter) -> Result<Vec<StrategyPerformance>, String> {
+    let all_trades = load_trades(conn, filter.paper_only)?;
+    let filled: Vec<Trade> = all_trades.iter().filter(|t| is_filled(t)).cloned().collect();
+    let use_fifo = pairing_is_fifo(conn, filter.pairing_method.as_deref());
+    let pairing_context = PairingContext::load(conn)?;
+    let (all_pairs, _open_trades) = pair_trades(filled.clone(), use_fifo, &pairing_context);
+    let in_range_filled: Vec<Trade> = filled.into_iter().filter(|t| filter.in_range(&t.timestamp)).collect();
+    let trade_to_position_strategy = position_strategies(&group_positions(&in_range_filled, &all_pairs));
+    let entry_trade_strategies: HashMap<i64, Option<i64>> =
+        all_trades.iter().filter_map(|t| t.id.map(|id| (id, t.strategy_id))).collect();
+
+    let mut pairs: Vec<PairedTrade> = all_pairs.into_iter().filter(|p| filter.in_range(&p.exit_timestamp)).collect();
+    pairs.sort_by(|a, b| a.exit_timestamp.cmp(&b.exit_timestamp));
+    let r_by_pair: HashMap<(i64, i64), f64> = r_multiples(conn, &pairs)?
+        .into_iter()
+        .map(|(pair, r)| ((pair.entry_trade_id, pair.exit_trade_id), r))
+        .collect();
+
+    let mut stmt = conn.prepare("SELECT id, name FROM strategies").map_err(|e| e.to_string())?;
+    let strategy_names: HashMap<i64, String> = stmt
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<_, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut totals: HashMap<Option<i64>, StrategyTotals> = HashMap::new();
+    for pair in &pairs {
+        let strategy_id = resolve_pair_strategy(pair, &trade_to_position_strategy, &entry_trade_strategies);
+        if filter.strategy_id.is_some() && strategy_id != filter.strategy_id {
+            continue;
+        }
+        let t = totals.entry(strategy_id).or_default();
+        t.stats.record(pair.net_profit_loss);
+        t.volume += pair.quantity * pair.entry_price;
+        t.equity += pair.net_profit_loss;
+        t.peak = f64::max(t.peak, t.equity);
+        t.max_drawdown = f64::max(t.max_drawdown, t.peak - t.equity);
+        if let Some(r) = r_by_pair.get(&(pair.entry_trade_id, pair.exit_trade_id)) {
+            t.r_stats.record(*r);
+        }
+    }
+
+    let mut performance: Vec<StrategyPerformance> = totals
+        .into_iter()
+        .map(|(strategy_id, StrategyTotals { stats, volume: total_volume, max_drawdown, r_stats, .. })| StrategyPerformance {
+            strategy_id,
+            strategy_name: match strategy_id {
+                Some(id) => strategy_names.get(&id).cloned().unwrap_or_else(|| "Unknown".to_string()),
+                None => "Unassigned".to_string(),
+            },
+            trade_count: stats.count,
+            winning_trades: stats.wins,
+            total_volume,
+            estimated_pnl: stats.net_pnl,
+            losing_trades: stats.losses,
+            win_rate: stats.win_rate(),
+            expectancy: stats.expectancy(),
+            profit_factor: ratio(stats.gross_profit, stats.gross_loss),
+            max_drawdown,
+            average_r: (r_stats.count > 0).then(|| r_stats.net_pnl / r_stats.count as f64),
+        })
+        .collect();
+    performance.sort_by(|a, b| {
+        a.strategy_id.is_none().cmp(&b.strategy_id.is_none()).then(b.trade_count.cmp(&a.trade_count))
+    });
+    Ok(performance)
+}
+
+pub const DEFAULT_EMOTION_WINDOW_MINUTES: i64 = 60;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EmotionPerformance {
+    pub emotion: String,
+    /// Logged states of this emotion in the range
+    pub state_count: i64,
+    pub average_intensity: f64,
+    /// States linked to trades explicitly (trade_id / trade_ids) vs. matched by time
+    pub linked_states: i64,
+    pub proximity_states: i64,
+    /// Distinct closed pairs attributed to the emotion
+    pub trade_count: i64,
+    pub winning_trades: i64,
+    pub losing_trades: i64,
+    pub win_rate: f64,
+    pub net_pnl: f64,
+    pub average_pnl: f64,
+}
+
+/// Instant of an emotional state. The journal stores real UTC ISO strings; naive times are read
+/// like trade times.
+fn state_time(timestamp: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+    chrono::DateTime::parse_from_rfc3339(timestamp)
+        .map(|dt| dt.with_timezone(&chrono::Utc))
+        .ok()
+        .or_else(|| trade_time_in(timestamp, chrono_tz::UTC).map(|dt| dt.with_timezone(&chrono::Utc)))
+}
+
+/// Trade ids a state is linked to through `trade_id` and the `trade_ids` JSON array.
+fn linked_trade_ids(trade_id: Option<i64>, trade_ids: Option<&str>) -> Vec<i64> {
+    let mut ids: Vec<i64> = trade_id.into_iter().collect();
+    if let Some(serde_json::Value::Array(values)) = trade_ids.and_then(|json| serde_json::from_str(json).ok()) {
+        ids.extend(values.iter().filter_map(|v| v.as_i64().or_else(|| v.as_str().and_then(|s| s.trim().parse().ok()))));
+    }
+    ids
+}
+
+/// Per-emotion results over pairs closed in the filter's range. A state counts toward the pairs whose
+/// entry or exit trade it is linked to; a state with no trade links counts toward pairs entered or
+/// exited within `window_minutes` of it. Survey-only states (intensity 0) are skipped.
+pub fn emotion_performance(conn: &Connection, filter: &MetricsFilter, window_minutes: i64) -> Result<Vec<EmotionPerformance>, String> {
+    let pairs = closed_pairs(conn, filter)?;
+    let window = chrono::Duration::minutes(window_minutes.max(0));
+    // Entry and exit time of each pair
+    let pair_times: Vec<[Option<chrono::DateTime<chrono::Utc>>; 2]> = pairs
+        .iter()
+        .map(|p| {
+            let at = |ts: &str| trade_time_in(ts, chrono_tz::UTC).map(|dt| dt.with_timezone(&chrono::Utc));
+            [at(&p.entry_timestamp), at(&p.exit_timestamp)]
+        })
+        .collect();
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT timestamp, emotion, intensity, trade_id, trade_ids FROM emotional_states{}",
+            emotional_states_paper_clause(conn, filter.paper_only)
+        ))
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, i32>(2)?,
+                row.get::<_, Option<i64>>(3)?,
+                row.get::<_, Option<String>>(4).ok().flatten(),
+            ))
+        })
+        .map_err(|e| e.to_string())?;
+
+    // Emotion -> (states, intensity sum, linked states, proximity states, attributed pair indexes)
+    let mut totals: BTreeMap<String, (i64, i64, i64, i64, BTreeSet<usize>)> = BTreeMap::new();
+    for row in rows {
+        let (timestamp, emotion, intensity, trade_id, trade_ids) = row.map_err(|e| e.to_string())?;
+        let emotion = emotion.trim().to_string();
+        if intensity <= 0 || emotion.is_empty() {
+            continue;
+        }
+        let linked = linked_trade_ids(trade_id, trade_ids.as_deref());
+        let matched: Vec<usize> = if !linked.is_empty() {
+            (0..pairs.len())
+                .filter(|&i| linked.contains(&pairs[i].entry_trade_id) || linked.contains(&pairs[i].exit_trade_id))
+                .collect()
+        } else if let Some(at) = state_time(&timestamp) {
+            let near = |time: Option<chrono::DateTime<chrono::Utc>>| time.is_some_and(|t| (t - at).abs() <= window);
+            (0..pairs.len()).filter(|&i| pair_times[i].iter().any(|&time| near(time))).collect()
+        } else {
+            Vec::new()
+        };
+        // A state outside the range with no pairs in it would only skew intensity
+        if matched.is_empty() && !filter.in_range(&timestamp) {
+            continue;
+        }
+        let entry = totals.entry(emotion).or_default();
+        entry.0 += 1;
+        entry.1 += intensity as i64;
+        if linked.is_empty() {
+            entry.3 += 1;
+        } else {
+            entry.2 += 1;
+        }
+        entry.4.extend(matched);
+    }
+
+    let mut performance: Vec<EmotionPerformance> = totals
+        .into_iter()
+        .map(|(emotion, (state_count, intensity_sum, linked_states, proximity_states, pair_indexes))| {
+            let mut stats = PairStats::default();
+            for i in pair_indexes {
+                stats.record(pairs[i].net_profit_loss);
+            }
+            EmotionPerformance {
+                emotion,
+                state_count,
+                average_intensity: ratio(intensity_sum as f64, state_count as f64),
+                linked_states,
+                proximity_states,
+                trade_count: stats.count,
+                winning_trades: stats.wins,
+                losing_trades: stats.losses,
+                win_rate: stats.win_rate(),
+                net_pnl: stats.net_pnl,
+                average_pnl: ratio(stats.net_pnl, stats.count as f64),
+            }
+        })
+        .collect();
+    performance.sort_by(|a, b| a.net_pnl.partial_cmp(&b.net_pnl).unwrap_or(std::cmp::Ordering::Equal));
+    Ok(performance)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MoodDay {
+    /// Exchange-local date, the same calendar as the daily P&L series
+    pub date: String,
+    pub pnl: f64,
+    pub trade_count: i64,
+    pub state_count: i64,
+    pub average_intensity: f64,
+    pub max_intensity: i32,
+    /// Highest intensity among negative emotions that day, 0 when none were logged
+    pub max_negative_intensity: i32,
+    /// Most-logged emotion of the day, ties going to the higher total intensity
+    pub dominant_emotion: Option<String>,
+    pub emotions: Vec<String>,
+}
+
+struct LoggedState {
+    time: chrono::DateTime<chrono::Utc>,
+    timestamp: String,
+    emotion: String,
+    intensity: i32,
+}
+
+/// Emotional states with a readable time, oldest first. Survey-only states (intensity 0) are skipped.
+fn logged_states(conn: &Connection, paper_only: Option<bool>) -> Result<Vec<LoggedState>, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT timestamp, emotion, intensity FROM emotional_states{}",
+            emotional_states_paper_clause(conn, paper_only)
+        ))
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, i32>(2)?)))
+        .map_err(|e| e.to_string())?;
+    let mut states = Vec::new();
+    for row in rows {
+        let (timestamp, emotion, intensity) = row.map_err(|e| e.to_string())?;
+        let emotion = emotion.trim().to_string();
+        if intensity <= 0 || emotion.is_empty() {
+            continue;
+        }
+        if let Some(time) = state_time(&timestamp) {
+            states.push(LoggedState { time, timestamp, emotion, intensity });
+        }
+    }
+    states.sort_by_key(|state| state.time);
+    Ok(states)
+}
+
+fn is_negative_emotion(emotion: &str) -> bool {
+    NEGATIVE_EMOTIONS.iter().any(|e| e.eq_ignore_ascii_case(emotion))
+}
+
+/// One row per day with closed pairs or logged emotions. State times are converted to exchange time
+/// so a late-evening UTC entry lands on the same day as that session's trades.
+pub fn mood_timeline(conn: &Connection, filter: &MetricsFilter) -> Result<Vec<MoodDay>, String> {
+    let mut day_pnl: BTreeMap<String, PairStats> = BTreeMap::new();
+    for pair in closed_pairs(conn, filter)? {
+        day_pnl.entry(date_of(&pair.exit_timestamp).to_string()).or_default().record(pair.net_profit_loss);
+    }
+
+    let start = filter.start_date.as_deref().map(date_of);
+    let end = filter.end_date.as_deref().map(date_of);
+    let mut day_states: BTreeMap<String, Vec<LoggedState>> = BTreeMap::new();
+    for state in logged_states(conn, filter.paper_only)? {
+        let date = state.time.with_timezone(&EXCHANGE_TIMEZONE).format("%Y-%m-%d").to_string();
+        if start.is_none_or(|s| date.as_str() >= s) && end.is_none_or(|e| date.as_str() <= e) {
+            day_states.entry(date).or_default().push(state);
+        }
+    }
+
+    let dates: BTreeSet<&String> = day_pnl.keys().chain(day_states.keys()).collect();
+    Ok(dates
+        .into_iter()
+        .map(|date| {
+            let stats = day_pnl.get(date).copied().unwrap_or_default();
+            let states = day_states.get(date).map(Vec::as_slice).unwrap_or(&[]);
+            let mut by_emotion: BTreeMap<&str, (i64, i64)> = BTreeMap::new();
+            for state in states {
+                let entry = by_emotion.entry(state.emotion.as_str()).or_default();
+                entry.0 += 1;
+                entry.1 += state.intensity as i64;
+            }
+            MoodDay {
+                date: date.clone(),
+                pnl: stats.net_pnl,
+                trade_count: stats.count,
+                state_count: states.len() as i64,
+                average_intensity: ratio(states.iter().map(|s| s.intensity as f64).sum(), states.len() as f64),
+                max_intensity: states.iter().map(|s| s.intensity).max().unwrap_or(0),
+                max_negative_intensity: states
+                    .iter()
+                    .filter(|s| is_negative_emotion(&s.emotion))
+                    .map(|s| s.intensity)
+                    .max()
+                    .unwrap_or(0),
+                dominant_emotion: by_emotion.iter().max_by_key(|(_, totals)| **totals).map(|(emotion, _)| emotion.to_string()),
+                emotions: by_emotion.keys().map(|emotion| emotion.to_string()).collect(),
+            }
+        })
+        .collect())
+}
+
+pub const DEFAULT_MOOD_STREAK: usize = 3;
+/// Trades at or above this multiple of the median trade size count as oversized
+pub const DEFAULT_OVERSIZE_MULTIPLE: f64 = 1.5;
+/// How long after a streak its follow-up trades are looked for, and the largest gap inside a streak
+const MOOD_STREAK_HOURS: i64 = 24;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OversizedTrade {
+    pub trade_id: i64,
+    pub symbol: String,
+    pub side: String,
+    pub timestamp: String,
+    pub notional: f64,
+    /// Notional divided by the median notional of all opening trades
+    pub size_multiple: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MoodStreak {
+    pub started_at: String,
+    pub ended_at: String,
+    pub entries: i64,
+    pub emotions: Vec<String>,
+    pub max_intensity: i32,
+    /// Opening trades within 24 hours after the streak's last entry
+    pub trades_after: i64,
+    pub oversized_trades: Vec<OversizedTrade>,
+    /// Net P&L of the pairs opened by the trades after the streak
+    pub pnl_after: f64,
+    /// The streak was followed by at least one oversized trade
+    pub flagged: bool,
+}
+
+/// Runs of `min_streak`+ consecutive high-intensity negative entries (no other entry in between and
+/// no more than 24 hours between neighbours), each with the opening trades that followed it within 24
+/// hours. Trades at `oversize_multiple` times the median opening notional or more flag the streak.
+pub fn mood_streaks(
+    conn: &Connection,
+    filter: &MetricsFilter,
+    min_intensity: i32,
+    min_streak: usize,
+    oversize_multiple: f64,
+) -> Result<Vec<MoodStreak>, String> {
+    let filled: Vec<Trade> = load_trades(conn, filter.paper_only)?.into_iter().filter(is_filled).collect();
+    let use_fifo = pairing_is_fifo(conn, filter.pairing_method.as_deref());
+    let pairing_context = PairingContext::load(conn)?;
+    let (pairs, open_trades) = pair_trades(filled.clone(), use_fifo, &pairing_context);
+    let mut opening_pnl: HashMap<i64, f64> = HashMap::new();
+    for pair in &pairs {
+        *opening_pnl.entry(pair.entry_trade_id).or_insert(0.0) += pair.net_profit_loss;
+    }
+    let open_ids: HashSet<i64> = open_trades.iter().filter_map(|t| t.id).collect();
+    let opening: Vec<(chrono::DateTime<chrono::Utc>, &Trade, f64)> = filled
+        .iter()
+        .filter(|t| t.id.is_some_and(|id| opening_pnl.contains_key(&id) || open_ids.contains(&id)))
+        .filter_map(|t| {
+            let time = trade_time_in(&t.timestamp, chrono_tz::UTC)?.with_timezone(&chrono::Utc);
+            Some((time, t, t.quantity.abs() * t.price))
+        })
+        .collect();
+    let mut notionals: Vec<f64> = opening.iter().map(|(_, _, notional)| *notional).collect();
+    notionals.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    let median = percentile(&notionals, 50.0);
+
+    let states = logged_states(conn, filter.paper_only)?;
+    let max_gap = chrono::Duration::hours(MOOD_STREAK_HOURS);
+    let mut runs: Vec<&[LoggedState]> = Vec::new();
+    let mut run_start = 0;
+    for i in 0..=states.len() {
+        let continues = i < states.len()
+            && is_negative_emotion(&states[i].emotion)
+            && states[i].intensity >= min_intensity
+            && (i == run_start || states[i].time - states[i - 1].time <= max_gap);
+        if continues {
+            continue;
+        }
+        if i - run_start >= min_streak.max(1) {
+            runs.push(&states[run_start..i]);
+        }
+        run_start = if i < states.len() && is_negative_emotion(&states[i].emotion) && states[i].intensity >= min_intensity {
+            i
+        } else {
+            i + 1
+        };
+    }
+
+    let mut streaks = Vec::new();
+    for run in runs {
+        let (first, last) = (&run[0], &run[run.len() - 1]);
+        if !filter.in_range(&last.timestamp) {
+            continue;
+        }
+        let after: Vec<&(chrono::DateTime<chrono::Utc>, &Trade, f64)> = opening
+            .iter()
+            .filter(|(time, _, _)| *time >= last.time && *time - last.time <= max_gap)
+            .collect();
+        let oversized_trades: Vec<OversizedTrade> = after
+            .iter()
+            .filter(|(_, _, notional)| median > 0.0 && *notional >= median * oversize_multiple)
+            .map(|(_, trade, notional)| OversizedTrade {
+                trade_id: trade.id.unwrap_or_default(),
+                symbol: trade.symbol.clone(),
+                side: trade.side.clone(),
+                timestamp: trade.timestamp.clone(),
+                notional: *notional,
+                size_multiple: notional / median,
+            })
+            .collect();
+        let mut emotions: Vec<String> = run.iter().map(|s| s.emotion.clone()).collect();
+        emotions.sort();
+        emotions.dedup();
+        streaks.push(MoodStreak {
+            started_at: first.timestamp.clone(),
+            ended_at: last.timestamp.clone(),
+            entries: run.len() as i64,
+            emotions,
+            max_intensity: run.iter().map(|s| s.intensity).max().unwrap_or(0),
+            trades_after: after.len() as i64,
+            pnl_after: after
+                .iter()
+                .filter_map(|(_, trade, _)| trade.id.and_then(|id| opening_pnl.get(&id)))
+                .fold(0.0, |total, pnl| total + pnl),
+            flagged: !oversized_trades.is_empty(),
+            oversized_trades,
+        });
+    }
+    streaks.reverse();
+    Ok(streaks)
+}
+
+/// One pair waiting for MAE/MFE: its bar window (epoch seconds) and how to price the excursion.
+pub(crate) struct ExcursionJob {
+    pub pair: PairedTrade,
+    pub is_long: bool,
+    pub multiplier: f64,
+    pub interval: String,
+    pub start: i64,
+    pub end: i64,
+}
+
+/// Finest bar size Yahoo still serves for a pair: 5m for short holds in the last ~2 months, hourly
+/// for holds up to two months in the last ~2 years, daily otherwise.
+fn default_bar_interval(start: i64, end: i64) -> &'static str {
+    let age_days = (chrono::Utc::now().timestamp() - start) / 86_400;
+    let hold_days = (end - start) / 86_400;
+    if age_days <= 55 && hold_days <= 3 {
+        "5m"
+    } else if age_days <= 700 && hold_days <= 60 {
+        "1h"
+    } else {
+        "1d"
+    }
+}
+
+/// Pairs closed in the filter's range that need MAE/MFE, plus how many were skipped because they
+/// already have it. `interval` overrides the per-pair default bar size.
+pub(crate) fn excursion_jobs(
+    conn: &Connection,
+    filter: &MetricsFilter,
+    interval: Option<&str>,
+    recompute: bool,
+) -> Result<(Vec<ExcursionJob>, usize), String> {
+    let mut stmt = conn
+        .prepare("SELECT entry_trade_id, exit_trade_id FROM pair_excursions")
+        .map_err(|e| e.to_string())?;
+    let done: HashSet<(i64, i64)> = stmt
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<_, _>>()
+        .map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare("SELECT id, side FROM trades").map_err(|e| e.to_string())?;
+    let sides: HashMap<i64, String> = stmt
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<_, _>>()
+        .map_err(|e| e.to_string())?;
+    let specs = load_instrument_specs(conn)?;
+    let asset_classes = load_asset_classes(conn)?;
+
+    let mut jobs = Vec::new();
+    let mut skipped = 0;
+    for pair in closed_pairs(conn, filter)? {
+        if !recompute && done.contains(&(pair.entry_trade_id, pair.exit_trade_id)) {
+            skipped += 1;
+            continue;
+        }
+        let epoch = |ts: &str| trade_time_in(ts, chrono_tz::UTC).map(|dt| dt.timestamp());
+        let (Some(start), Some(end)) = (epoch(&pair.entry_timestamp), epoch(&pair.exit_timestamp)) else {
+            continue;
+        };
+        let asset_class = asset_classes.get(&pair.entry_trade_id).map(String::as_str);
+        jobs.push(ExcursionJob {
+            is_long: sides.get(&pair.entry_trade_id).is_none_or(|side| side.to_uppercase() == "BUY"),
+            multiplier: multiplier_for_asset_class(&pair.symbol, asset_class, &specs),
+            interval: interval.unwrap_or_else(|| default_bar_interval(start, end.max(start))).to_string(),
+            start,
+            end: end.max(start),
+            pair,
+        });
+    }
+    Ok((jobs, skipped))
+}
+
+/// Store MAE/MFE for a pair from the bars spanning its holding period. The fills themselves count as
+/// observed prices, and the entry and exit bars are used whole, so moves earlier in the entry bar or
+/// later in the exit bar can widen the excursion slightly. Returns false when there are no bars.
+pub(crate) fn record_excursion(conn: &Connection, job: &ExcursionJob, bars: &[crate::marketdata::Bar]) -> Result<bool, String> {
+    if bars.is_empty() {
+        return Ok(false);
+    }
+    let pair = &job.pair;
+    let high = bars.iter().map(|b| b.high).fold(pair.entry_price.max(pair.exit_price), f64::max);
+    let low = bars.iter().map(|b| b.low).fold(pair.entry_price.min(pair.exit_price), f64::min);
+    let (mae, mfe) = if job.is_long {
+        (pair.entry_price - low, high - pair.entry_price)
+    } else {
+        (high - pair.entry_price, pair.entry_price - low)
+    };
+    let scale = pair.quantity * job.multiplier;
+    conn.execute(
+        "INSERT OR REPLACE INTO pair_excursions (entry_trade_id, exit_trade_id, interval, bars, mae, mfe, mae_amount, mfe_amount, computed_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, CURRENT_TIMESTAMP)",
+        rusqlite::params![pair.entry_trade_id, pair.exit_trade_id, job.interval, bars.len() as i64, mae, mfe, mae * scale, mfe * scale],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PairExcursion {
+    pub entry_trade_id: i64,
+    pub exit_trade_id: i64,
+    pub symbol: String,
+    pub exit_timestamp: String,
+    pub net_profit_loss: f64,
+    pub interval: String,
+    /// Largest move against / in favour of the position, in price and in account currency
+    pub mae: f64,
+    pub mfe: f64,
+    pub mae_amount: f64,
+    pub mfe_amount: f64,
+    /// Gross P&L as a fraction of the MFE: how much of the best move was kept
+    pub mfe_captured: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SetupExcursion {
+    /// None for pairs without a setup
+    pub setup_id: Option<i64>,
+    pub setup: String,
+    pub trade_count: i64,
+    pub average_mae_amount: f64,
+    pub average_mfe_amount: f64,
+    /// Heat winners took before working: a stop tighter than this would have cut them
+    pub winners_average_mae_amount: f64,
+    pub winners_max_mae_amount: f64,
+    /// Open profit losers gave back
+    pub losers_average_mfe_amount: f64,
+    pub average_mfe_captured: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExcursionReport {
+    /// Newest exit first
+    pub pairs: Vec<PairExcursion>,
+    pub by_setup: Vec<SetupExcursion>,
+    /// Closed pairs in the range without MAE/MFE yet (see backfill_excursions)
+    pub missing_pairs: i64,
+}
+
+/// A pair_excursions row: (interval, mae, mfe, mae_amount, mfe_amount)
+type StoredExcursion = (String, f64, f64, f64, f64);
+
+/// MAE/MFE of the pairs closed in the filter's range, per pair and per setup.
+pub fn excursion_report(conn: &Connection, filter: &MetricsFilter) -> Result<ExcursionReport, String> {
+    let pairs = closed_pairs(conn, filter)?;
+    let mut stmt = conn
+        .prepare("SELECT entry_trade_id, exit_trade_id, interval, mae, mfe, mae_amount, mfe_amount FROM pair_excursions")
+        .map_err(|e| e.to_string())?;
+    let excursions: HashMap<(i64, i64), StoredExcursion> = stmt
+        .query_map([], |row| {
+            Ok(((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?), (row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?, row.get(6)?)))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<_, _>>()
+        .map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare("SELECT ps.entry_trade_id, ps.exit_trade_id, s.id, s.name FROM pair_setups ps JOIN setups s ON s.id = ps.setup_id")
+        .map_err(|e| e.to_string())?;
+    let setup_of_pair: HashMap<(i64, i64), (i64, String)> = stmt
+        .query_map([], |row| Ok(((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?), (row.get::<_, i64>(2)?, row.get::<_, String>(3)?))))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<_, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut report = ExcursionReport { pairs: Vec::new(), by_setup: Vec::new(), missing_pairs: 0 };
+    let mut by_setup: BTreeMap<Option<i64>, (String, Vec<PairExcursion>)> = BTreeMap::new();
+    for pair in &pairs {
+        let key = (pair.entry_trade_id, pair.exit_trade_id);
+        let Some((interval, mae, mfe, mae_amount, mfe_amount)) = excursions.get(&key).cloned() else {
+            report.missing_pairs += 1;
+            continue;
+        };
+        let excursion = PairExcursion {
+            entry_trade_id: pair.entry_trade_id,
+            exit_trade_id: pair.exit_trade_id,
+            symbol: pair.symbol.clone(),
+            exit_timestamp: pair.exit_timestamp.clone(),
+            net_profit_loss: pair.net_profit_loss,
+            interval,
+            mae,
+            mfe,
+            mae_amount,
+            mfe_amount,
+            mfe_captured: (mfe_amount > 0.0).then(|| pair.gross_profit_loss / mfe_amount),
+        };
+        let (setup_id, setup) = match setup_of_pair.get(&key) {
+            Some((id, name)) => (Some(*id), name.clone()),
+            None => (None, "No setup".to_string()),
+        };
+        let group = by_setup.entry(setup_id).or_insert_with(|| (setup, Vec::new()));
+        group.1.push(excursion.clone());
+        report.pairs.push(excursion);
+    }
+
+    let average = |values: &[f64]| ratio(values.iter().sum(), values.len() as f64);
+    for (setup_id, (setup, group)) in by_setup {
+        let winners: Vec<&PairExcursion> = group.iter().filter(|p| p.net_profit_loss > 0.0).collect();
+        let losers: Vec<&PairExcursion> = group.iter().filter(|p| p.net_profit_loss < 0.0).collect();
+        let captured: Vec<f64> = group.iter().filter_map(|p| p.mfe_captured).collect();
+        report.by_setup.push(SetupExcursion {
+            setup_id,
+            setup,
+            trade_count: group.len() as i64,
+            average_mae_amount: average(&group.iter().map(|p| p.mae_amount).collect::<Vec<_>>()),
+            average_mfe_amount: average(&group.iter().map(|p| p.mfe_amount).collect::<Vec<_>>()),
+            winners_average_mae_amount: average(&winners.iter().map(|p| p.mae_amount).collect::<Vec<_>>()),
+            winners_max_mae_amount: winners.iter().map(|p| p.mae_amount).fold(0.0, f64::max),
+            losers_average_mfe_amount: average(&losers.iter().map(|p| p.mfe_amount).collect::<Vec<_>>()),
+            average_mfe_captured: (!captured.is_empty()).then(|| average(&captured)),
+        });
+    }
+    // Setups first, "No setup" last
+    report.by_setup.sort_by_key(|s| s.setup_id.is_none());
+    report.pairs.sort_by(|a, b| b.exit_timestamp.cmp(&a.exit_timestamp));
+    Ok(report)
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct EventPerformance {
+    pub event_type: String,
+    pub trade_count: i64,
+    pub winning_trades: i64,
+    pub losing_trades: i64,
+    pub win_rate: f64,
+    pub net_pnl: f64,
+    pub average_pnl: f64,
+}
+
+impl EventPerformance {
+    fn from_stats(event_type: &str, stats: &PairStats) -> EventPerformance {
+        EventPerformance {
+            event_type: event_type.to_string(),
+            trade_count: stats.count,
+            winning_trades: stats.wins,
+            losing_trades: stats.losses,
+            win_rate: stats.win_rate(),
+            net_pnl: stats.net_pnl,
+            average_pnl: ratio(stats.net_pnl, stats.count as f64),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EventPair {
+    pub entry_trade_id: i64,
+    pub exit_trade_id: i64,
+    pub symbol: String,
+    pub entry_timestamp: String,
+    pub exit_timestamp: String,
+    pub net_profit_loss: f64,
+    pub event_types: Vec<String>,
+    /// "2025-01-29 FOMC Meeting" for each event the pair was open on
+    pub events: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EventPerformanceReport {
+    /// A pair counts once toward each event type it was exposed to
+    pub by_event_type: Vec<EventPerformance>,
+    pub without_events: EventPerformance,
+    /// Newest exit first
+    pub flagged_pairs: Vec<EventPair>,
+}
+
+/// Trading day an event moves the market on: the next weekday for after-the-close earnings.
+fn event_market_day(event: &crate::calendar::MarketEvent) -> String {
+    if event.timing.as_deref() != Some("amc") {
+        return event.date.clone();
+    }
+    let Ok(mut day) = chrono::NaiveDate::parse_from_str(&event.date, "%Y-%m-%d") else {
+        return event.date.clone();
+    };
+    loop {
+        day = day.succ_opt().unwrap_or(day);
+        if day.weekday().number_from_monday() <= 5 {
+            return day.format("%Y-%m-%d").to_string();
+        }
+    }
+}
+
+/// Pairs closed in the filter's range split by the market_events they were exposed to: a pair is
+/// flagged when an event's market day falls between its entry and exit dates (inclusive). Earnings
+/// only count for the pair's own symbol, or an option's underlying.
+pub fn event_performance(conn: &Connection, filter: &MetricsFilter) -> Result<EventPerformanceReport, String> {
+    let pairs = closed_pairs(conn, filter)?;
+    let first_entry = pairs.iter().map(|p| date_of(&p.entry_timestamp)).min().map(str::to_string);
+    let last_exit = pairs.iter().map(|p| date_of(&p.exit_timestamp)).max().map(str::to_string);
+    let mut market_wide: Vec<(String, crate::calendar::MarketEvent)> = Vec::new();
+    let mut by_symbol: HashMap<String, Vec<(String, crate::calendar::MarketEvent)>> = HashMap::new();
+    // One extra week back so after-the-close earnings just before the first entry still land
+    let lookback = first_entry
+        .as_deref()
+        .and_then(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+        .map(|d| (d - chrono::Duration::days(7)).format("%Y-%m-%d").to_string());
+    for event in crate::calendar::load_events(conn, lookback.as_deref(), last_exit.as_deref(), None, None)? {
+        let day = event_market_day(&event);
+        match event.symbol.clone() {
+            Some(symbol) => by_symbol.entry(symbol).or_default().push((day, event)),
+            None => market_wide.push((day, event)),
+        }
+    }
+
+    let mut totals: BTreeMap<String, PairStats> = BTreeMap::new();
+    let mut without_events = PairStats::default();
+    let mut flagged_pairs = Vec::new();
+    for pair in &pairs {
+        let (entry_day, exit_day) = (date_of(&pair.entry_timestamp), date_of(&pair.exit_timestamp));
+        let underlying = crate::options::parse_occ_symbol(&pair.symbol)
+            .map(|occ| occ.underlying)
+            .unwrap_or_else(|| pair.symbol.to_uppercase());
+        let hits: Vec<&(String, crate::calendar::MarketEvent)> = market_wide
+            .iter()
+            .chain(by_symbol.get(&underlying).into_iter().flatten())
+            .filter(|(day, _)| day.as_str() >= entry_day && day.as_str() <= exit_day)
+            .collect();
+        if hits.is_empty() {
+            without_events.record(pair.net_profit_loss);
+            continue;
+        }
+        let mut event_types: Vec<String> = hits.iter().map(|(_, event)| event.event_type.clone()).collect();
+        event_types.sort();
+        event_types.dedup();
+        for event_type in &event_types {
+            totals.entry(event_type.clone()).or_default().record(pair.net_profit_loss);
+        }
+        let mut events: Vec<String> = hits.iter().map(|(_, event)| format!("{} {}", event.date, event.title)).collect();
+        events.sort();
+        flagged_pairs.push(EventPair {
+            entry_trade_id: pair.entry_trade_id,
+            exit_trade_id: pair.exit_trade_id,
+            symbol: pair.symbol.clone(),
+            entry_timestamp: pair.entry_timestamp.clone(),
+            exit_timestamp: pair.exit_timestamp.clone(),
+            net_profit_loss: pair.net_profit_loss,
+            event_types,
+            events,
+        });
+    }
+    flagged_pairs.sort_by(|a, b| b.exit_timestamp.cmp(&a.exit_timestamp));
+
+    Ok(EventPerformanceReport {
+        by_event_type: totals.iter().map(|(event_type, stats)| EventPerformance::from_stats(event_type, stats)).collect(),
+        without_events: EventPerformance::from_stats("none", &without_events),
+        flagged_pairs,
+    })
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ClassificationStats {
+    /// Sector or asset class; "Unclassified" for pairs whose instrument has no sector
+    pub group: String,
+    /// Underlying symbols traded in the group, alphabetical
+    pub symbols: Vec<String>,
+    pub trade_count: i64,
+    pub winning_trades: i64,
+    pub losing_trades: i64,
+    pub win_rate: f64,
+    pub expectancy: f64,
+    pub gross_profit: f64,
+    pub gross_loss: f64,
+    pub profit_factor: f64,
+    pub net_pnl: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ClassificationRollups {
+    /// Best net P&L first, "Unclassified" last
+    pub by_sector: Vec<ClassificationStats>,
+    /// In ASSET_CLASSES order, classes without trades left out
+    pub by_asset_class: Vec<ClassificationStats>,
+}
+
+pub const UNCLASSIFIED_SECTOR: &str = "Unclassified";
+
+/// Symbol analytics rolled up by sector (from the instruments table, options under their underlying's
+/// sector) and by asset class (the entry trade's class), over the pairs closed in the filter's range.
+pub fn classification_rollups(conn: &Connection, filter: &MetricsFilter) -> Result<ClassificationRollups, String> {
+    let pairs = closed_pairs(conn, filter)?;
+    let specs = load_instrument_specs(conn)?;
+    let classes = load_asset_classes(conn)?;
+
+    let mut sectors: HashMap<String, (PairStats, BTreeSet<String>)> = HashMap::new();
+    let mut asset_classes: HashMap<String, (PairStats, BTreeSet<String>)> = HashMap::new();
+    for pair in &pairs {
+        let underlying = crate::options::parse_occ_symbol(&pair.symbol)
+            .map(|occ| occ.underlying)
+            .unwrap_or_else(|| pair.symbol.to_uppercase());
+        let sector = instrument_sector(&pair.symbol, &specs).unwrap_or_else(|| UNCLASSIFIED_SECTOR.to_string());
+        let class = classes
+            .get(&pair.entry_trade_id)
+            .cloned()
+            .unwrap_or_else(|| infer_asset_class(&pair.symbol, &specs).to_string());
+        for (group, totals) in [(sector, &mut sectors), (class, &mut asset_classes)] {
+            let (stats, symbols) = totals.entry(group).or_default();
+            stats.record(pair.net_profit_loss);
+            symbols.insert(underlying.clone());
+        }
+    }
+
+    let rollup = |(group, (stats, symbols)): (String, (PairStats, BTreeSet<String>))| ClassificationStats {
+        group,
+        symbols: symbols.into_iter().collect(),
+        trade_count: stats.count,
+        winning_trades: stats.wins,
+        losing_trades: stats.losses,
+        win_rate: stats.win_rate(),
+        expectancy: stats.expectancy(),
+        gross_profit: stats.gross_profit,
+        gross_loss: stats.gross_loss,
+        profit_factor: ratio(stats.gross_profit, stats.gross_loss),
+        net_pnl: stats.net_pnl,
+    };
+    let mut by_sector: Vec<ClassificationStats> = sectors.into_iter().map(rollup).collect();
+    by_sector.sort_by(|a, b| {
+        (a.group == UNCLASSIFIED_SECTOR)
+            .cmp(&(b.group == UNCLASSIFIED_SECTOR))
+            .then(b.net_pnl.partial_cmp(&a.net_pnl).unwrap_or(std::cmp::Ordering::Equal))
+    });
+    let mut by_asset_class: Vec<ClassificationStats> = asset_classes.into_iter().map(rollup).collect();
+    by_asset_class.sort_by_key(|c| ASSET_CLASSES.iter().position(|known| *known == c.group).unwrap_or(ASSET_CLASSES.len()));
+    Ok(ClassificationRollups { by_sector, by_asset_class })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database;
+
+    #[test]
+    fn realized_pnl_uses_the_callers_instrument_specs() {
+        let conn = database::test_connection();
+        conn.execute_batch(
+            "INSERT INTO instruments (symbol, instrument_type, multiplier) VALUES ('MINI', 'option', 10);
+             INSERT INTO trades (id, symbol, side, quantity, price, timestamp, order_type, status, fees)
+             VALUES (1, 'MINI', 'BUY', 2, 1.0, '2024-03-05T10:00:00-05:00', 'LIMIT', 'Filled', 1.0),
+                    (2, 'MINI', 'SELL', 2, 1.5, '2024-03-05T11:00:00-05:00', 'LIMIT', 'Filled', 1.0);",
+        )
+        .unwrap();
+
+        let pnl = realized_pnl_by_trade(&conn, Some("FIFO"), None).unwrap();
+        // 0.50 x 2 contracts x 10 per contract, less both fills' fees
+        assert_eq!(pnl.get(&2).copied(), Some(8.0));
+        assert!(!pnl.contains_key(&1));
+    }
+}
@@ -0,0 +1,295 @@
+// Natural-language journal questions -> structured trade filter.
+// "what's my win rate on SPY 0DTE after 2pm this quarter?" becomes
+// { symbols: [SPY], min_dte: 0, max_dte: 0, entry_after_minute: 840, start_date: 2026-10-01, metric: win_rate }.
+// The filter is returned with every answer so the user can see exactly what was counted.
+
+use chrono::{Datelike, Duration, NaiveDate};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+pub const QUERY_METRICS: [&str; 6] = ["win_rate", "total_pnl", "avg_pnl", "count", "profit_factor", "largest_loss"];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JournalQueryFilter {
+    /// Symbols or option underlyings
+    pub symbols: Vec<String>,
+    pub strategy_ids: Vec<i64>,
+    /// stock, option, future, forex, crypto
+    pub asset_class: Option<String>,
+    /// "C" or "P"
+    pub option_right: Option<String>,
+    /// Days to expiration at entry (options only)
+    pub min_dte: Option<i64>,
+    pub max_dte: Option<i64>,
+    /// Entry time window, minutes after midnight (inclusive start, exclusive end)
+    pub entry_after_minute: Option<u32>,
+    pub entry_before_minute: Option<u32>,
+    /// Entry weekdays, 0 = Monday
+    pub weekdays: Vec<u32>,
+    /// "Long" or "Short"
+    pub direction: Option<String>,
+    /// Exit date range, YYYY-MM-DD inclusive
+    pub start_date: Option<String>,
+    pub end_date: Option<String>,
+    /// One of QUERY_METRICS; the headline number of the answer
+    pub metric: String,
+}
+
+/// Names the parser can recognise in a question: traded symbols/underlyings and strategies.
+pub struct QueryVocabulary {
+    pub symbols: Vec<String>,
+    pub strategies: Vec<(i64, String)>,
+}
+
+/// Parse a question. Returns the filter and the phrases that were recognised, so callers can
+/// show how the question was read.
+pub fn parse_question(question: &str, vocabulary: &QueryVocabulary, today: NaiveDate) -> (JournalQueryFilter, Vec<String>) {
+    let q = question.to_lowercase();
+    let mut filter = JournalQueryFilter::default();
+    let mut understood: Vec<String> = Vec::new();
+    let re = |pattern: &str| Regex::new(pattern).expect("valid query regex");
+
+    // Metric
+    filter.metric = if q.contains("win rate") || q.contains("win %") || q.contains("winrate") || q.contains("hit rate") {
+        "win_rate"
+    } else if q.contains("profit factor") {
+        "profit_factor"
+    } else if q.contains("biggest loss") || q.contains("largest loss") || q.contains("worst") {
+        "largest_loss"
+    } else if q.contains("average") || q.contains("avg") || q.contains("expectancy") || q.contains("per trade") {
+        "avg_pnl"
+    } else if q.contains("how many") || q.contains("number of") || q.contains("count") {
+        "count"
+    } else {
+        "total_pnl"
+    }
+    .to_string();
+    understood.push(format!("metric: {}", filter.metric));
+
+    // Symbols: uppercase-looking words that were actually traded
+    let word_re = re(r"[A-Za-z][A-Za-z.]{0,5}");
+    for word in word_re.find_iter(question) {
+        let upper = word.as_str().to_uppercase();
+        if vocabulary.symbols.contains(&upper) && !filter.symbols.contains(&upper) {
+            // Lowercase common words ("on", "all") only count when written in caps
+            if word.as_str().len() <= 2 && word.as_str() != upper {
+                continue;
+            }
+            filter.symbols.push(upper);
+        }
+    }
+    if !filter.symbols.is_empty() {
+        understood.push(format!("symbols: {}", filter.symbols.join(", ")));
+    }
+
+    // Strategies, longest names first so "ORB 2" wins over "ORB"
+    let mut strategies = vocabulary.strategies.clone();
+    strategies.sort_by_key(|(_, name)| std::cmp::Reverse(name.len()));
+    for (id, name) in strategies {
+        let name_lower = name.to_lowercase();
+        if !name_lower.trim().is_empty()
+            && re(&format!(r"\b{}\b", regex::escape(&name_lower))).is_match(&q)
+            && !filter.strategy_ids.contains(&id)
+        {
+            filter.strategy_ids.push(id);
+            understood.push(format!("strategy: {}", name));
+        }
+    }
+
+    // Asset class and option type
+    for (words, class) in [
+        (&["option", "options"][..], "option"),
+        (&["future", "futures"][..], "future"),
+        (&["forex", "fx"][..], "forex"),
+        (&["crypto", "bitcoin"][..], "crypto"),
+        (&["stock", "stocks", "shares", "equities"][..], "stock"),
+    ] {
+        if words.iter().any(|w| re(&format!(r"\b{}\b", w)).is_match(&q)) {
+            filter.asset_class = Some(class.to_string());
+            understood.push(format!("asset class: {}", class));
+            break;
+        }
+    }
+    if re(r"\bcalls?\b").is_match(&q) {
+        filter.option_right = Some("C".to_string());
+    } else if re(r"\bputs?\b").is_match(&q) {
+        filter.option_right = Some("P".to_string());
+    }
+    if let Some(right) = &filter.option_right {
+        understood.push(format!("option type: {}", right));
+    }
+    if let Some(c) = re(r"\b(\d{1,3})\s*-?\s*dte\b").captures(&q) {
+        let dte: i64 = c[1].parse().unwrap_or(0);
+        filter.min_dte = Some(dte);
+        filter.max_dte = Some(dte);
+        understood.push(format!("DTE: {}", dte));
+    }
+
+    // Direction
+    if re(r"\b(long|longs)\b").is_match(&q) {
+        filter.direction = Some("Long".to_string());
+    } else if re(r"\b(short|shorts)\b").is_match(&q) {
+        filter.direction = Some("Short".to_string());
+    }
+    if let Some(direction) = &filter.direction {
+        understood.push(format!("direction: {}", direction));
+    }
+
+    // Time of day (entry)
+    let time = r"(\d{1,2})(?::(\d{2}))?\s*(am|pm)?";
+    let to_minutes = |c: &regex::Captures, h: usize| -> Option<u32> {
+        let mut hour: u32 = c.get(h)?.as_str().parse().ok()?;
+        let minute: u32 = c.get(h + 1).map_or(Some(0), |m| m.as_str().parse().ok())?;
+        match c.get(h + 2).map(|m| m.as_str()) {
+            Some("pm") if hour < 12 => hour += 12,
+            Some("am") if hour == 12 => hour = 0,
+            // Bare "after 2" during a trading day means 2pm
+            None if (1..=7).contains(&hour) => hour += 12,
+            _ => {}
+        }
+        (hour < 24 && minute < 60).then_some(hour * 60 + minute)
+    };
+    if let Some(c) = re(&format!(r"\bbetween\s+{}\s+and\s+{}", time, time)).captures(&q) {
+        filter.entry_after_minute = to_minutes(&c, 1);
+        filter.entry_before_minute = to_minutes(&c, 4);
+    } else {
+        // Not followed by a digit or '-' so "since 2025-01-01" stays a date
+        if let Some(c) = re(&format!(r"\b(?:after|since)\s+{}(?:$|[^\d-])", time)).captures(&q) {
+            filter.entry_after_minute = to_minutes(&c, 1);
+        }
+        if let Some(c) = re(&format!(r"\bbefore\s+{}", time)).captures(&q) {
+            filter.entry_before_minute = to_minutes(&c, 1);
+        }
+    }
+    if filter.entry_after_minute.is_none() && filter.entry_before_minute.is_none() {
+        if q.contains("morning") {
+            filter.entry_before_minute = Some(12 * 60);
+        } else if q.contains("afternoon") {
+            filter.entry_after_minute = Some(12 * 60);
+        } else if q.contains("first hour") || q.contains("at the open") {
+            filter.entry_after_minute = Some(9 * 60 + 30);
+            filter.entry_before_minute = Some(10 * 60 + 30);
+        } else if q.contains("power hour") || q.contains("last hour") {
+            filter.entry_after_minute = Some(15 * 60);
+            filter.entry_before_minute = Some(16 * 60);
+        }
+    }
+    if filter.entry_after_minute.is_some() || filter.entry_before_minute.is_some() {
+        let fmt = |m: Option<u32>| m.map_or("-".to_string(), |m| format!("{:02}:{:02}", m / 60, m % 60));
+        understood.push(format!(
+            "entry time: {} to {}",
+            fmt(filter.entry_after_minute),
+            fmt(filter.entry_before_minute)
+        ));
+    }
+
+    // Weekdays
+    for (i, day) in ["monday", "tuesday", "wednesday", "thursday", "friday", "saturday", "sunday"].iter().enumerate() {
+        if q.contains(day) {
+            filter.weekdays.push(i as u32);
+            understood.push(format!("weekday: {}", day));
+        }
+    }
+
+    // Date range (exit date)
+    if let Some((start, end, label)) = parse_date_range(&q, today) {
+        filter.start_date = start.map(|d| d.format("%Y-%m-%d").to_string());
+        filter.end_date = end.map(|d| d.format("%Y-%m-%d").to_string());
+        understood.push(format!(
+            "{}: {} to {}",
+            label,
+            filter.start_date.as_deref().unwrap_or("start"),
+            filter.end_date.as_deref().unwrap_or("today")
+        ));
+    }
+
+    (filter, understood)
+}
+
+fn month_start(year: i32, month: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, month, 1).expect("valid month start")
+}
+
+fn month_end(year: i32, month: u32) -> NaiveDate {
+    let (y, m) = if month == 12 { (year + 1, 1) } else { (year, month + 1) };
+    month_start(y, m) - Duration::days(1)
+}
+
+type DateRange = (Option<NaiveDate>, Option<NaiveDate>, &'static str);
+
+fn parse_date_range(q: &str, today: NaiveDate) -> Option<DateRange> {
+    let re = |pattern: &str| Regex::new(pattern).expect("valid date regex");
+    let (year, month) = (today.year(), today.month());
+
+    if let Some(c) = re(r"(\d{4}-\d{2}-\d{2})\s+(?:to|and|-)\s+(\d{4}-\d{2}-\d{2})").captures(q) {
+        let start = NaiveDate::parse_from_str(&c[1], "%Y-%m-%d").ok();
+        let end = NaiveDate::parse_from_str(&c[2], "%Y-%m-%d").ok();
+        return Some((start, end, "dates"));
+    }
+    if let Some(c) = re(r"\bsince\s+(\d{4}-\d{2}-\d{2})").captures(q) {
+        return Some((NaiveDate::parse_from_str(&c[1], "%Y-%m-%d").ok(), None, "since"));
+    }
+    if let Some(c) = re(r"\b(?:last|past)\s+(\d{1,4})\s+days?\b").captures(q) {
+        let days: i64 = c[1].parse().ok()?;
+        return Some((Some(today - Duration::days(days - 1)), Some(today), "last days"));
+    }
+    if q.contains("today") {
+        return Some((Some(today), Some(today), "today"));
+    }
+    if q.contains("yesterday") {
+        let day = today - Duration::days(1);
+        return Some((Some(day), Some(day), "yesterday"));
+    }
+    let week_start = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+    if q.contains("this week") {
+        return Some((Some(week_start), Some(today), "this week"));
+    }
+    if q.contains("last week") {
+        return Some((Some(week_start - Duration::days(7)), Some(week_start - Duration::days(1)), "last week"));
+    }
+    if q.contains("this month") || q.contains("mtd") {
+        return Some((Some(month_start(year, month)), Some(today), "this month"));
+    }
+    if q.contains("last month") {
+        let (y, m) = if month == 1 { (year - 1, 12) } else { (year, month - 1) };
+        return Some((Some(month_start(y, m)), Some(month_end(y, m)), "last month"));
+    }
+    let quarter_month = (month - 1) / 3 * 3 + 1;
+    if q.contains("this quarter") || q.contains("qtd") {
+        return Some((Some(month_start(year, quarter_month)), Some(today), "this quarter"));
+    }
+    if q.contains("last quarter") {
+        let (y, m) = if quarter_month == 1 { (year - 1, 10) } else { (year, quarter_month - 3) };
+        return Some((Some(month_start(y, m)), Some(month_end(y, m + 2)), "last quarter"));
+    }
+    if q.contains("this year") || q.contains("ytd") {
+        return Some((Some(month_start(year, 1)), Some(today), "this year"));
+    }
+    if q.contains("last year") {
+        return Some((Some(month_start(year - 1, 1)), Some(month_end(year - 1, 12)), "last year"));
+    }
+    if let Some(c) = re(r"\bq([1-4])\s*(\d{4})?\b").captures(q) {
+        let quarter: u32 = c[1].parse().ok()?;
+        let y: i32 = c.get(2).and_then(|m| m.as_str().parse().ok()).unwrap_or(year);
+        let first = (quarter - 1) * 3 + 1;
+        return Some((Some(month_start(y, first)), Some(month_end(y, first + 2)), "quarter"));
+    }
+    let months = [
+        "january", "february", "march", "april", "may", "june", "july", "august", "september", "october", "november", "december",
+    ];
+    if let Some(c) = re(&format!(r"\bin\s+({})(?:\s+(\d{{4}}))?\b", months.join("|"))).captures(q) {
+        let m = months.iter().position(|name| *name == &c[1])? as u32 + 1;
+        // A month without a year is the most recent one
+        let y = c
+            .get(2)
+            .and_then(|y| y.as_str().parse().ok())
+            .unwrap_or(if m > month { year - 1 } else { year });
+        return Some((Some(month_start(y, m)), Some(month_end(y, m)), "month"));
+    }
+    if let Some(c) = re(r"\bin\s+(20\d{2})\b").captures(q) {
+        let y: i32 = c[1].parse().ok()?;
+        return Some((Some(month_start(y, 1)), Some(month_end(y, 12)), "year"));
+    }
+    None
+}
@@ -0,0 +1,242 @@
+// Screenshot OCR import: runs the Tesseract CLI on a broker screenshot and pulls fills
+// (symbol, side, quantity, price, time) out of the recognised text.
+// Layouts differ per broker, so parsing is keyword based and every result goes through a
+// user-confirmed preview before anything is inserted.
+
+use chrono::NaiveDate;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Command;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OcrFill {
+    pub symbol: String,
+    /// BUY or SELL
+    pub side: String,
+    pub quantity: f64,
+    pub price: f64,
+    /// ISO 8601 ("2025-03-14T10:32:00Z"); None when no date was found in the screenshot
+    pub timestamp: Option<String>,
+    /// Text the fill was read from, shown in the preview so the user can check it
+    pub source_text: String,
+    /// Set by the preview when an identical trade already exists
+    #[serde(default)]
+    pub duplicate: bool,
+}
+
+// Default Tesseract locations tried when no path is configured
+#[cfg(target_os = "windows")]
+const TESSERACT_CANDIDATES: [&str; 3] = [
+    "tesseract",
+    r"C:\Program Files\Tesseract-OCR\tesseract.exe",
+    r"C:\Program Files (x86)\Tesseract-OCR\tesseract.exe",
+];
+#[cfg(not(target_os = "windows"))]
+const TESSERACT_CANDIDATES: [&str; 3] = ["tesseract", "/opt/homebrew/bin/tesseract", "/usr/local/bin/tesseract"];
+
+/// Recognised text of an image. `binary` overrides the Tesseract executable.
+pub fn recognize_text(image_path: &Path, binary: Option<&str>) -> Result<String, String> {
+    if !image_path.is_file() {
+        return Err(format!("Image not found: {}", image_path.display()));
+    }
+    let candidates: Vec<&str> = match binary {
+        Some(b) => vec![b],
+        None => TESSERACT_CANDIDATES.to_vec(),
+    };
+    let mut last_error = String::new();
+    for candidate in candidates {
+        let mut command = Command::new(candidate);
+        // psm 6: treat the screenshot as one uniform block of text, keeps rows together
+        command.arg(image_path).arg("stdout").args(["--psm", "6"]);
+        #[cfg(target_os = "windows")]
+        {
+            use std::os::windows::process::CommandExt;
+            command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+        }
+        match command.output() {
+            Ok(output) if output.status.success() => {
+                return Ok(String::from_utf8_lossy(&output.stdout).to_string());
+            }
+            Ok(output) => {
+                return Err(format!("Tesseract failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+            }
+            Err(e) => last_error = e.to_string(),
+        }
+    }
+    Err(format!(
+        "Tesseract OCR was not found ({}). Install it from https://github.com/tesseract-ocr/tesseract or set its path in Settings.",
+        last_error
+    ))
+}
+
+const SIDE_PATTERN: &str = r"(?i)\b(buy to cover|buy to open|buy to close|sell short|sell to open|sell to close|bought|sold|buy|sell)\b";
+
+// Uppercase words on broker screens that are not tickers
+const NON_SYMBOLS: [&str; 48] = [
+    "BUY", "SELL", "SOLD", "BOUGHT", "SHORT", "COVER", "OPEN", "CLOSE", "TO", "OF", "AT", "AM", "PM", "ET", "EST",
+    "EDT", "UTC", "USD", "QTY", "LIMIT", "MARKET", "LMT", "MKT", "STP", "STOP", "DAY", "GTC", "IOC", "FILLED", "FILL",
+    "ORDER", "SHARES", "SHARE", "PRICE", "TOTAL", "AVG", "FEE", "FEES", "CALL", "PUT", "EXECUTED", "STATUS", "DONE",
+    "THE", "A", "I", "X", "ID",
+];
+
+const MONTHS: [&str; 12] = ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+
+/// Split OCR text into one chunk per fill and parse each. Chunks without a side keyword are ignored;
+/// chunks with one but missing fields are reported in the returned warnings.
+/// `default_date` is used when a fill shows a time but no date (many apps omit today's date).
+pub fn parse_fills(text: &str, default_date: NaiveDate) -> (Vec<OcrFill>, Vec<String>) {
+    let side_re = Regex::new(SIDE_PATTERN).expect("valid side regex");
+    let mut fills = Vec::new();
+    let mut warnings = Vec::new();
+
+    for chunk in split_fill_chunks(text, &side_re) {
+        let joined = chunk.join(" ");
+        let Some(side_match) = side_re.find(&joined) else { continue };
+        match parse_chunk(&joined, side_match, default_date) {
+            Ok(fill) => fills.push(fill),
+            Err(missing) => warnings.push(format!("Could not read {} from: \"{}\"", missing, joined)),
+        }
+    }
+    if fills.is_empty() && warnings.is_empty() {
+        warnings.push("No buy or sell fills were recognised in the screenshot".to_string());
+    }
+    (fills, warnings)
+}
+
+// Blank-line separated blocks; a block containing several side keywords (list views) is split at
+// each line that starts a new fill. Lines before the first keyword line stay with it (symbol headers).
+fn split_fill_chunks<'a>(text: &'a str, side_re: &Regex) -> Vec<Vec<&'a str>> {
+    let mut chunks = Vec::new();
+    for block in text.split("\n\n") {
+        let lines: Vec<&str> = block.lines().map(|l| l.trim()).filter(|l| !l.is_empty()).collect();
+        let side_lines = lines.iter().filter(|l| side_re.is_match(l)).count();
+        if side_lines <= 1 {
+            chunks.push(lines);
+            continue;
+        }
+        let mut current: Vec<&str> = Vec::new();
+        let mut current_has_side = false;
+        for line in lines {
+            let has_side = side_re.is_match(line);
+            if has_side && current_has_side {
+                chunks.push(std::mem::take(&mut current));
+                current_has_side = false;
+            }
+            current_has_side |= has_side;
+            current.push(line);
+        }
+        chunks.push(current);
+    }
+    chunks
+}
+
+fn parse_number(s: &str) -> Option<f64> {
+    s.replace(',', "").parse::<f64>().ok()
+}
+
+fn parse_chunk(text: &str, side_match: regex::Match, default_date: NaiveDate) -> Result<OcrFill, &'static str> {
+    let keyword = side_match.as_str().to_lowercase();
+    let side = if keyword.starts_with("buy") || keyword == "bought" { "BUY" } else { "SELL" };
+    let after_side = &text[side_match.end()..];
+
+    let price_re = Regex::new(r"(?i)(?:@|\bat\b|\bprice\b:?|\bavg\.?(?:\s*price)?:?|\baverage(?:\s*price)?:?)\s*\$?\s*(\d[\d,]*(?:\.\d+)?)").expect("valid price regex");
+    let price = price_re
+        .captures(text)
+        .and_then(|c| parse_number(&c[1]))
+        .or_else(|| {
+            Regex::new(r"\$\s*(\d[\d,]*\.\d+)")
+                .expect("valid dollar regex")
+                .captures(text)
+                .and_then(|c| parse_number(&c[1]))
+        })
+        .ok_or("a price")?;
+
+    let qty_re = Regex::new(r"(?i)(\d[\d,]*(?:\.\d+)?)\s*(?:shares?|shs?|contracts?|cts?|lots?)\b").expect("valid quantity regex");
+    let quantity = qty_re
+        .captures(text)
+        .and_then(|c| parse_number(&c[1]))
+        .or_else(|| {
+            // "Buy 10 AAPL", "Bought AAPL 10 @ ..."
+            Regex::new(r"^\s*(?:[A-Z][A-Z.]{0,6}\s+)?(\d[\d,]*(?:\.\d+)?)\b")
+                .expect("valid leading quantity regex")
+                .captures(after_side)
+                .and_then(|c| parse_number(&c[1]))
+        })
+        .or_else(|| {
+            Regex::new(r"(?i)\b(?:qty|quantity|filled)\b:?\s*(\d[\d,]*(?:\.\d+)?)")
+                .expect("valid labelled quantity regex")
+                .captures(text)
+                .and_then(|c| parse_number(&c[1]))
+        })
+        .filter(|q| *q > 0.0)
+        .ok_or("a quantity")?;
+
+    let symbol = find_symbol(text, after_side).ok_or("a symbol")?;
+
+    Ok(OcrFill {
+        symbol,
+        side: side.to_string(),
+        quantity,
+        price,
+        timestamp: find_timestamp(text, default_date),
+        source_text: text.to_string(),
+        duplicate: false,
+    })
+}
+
+fn find_symbol(text: &str, after_side: &str) -> Option<String> {
+    let occ = Regex::new(r"\b([A-Z]{1,6}\d{6}[CP]\d{8})\b").expect("valid OCC regex");
+    if let Some(c) = occ.captures(text) {
+        return Some(c[1].to_string());
+    }
+    let ticker = Regex::new(r"\b([A-Z]{1,5}(?:\.[A-Z])?)\b").expect("valid ticker regex");
+    let is_symbol = |s: &str| !NON_SYMBOLS.contains(&s);
+    // Prefer the ticker right after the side keyword ("Sold 5 TSLA"), then any other
+    let first_in = |haystack: &str| ticker.captures_iter(haystack).map(|c| c[1].to_string()).find(|s| is_symbol(s));
+    first_in(after_side).or_else(|| first_in(text))
+}
+
+fn find_timestamp(text: &str, default_date: NaiveDate) -> Option<String> {
+    let iso = Regex::new(r"\b(\d{4})-(\d{2})-(\d{2})\b").expect("valid ISO date regex");
+    let us = Regex::new(r"\b(\d{1,2})/(\d{1,2})/(\d{2,4})\b").expect("valid US date regex");
+    let named = Regex::new(r"(?i)\b(jan|feb|mar|apr|may|jun|jul|aug|sep|oct|nov|dec)[a-z]*\.?\s+(\d{1,2}),?\s+(\d{4})\b").expect("valid named date regex");
+
+    let date = if let Some(c) = iso.captures(text) {
+        NaiveDate::from_ymd_opt(c[1].parse().ok()?, c[2].parse().ok()?, c[3].parse().ok()?)
+    } else if let Some(c) = us.captures(text) {
+        let year: i32 = c[3].parse().ok()?;
+        let year = if year < 100 { 2000 + year } else { year };
+        NaiveDate::from_ymd_opt(year, c[1].parse().ok()?, c[2].parse().ok()?)
+    } else if let Some(c) = named.captures(text) {
+        let month = MONTHS.iter().position(|m| c[1].eq_ignore_ascii_case(m))? as u32 + 1;
+        NaiveDate::from_ymd_opt(c[3].parse().ok()?, month, c[2].parse().ok()?)
+    } else {
+        None
+    };
+
+    let time_re = Regex::new(r"(?i)\b(\d{1,2}):(\d{2})(?::(\d{2}))?\s*([ap]\.?m\.?)?").expect("valid time regex");
+    let time = time_re.captures(text).and_then(|c| {
+        let mut hour: u32 = c[1].parse().ok()?;
+        let minute: u32 = c[2].parse().ok()?;
+        let second: u32 = c.get(3).and_then(|s| s.as_str().parse().ok()).unwrap_or(0);
+        if let Some(ampm) = c.get(4) {
+            let pm = ampm.as_str().to_lowercase().starts_with('p');
+            if hour == 12 {
+                hour = if pm { 12 } else { 0 };
+            } else if pm {
+                hour += 12;
+            }
+        }
+        (hour < 24 && minute < 60 && second < 60).then_some((hour, minute, second))
+    });
+
+    // Without a date or a time there is nothing to anchor the fill to
+    let date = match (date, time) {
+        (Some(d), _) => d,
+        (None, Some(_)) => default_date,
+        (None, None) => return None,
+    };
+    let (h, m, s) = time.unwrap_or((0, 0, 0));
+    Some(format!("{}T{:02}:{:02}:{:02}Z", date.format("%Y-%m-%d"), h, m, s))
+}
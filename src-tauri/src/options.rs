@@ -0,0 +1,163 @@
+// OCC option symbol parsing.
+// Format: ROOT (1-6 chars, space padded in the official form) + YYMMDD + C/P + strike x 1000 (8 digits)
+// Examples: "SPY251218C00679000", "SPY   251218C00679000", "BRKB  250117P00400000"
+
+use rusqlite::{params, Connection, Result};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OccOption {
+    pub underlying: String,
+    /// YYYY-MM-DD
+    pub expiration: String,
+    /// "C" or "P"
+    pub right: String,
+    pub strike: f64,
+}
+
+/// Parse an OCC option symbol. Returns None for anything that isn't a well-formed OCC symbol,
+/// so plain tickers that happen to contain C/P or digits (COP, BRK.B, ESZ4) are never misread.
+pub fn parse_occ_symbol(symbol: &str) -> Option<OccOption> {
+    // Tolerate common broker prefixes (".SPY..." thinkorswim, "-SPY..." some exports, "O:SPY..." Polygon)
+    let s = symbol.trim();
+    let s = s.strip_prefix("O:").unwrap_or(s);
+    let s = s.trim_start_matches(['.', '-']);
+
+    // Minimum: 1-char root + 6 date + 1 right + 8 strike
+    if !s.is_ascii() || s.len() < 16 {
+        return None;
+    }
+    let (rest, strike_part) = s.split_at(s.len() - 8);
+    if !strike_part.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let (rest, right) = rest.split_at(rest.len() - 1);
+    let right = match right {
+        "C" | "c" => "C",
+        "P" | "p" => "P",
+        _ => return None,
+    };
+    let (root, date_part) = rest.split_at(rest.len() - 6);
+    if !date_part.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let expiration = chrono::NaiveDate::parse_from_str(date_part, "%y%m%d").ok()?;
+
+    let root = root.trim();
+    if root.is_empty()
+        || root.len() > 6
+        || !root.chars().next().is_some_and(|c| c.is_ascii_alphabetic())
+        || !root.chars().all(|c| c.is_ascii_alphanumeric())
+    {
+        return None;
+    }
+
+    let strike = strike_part.parse::<u64>().ok()? as f64 / 1000.0;
+    if strike <= 0.0 {
+        return None;
+    }
+
+    Some(OccOption {
+        underlying: root.to_uppercase(),
+        expiration: expiration.format("%Y-%m-%d").to_string(),
+        right: right.to_string(),
+        strike,
+    })
+}
+
+/// Store the parsed option fields for one trade. Non-options get underlying = symbol and NULL option fields.
+pub fn set_trade_option_fields(conn: &Connection, trade_id: i64, symbol: &str) -> Result<()> {
+    match parse_occ_symbol(symbol) {
+        Some(opt) => conn.execute(
+            "UPDATE trades SET underlying = ?1, option_expiration = ?2, option_right = ?3, option_strike = ?4 WHERE id = ?5",
+            params![opt.underlying, opt.expiration, opt.right, opt.strike, trade_id],
+        )?,
+        None => conn.execute(
+            "UPDATE trades SET underlying = ?1, option_expiration = NULL, option_right = NULL, option_strike = NULL WHERE id = ?2",
+            params![symbol.trim().to_uppercase(), trade_id],
+        )?,
+    };
+    Ok(())
+}
+
+/// Fill the option columns for trades that haven't been parsed yet (underlying IS NULL).
+pub fn backfill_option_fields(conn: &Connection) -> Result<usize> {
+    let mut stmt = conn.prepare("SELECT id, symbol FROM trades WHERE underlying IS NULL")?;
+    let rows: Vec<(i64, String)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .filter_map(|r| r.ok())
+        .collect();
+    for (id, symbol) in &rows {
+        set_trade_option_fields(conn, *id, symbol)?;
+    }
+    Ok(rows.len())
+}
+
+// Black-Scholes helpers (no dividends). Used to derive IV/delta from a fill price when the broker
+// export doesn't include greeks.
+
+fn norm_cdf(x: f64) -> f64 {
+    // Abramowitz & Stegun 7.1.26 approximation of erf
+    let t = 1.0 / (1.0 + 0.3275911 * (x.abs() / std::f64::consts::SQRT_2));
+    let poly = t * (0.254829592 + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    let erf = 1.0 - poly * (-(x * x) / 2.0).exp();
+    if x >= 0.0 { 0.5 * (1.0 + erf) } else { 0.5 * (1.0 - erf) }
+}
+
+fn d1_d2(spot: f64, strike: f64, years: f64, rate: f64, vol: f64) -> (f64, f64) {
+    let vol_sqrt_t = vol * years.sqrt();
+    let d1 = ((spot / strike).ln() + (rate + 0.5 * vol * vol) * years) / vol_sqrt_t;
+    (d1, d1 - vol_sqrt_t)
+}
+
+pub fn black_scholes_price(spot: f64, strike: f64, years: f64, rate: f64, vol: f64, is_call: bool) -> f64 {
+    if years <= 0.0 || vol <= 0.0 {
+        let intrinsic = if is_call { spot - strike } else { strike - spot };
+        return intrinsic.max(0.0);
+    }
+    let (d1, d2) = d1_d2(spot, strike, years, rate, vol);
+    let discount = (-rate * years).exp();
+    if is_call {
+        spot * norm_cdf(d1) - strike * discount * norm_cdf(d2)
+    } else {
+        strike * discount * norm_cdf(-d2) - spot * norm_cdf(-d1)
+    }
+}
+
+pub fn black_scholes_delta(spot: f64, strike: f64, years: f64, rate: f64, vol: f64, is_call: bool) -> f64 {
+    if years <= 0.0 || vol <= 0.0 {
+        let itm = if is_call { spot > strike } else { spot < strike };
+        return match (itm, is_call) {
+            (true, true) => 1.0,
+            (true, false) => -1.0,
+            _ => 0.0,
+        };
+    }
+    let (d1, _) = d1_d2(spot, strike, years, rate, vol);
+    if is_call { norm_cdf(d1) } else { norm_cdf(d1) - 1.0 }
+}
+
+/// Implied volatility by bisection; None when the price is outside the no-arbitrage range.
+pub fn implied_volatility(price: f64, spot: f64, strike: f64, years: f64, rate: f64, is_call: bool) -> Option<f64> {
+    if price <= 0.0 || spot <= 0.0 || strike <= 0.0 || years <= 0.0 {
+        return None;
+    }
+    let (mut low, mut high) = (0.0001_f64, 5.0_f64);
+    if price < black_scholes_price(spot, strike, years, rate, low, is_call)
+        || price > black_scholes_price(spot, strike, years, rate, high, is_call)
+    {
+        return None;
+    }
+    for _ in 0..100 {
+        let mid = (low + high) / 2.0;
+        if black_scholes_price(spot, strike, years, rate, mid, is_call) > price {
+            high = mid;
+        } else {
+            low = mid;
+        }
+        if high - low < 1e-6 {
+            break;
+        }
+    }
+    Some((low + high) / 2.0)
+}
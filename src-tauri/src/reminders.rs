@@ -0,0 +1,137 @@
+// Background reminder scheduler.
+// Reminders live in the `reminders` table (see database.rs); every tick we check which ones are due,
+// evaluate their condition (backup age, expiring options/futures), fire an OS notification and log the delivery.
+
+use crate::commands::{get_app_setting, get_expiring_open_futures, get_expiring_open_options, load_reminders, Reminder};
+use crate::database::{write_lock, Database};
+use chrono::{Datelike, Local, NaiveTime};
+use rusqlite::{params, Connection};
+use std::time::Duration;
+use tauri::api::notification::Notification;
+use tauri::AppHandle;
+
+const TICK_SECONDS: u64 = 30;
+
+pub fn start_scheduler(app: AppHandle, db: Database) {
+    let identifier = app.config().tauri.bundle.identifier.clone();
+    std::thread::spawn(move || loop {
+        if let Err(e) = run_due_reminders(&db, &identifier) {
+            eprintln!("[Reminders] Error: {}", e);
+        }
+        std::thread::sleep(Duration::from_secs(TICK_SECONDS));
+    });
+}
+
+fn run_due_reminders(db: &Database, identifier: &str) -> Result<(), String> {
+    let conn = db.connection().map_err(|e| e.to_string())?;
+    let now = Local::now();
+    let today = now.date_naive();
+
+    for reminder in load_reminders(&conn)? {
+        let id = match reminder.id {
+            Some(id) => id,
+            None => continue,
+        };
+        if !reminder.enabled || !is_due(&reminder, now) {
+            continue;
+        }
+        // Mark as run for today first so a failing notification doesn't retry every tick. The write
+        // lock is only held for the statements; showing the notification can block
+        {
+            let _write_guard = write_lock();
+            conn.execute(
+                "UPDATE reminders SET last_run_at = ?1 WHERE id = ?2",
+                params![now.format("%Y-%m-%d %H:%M:%S").to_string(), id],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+
+        let body = match reminder_body(&conn, &reminder, today)? {
+            Some(body) => body,
+            None => continue, // Condition not met (e.g. backup is recent)
+        };
+
+        let result = Notification::new(identifier)
+            .title(&reminder.title)
+            .body(&body)
+            .show();
+        let (status, error) = match result {
+            Ok(()) => ("delivered", None),
+            Err(e) => ("failed", Some(e.to_string())),
+        };
+        let _write_guard = write_lock();
+        conn.execute(
+            "INSERT INTO reminder_deliveries (reminder_id, title, body, status, error) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![id, reminder.title, body, status, error],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+
+    Ok(())
+}
+
+/// Due when today is one of the reminder's weekdays, the time of day has passed,
+/// and it hasn't already run today.
+fn is_due(reminder: &Reminder, now: chrono::DateTime<Local>) -> bool {
+    let weekday = now.weekday().number_from_monday();
+    if let Some(days) = &reminder.days_of_week {
+        let on_today = days
+            .split(',')
+            .filter_map(|d| d.trim().parse::<u32>().ok())
+            .any(|d| d == weekday);
+        if !on_today {
+            return false;
+        }
+    }
+
+    let at = match NaiveTime::parse_from_str(&reminder.time_of_day, "%H:%M") {
+        Ok(t) => t,
+        Err(_) => return false,
+    };
+    if now.time() < at {
+        return false;
+    }
+
+    let today = now.format("%Y-%m-%d").to_string();
+    match &reminder.last_run_at {
+        Some(last) => !last.starts_with(&today),
+        None => true,
+    }
+}
+
+/// Notification body for a due reminder, or None if its condition isn't met.
+fn reminder_body(conn: &Connection, reminder: &Reminder, today: chrono::NaiveDate) -> Result<Option<String>, String> {
+    let default_body = reminder.body.clone().unwrap_or_default();
+    match reminder.kind.as_str() {
+        "backup_stale" => {
+            let max_age_days = reminder.threshold_days.unwrap_or(7);
+            let last_backup = get_app_setting(conn, "last_backup_at")
+                .and_then(|s| chrono::DateTime::parse_from_rfc3339(&s).ok())
+                .map(|d| d.with_timezone(&Local).date_naive());
+            match last_backup {
+                Some(d) if (today - d).num_days() < max_age_days => Ok(None),
+                Some(d) => Ok(Some(format!("{} Last backup: {} days ago.", default_body, (today - d).num_days()).trim().to_string())),
+                None => Ok(Some(format!("{} No backup has been taken yet.", default_body).trim().to_string())),
+            }
+        }
+        "option_expiring" => {
+            let symbols = get_expiring_open_options(conn, today, reminder.threshold_days.unwrap_or(0))?;
+            if symbols.is_empty() {
+                return Ok(None);
+            }
+            Ok(Some(format!("{} {}", default_body, symbols.join(", ")).trim().to_string()))
+        }
+        "futures_expiring" => {
+            let positions = get_expiring_open_futures(conn, today, reminder.threshold_days.unwrap_or(3))?;
+            if positions.is_empty() {
+                return Ok(None);
+            }
+            let list: Vec<String> = positions
+                .iter()
+                .map(|p| format!("{} (~{})", p.symbol, p.approx_expiration))
+                .collect();
+            Ok(Some(format!("{} {}", default_body, list.join(", ")).trim().to_string()))
+        }
+        _ => Ok(Some(default_body)),
+    }
+}
@@ -0,0 +1,450 @@
+// Tax reporting over the pairing engine's closed lots.
+// Wash sales: a long position sold at a loss while substantially identical shares are bought within
+// 30 days before or after the sale. The disallowed part of the loss is added to the basis of the
+// replacement shares instead of being reportable this year. Paper trades are never included.
+// Form 8949 rows (CSV or TXF for tax software) are the closed lots of a year with those adjustments.
+
+use crate::commands::{load_asset_classes, load_instrument_specs, load_trade_fx_rates, pair_trades, pairing_is_fifo, timestamp_order, trade_multiplier, PairedTrade, PairingContext, LONG_TERM};
+use crate::database::Trade;
+use crate::metrics::{is_filled, load_trades};
+use chrono::NaiveDate;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Days on either side of a loss sale in which a purchase washes it.
+pub const WASH_SALE_WINDOW_DAYS: i64 = 30;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WashSaleReplacement {
+    pub trade_id: i64,
+    pub symbol: String,
+    pub date: String,
+    /// Quantity of this purchase matched to the loss
+    pub quantity: f64,
+    /// Disallowed loss added to the replacement lot's cost basis
+    pub basis_adjustment: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WashSale {
+    pub symbol: String,
+    pub entry_trade_id: i64,
+    pub exit_trade_id: i64,
+    pub acquired_date: String,
+    pub sale_date: String,
+    pub quantity: f64,
+    /// Net loss of the lot (negative)
+    pub loss: f64,
+    /// Part of the loss that cannot be claimed (positive)
+    pub disallowed_loss: f64,
+    /// loss + disallowed_loss: what goes on the return (0 when fully washed)
+    pub reportable_loss: f64,
+    pub replacements: Vec<WashSaleReplacement>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WashSaleReport {
+    pub tax_year: i32,
+    pub wash_sales: Vec<WashSale>,
+    pub total_loss: f64,
+    pub total_disallowed: f64,
+    pub total_reportable_loss: f64,
+}
+
+fn trade_date(timestamp: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(timestamp.get(..10)?, "%Y-%m-%d").ok()
+}
+
+/// Symbol under which purchases count as substantially identical: share-class spellings (BRK.B,
+/// BRK/B, BRK-B) are one security and an option contract only matches itself. Futures, forex and
+/// crypto are not covered by the wash-sale rule and get None.
+fn wash_sale_key(symbol: &str, asset_class: Option<&str>) -> Option<String> {
+    match asset_class {
+        Some("future") | Some("forex") | Some("crypto") => None,
+        _ => Some(symbol.trim().to_uppercase().replace(['/', '-'], ".")),
+    }
+}
+
+/// Filled live-account trades and their pairs under the given pairing method.
+pub fn tax_lots(conn: &Connection, pairing_method: Option<&str>) -> Result<(Vec<Trade>, Vec<PairedTrade>), String> {
+    let trades: Vec<Trade> = load_trades(conn, Some(false))?.into_iter().filter(is_filled).collect();
+    let pairing_context = PairingContext::load(conn)?;
+    let (pairs, _open_trades) = pair_trades(trades.clone(), pairing_is_fifo(conn, pairing_method), &pairing_context);
+    Ok((trades, pairs))
+}
+
+/// Every wash sale in the journal, oldest sale first. Losses are matched in sale order and each
+/// purchased share replaces at most one sold share; shares no longer held at the loss sale never
+/// count as replacements. Short-sale losses are not analysed.
+pub fn find_wash_sales(trades: &[Trade], pairs: &[PairedTrade], asset_classes: &HashMap<i64, String>) -> Vec<WashSale> {
+    let trades_by_id: HashMap<i64, &Trade> = trades.iter().filter_map(|t| Some((t.id?, t))).collect();
+    let class_of = |id: i64| asset_classes.get(&id).map(String::as_str);
+
+    // Purchases by key with the quantity still free to act as a replacement
+    let mut purchases: HashMap<String, Vec<(i64, NaiveDate, f64)>> = HashMap::new();
+    for trade in trades.iter().filter(|t| t.side.eq_ignore_ascii_case("BUY")) {
+        let (Some(id), Some(date)) = (trade.id, trade_date(&trade.timestamp)) else { continue };
+        if let Some(key) = wash_sale_key(&trade.symbol, class_of(id)) {
+            purchases.entry(key).or_default().push((id, date, trade.quantity));
+        }
+    }
+
+    let mut losses: Vec<&PairedTrade> = pairs
+        .iter()
+        .filter(|p| p.net_profit_loss < 0.0)
+        .filter(|p| trades_by_id.get(&p.entry_trade_id).is_some_and(|t| t.side.eq_ignore_ascii_case("BUY")))
+        .collect();
+    losses.sort_by(|a, b| timestamp_order(&a.exit_timestamp).cmp(&timestamp_order(&b.exit_timestamp)));
+
+    let mut wash_sales = Vec::new();
+    for loss in losses {
+        let sale_order = timestamp_order(&loss.exit_timestamp);
+        let (Some(key), Some(sale_date)) = (wash_sale_key(&loss.symbol, class_of(loss.entry_trade_id)), trade_date(&loss.exit_timestamp)) else {
+            continue;
+        };
+        let Some(candidates) = purchases.get_mut(&key) else { continue };
+        let mut unmatched = loss.quantity;
+        let mut replacements = Vec::new();
+        for (trade_id, date, available) in candidates.iter_mut() {
+            if unmatched <= 0.0 {
+                break;
+            }
+            if *trade_id == loss.entry_trade_id || (*date - sale_date).num_days().abs() > WASH_SALE_WINDOW_DAYS {
+                continue;
+            }
+            // Shares of an earlier purchase already sold by the time of the loss sale (including by
+            // the loss sale itself) are not held and cannot replace anything
+            let sold_by_then: f64 = pairs
+                .iter()
+                .filter(|p| p.entry_trade_id == *trade_id && timestamp_order(&p.exit_timestamp) <= sale_order)
+                .fold(0.0, |sum, p| sum + p.quantity);
+            let purchased = trades_by_id.get(trade_id).map_or(0.0, |t| t.quantity);
+            let matched = available.min(purchased - sold_by_then).min(unmatched);
+            if matched <= 0.0 {
+                continue;
+            }
+            *available -= matched;
+            unmatched -= matched;
+            replacements.push(WashSaleReplacement {
+                trade_id: *trade_id,
+                symbol: trades_by_id.get(trade_id).map(|t| t.symbol.clone()).unwrap_or_default(),
+                date: date.format("%Y-%m-%d").to_string(),
+                quantity: matched,
+                basis_adjustment: -loss.net_profit_loss * matched / loss.quantity,
+            });
+        }
+        if replacements.is_empty() {
+            continue;
+        }
+        let disallowed_loss = replacements.iter().fold(0.0, |sum, r| sum + r.basis_adjustment);
+        wash_sales.push(WashSale {
+            symbol: loss.symbol.clone(),
+            entry_trade_id: loss.entry_trade_id,
+            exit_trade_id: loss.exit_trade_id,
+            acquired_date: loss.entry_timestamp.get(..10).unwrap_or(&loss.entry_timestamp).to_string(),
+            sale_date: sale_date.format("%Y-%m-%d").to_string(),
+            quantity: loss.quantity,
+            loss: loss.net_profit_loss,
+            disallowed_loss,
+            reportable_loss: loss.net_profit_loss + disallowed_loss,
+            replacements,
+        });
+    }
+    wash_sales
+}
+
+/// Wash sales whose loss sale falls in `tax_year`. Losses late in the previous year are still
+/// matched first so they keep the January purchases they used up.
+pub fn wash_sale_report(conn: &Connection, tax_year: i32, pairing_method: Option<&str>) -> Result<WashSaleReport, String> {
+    let (trades, pairs) = tax_lots(conn, pairing_method)?;
+    let asset_classes = load_asset_classes(conn)?;
+    let year_prefix = format!("{:04}-", tax_year);
+    let wash_sales: Vec<WashSale> = find_wash_sales(&trades, &pairs, &asset_classes)
+        .into_iter()
+        .filter(|w| w.sale_date.starts_with(&year_prefix))
+        .collect();
+    let total = |f: fn(&WashSale) -> f64| wash_sales.iter().fold(0.0, |sum, w| sum + f(w));
+    Ok(WashSaleReport {
+        tax_year,
+        total_loss: total(|w| w.loss),
+        total_disallowed: total(|w| w.disallowed_loss),
+        total_reportable_loss: total(|w| w.reportable_loss),
+        wash_sales,
+    })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Form8949Row {
+    pub description: String,
+    /// MM/DD/YYYY
+    pub acquired_date: String,
+    /// MM/DD/YYYY
+    pub sold_date: String,
+    pub proceeds: f64,
+    pub cost_basis: f64,
+    /// "W" for a wash sale, empty otherwise
+    pub adjustment_code: String,
+    /// Disallowed loss added back (positive)
+    pub adjustment_amount: f64,
+    pub gain_loss: f64,
+    pub long_term: bool,
+}
+
+fn form_date(date: NaiveDate) -> String {
+    date.format("%m/%d/%Y").to_string()
+}
+
+fn round_cents(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+/// Form 8949 rows for the lots sold in `tax_year`, short-term first, each group by sale date.
+/// Proceeds are net of the closing fees and basis includes the opening fees. Washed losses carry
+/// code W with the disallowed amount, and replacement lots sold later carry the added basis. Short
+/// sales are reported on the cover date. Futures (Form 6781) and forex (section 988) are left out.
+pub fn form_8949_rows(conn: &Connection, tax_year: i32, pairing_method: Option<&str>) -> Result<Vec<Form8949Row>, String> {
+    let (trades, pairs) = tax_lots(conn, pairing_method)?;
+    let asset_classes = load_asset_classes(conn)?;
+    let specs = load_instrument_specs(conn)?;
+    let fx_rates = load_trade_fx_rates(conn)?;
+    let trades_by_id: HashMap<i64, &Trade> = trades.iter().filter_map(|t| Some((t.id?, t))).collect();
+
+    let wash_sales = find_wash_sales(&trades, &pairs, &asset_classes);
+    let disallowed: HashMap<(i64, i64), f64> = wash_sales
+        .iter()
+        .map(|w| ((w.entry_trade_id, w.exit_trade_id), w.disallowed_loss))
+        .collect();
+    // Replacement purchase -> (quantity still carrying added basis, added basis per unit)
+    let mut added_basis: HashMap<i64, (f64, f64)> = HashMap::new();
+    for replacement in wash_sales.iter().flat_map(|w| &w.replacements) {
+        let entry = added_basis.entry(replacement.trade_id).or_insert((0.0, 0.0));
+        let total = entry.0 * entry.1 + replacement.basis_adjustment;
+        entry.0 += replacement.quantity;
+        entry.1 = total / entry.0;
+    }
+
+    let mut sorted: Vec<&PairedTrade> = pairs.iter().collect();
+    sorted.sort_by(|a, b| timestamp_order(&a.exit_timestamp).cmp(&timestamp_order(&b.exit_timestamp)));
+    let mut rows = Vec::new();
+    for pair in sorted {
+        let class = asset_classes.get(&pair.entry_trade_id).map(String::as_str);
+        if matches!(class, Some("future") | Some("forex")) {
+            continue;
+        }
+        let (Some(entry_date), Some(exit_date)) = (trade_date(&pair.entry_timestamp), trade_date(&pair.exit_timestamp)) else {
+            continue;
+        };
+        let multiplier = trade_multiplier(&pair.symbol, class, &specs, fx_rates.get(&pair.exit_trade_id).copied());
+        let entry_value = pair.entry_price * pair.quantity * multiplier;
+        let exit_value = pair.exit_price * pair.quantity * multiplier;
+        let is_long = trades_by_id.get(&pair.entry_trade_id).is_none_or(|t| t.side.eq_ignore_ascii_case("BUY"));
+        let (proceeds, mut cost_basis, acquired) = if is_long {
+            (exit_value - pair.exit_fees, entry_value + pair.entry_fees, entry_date)
+        } else {
+            (entry_value - pair.entry_fees, exit_value + pair.exit_fees, exit_date)
+        };
+        // Basis carried over from washed losses, used up by the first lots sold from the purchase
+        if let Some((quantity, per_unit)) = added_basis.get_mut(&pair.entry_trade_id) {
+            let carried = quantity.min(pair.quantity);
+            if carried > 0.0 {
+                cost_basis += carried * *per_unit;
+                *quantity -= carried;
+            }
+        }
+        if exit_date.format("%Y").to_string() != format!("{:04}", tax_year) {
+            continue;
+        }
+        let adjustment = disallowed.get(&(pair.entry_trade_id, pair.exit_trade_id)).copied().unwrap_or(0.0);
+        let is_option = class == Some("option");
+        rows.push(Form8949Row {
+            description: format!("{} {}{}", pair.quantity, if is_option { "" } else { "sh " }, pair.symbol),
+            acquired_date: form_date(acquired),
+            sold_date: form_date(exit_date),
+            proceeds: round_cents(proceeds),
+            cost_basis: round_cents(cost_basis),
+            adjustment_code: if adjustment > 0.0 { "W".to_string() } else { String::new() },
+            adjustment_amount: round_cents(adjustment),
+            gain_loss: round_cents(proceeds - cost_basis + adjustment),
+            long_term: pair.holding_period == LONG_TERM,
+        });
+    }
+    rows.sort_by_key(|row| row.long_term);
+    Ok(rows)
+}
+
+/// Form 8949 rows as CSV with a header row, followed by short-term and long-term totals (the
+/// Schedule D lines).
+pub fn form_8949_csv(rows: &[Form8949Row]) -> Result<String, String> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer
+        .write_record([
+            "Description", "Date Acquired", "Date Sold", "Proceeds", "Cost Basis", "Adjustment Code",
+            "Adjustment Amount", "Gain or Loss", "Term",
+        ])
+        .map_err(|e| e.to_string())?;
+    for row in rows {
+        writer
+            .write_record([
+                row.description.clone(),
+                row.acquired_date.clone(),
+                row.sold_date.clone(),
+                format!("{:.2}", row.proceeds),
+                format!("{:.2}", row.cost_basis),
+                row.adjustment_code.clone(),
+                if row.adjustment_amount > 0.0 { format!("{:.2}", row.adjustment_amount) } else { String::new() },
+                format!("{:.2}", row.gain_loss),
+                if row.long_term { "Long-term" } else { "Short-term" }.to_string(),
+            ])
+            .map_err(|e| e.to_string())?;
+    }
+    for (long_term, label) in [(false, "Short-term"), (true, "Long-term")] {
+        let group: Vec<&Form8949Row> = rows.iter().filter(|row| row.long_term == long_term).collect();
+        let total = |f: fn(&Form8949Row) -> f64| group.iter().fold(0.0, |sum, row| sum + f(row));
+        writer
+            .write_record([
+                format!("Total {} ({} lots)", label.to_lowercase(), group.len()),
+                String::new(),
+                String::new(),
+                format!("{:.2}", total(|r| r.proceeds)),
+                format!("{:.2}", total(|r| r.cost_basis)),
+                String::new(),
+                format!("{:.2}", total(|r| r.adjustment_amount)),
+                format!("{:.2}", total(|r| r.gain_loss)),
+                label.to_string(),
+            ])
+            .map_err(|e| e.to_string())?;
+    }
+    let bytes = writer.into_inner().map_err(|e| e.to_string())?;
+    String::from_utf8(bytes).map_err(|e| e.to_string())
+}
+
+/// Form 8949 rows as a TXF (V042) file: one detailed record per lot, N321 short-term / N323
+/// long-term, with the disallowed wash-sale loss as the record's third amount.
+pub fn form_8949_txf(rows: &[Form8949Row], export_date: NaiveDate) -> String {
+    let mut txf = format!("V042\nATradeButler\nD{}\n^\n", form_date(export_date));
+    for row in rows {
+        txf.push_str(&format!(
+            "TD\nN{}\nC1\nL1\nP{}\nD{}\nD{}\n${:.2}\n${:.2}\n",
+            if row.long_term { 323 } else { 321 },
+            row.description,
+            row.acquired_date,
+            row.sold_date,
+            row.cost_basis,
+            row.proceeds
+        ));
+        if row.adjustment_amount > 0.0 {
+            txf.push_str(&format!("${:.2}\n", row.adjustment_amount));
+        }
+        txf.push_str("^\n");
+    }
+    txf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database;
+
+    #[test]
+    fn replacement_sold_after_the_loss_sale_still_washes_it_across_timestamp_formats() {
+        let conn = database::test_connection();
+        // The replacement (3) is sold at 11:00, an hour after the loss sale; the space-separated stamp
+        // sorts before the "T" one as a string
+        conn.execute_batch(
+            "INSERT INTO trades (id, symbol, side, quantity, price, timestamp, order_type, status)
+             VALUES (1, 'XYZ', 'BUY', 10, 100.0, '2024-03-01T10:00:00', 'MARKET', 'Filled'),
+                    (3, 'XYZ', 'BUY', 10, 95.0, '2024-03-04T10:00:00', 'MARKET', 'Filled'),
+                    (2, 'XYZ', 'SELL', 10, 90.0, '2024-03-05T10:00:00', 'MARKET', 'Filled'),
+                    (4, 'XYZ', 'SELL', 10, 96.0, '2024-03-05 11:00:00', 'MARKET', 'Filled');",
+        )
+        .unwrap();
+
+        let (trades, pairs) = tax_lots(&conn, Some("FIFO")).unwrap();
+        let wash_sales = find_wash_sales(&trades, &pairs, &load_asset_classes(&conn).unwrap());
+        assert_eq!(wash_sales.len(), 1);
+        assert_eq!(wash_sales[0].exit_trade_id, 2);
+        assert_eq!(wash_sales[0].replacements.len(), 1);
+        assert_eq!(wash_sales[0].replacements[0].trade_id, 3);
+        assert_eq!(wash_sales[0].replacements[0].quantity, 10.0);
+    }
+
+    fn wash_sales_for(conn: &Connection) -> Vec<WashSale> {
+        let (trades, pairs) = tax_lots(conn, Some("FIFO")).unwrap();
+        find_wash_sales(&trades, &pairs, &load_asset_classes(conn).unwrap())
+    }
+
+    #[test]
+    fn smaller_repurchase_disallows_only_its_share_of_the_loss() {
+        let conn = database::test_connection();
+        conn.execute_batch(
+            "INSERT INTO trades (id, symbol, side, quantity, price, timestamp, order_type, status)
+             VALUES (1, 'XYZ', 'BUY', 10, 100.0, '2024-03-01T10:00:00', 'MARKET', 'Filled'),
+                    (2, 'XYZ', 'SELL', 10, 90.0, '2024-03-05T10:00:00', 'MARKET', 'Filled'),
+                    (3, 'XYZ', 'BUY', 4, 92.0, '2024-03-10T10:00:00', 'MARKET', 'Filled');",
+        )
+        .unwrap();
+
+        let wash_sales = wash_sales_for(&conn);
+        assert_eq!(wash_sales.len(), 1);
+        assert_eq!(wash_sales[0].loss, -100.0);
+        assert_eq!(wash_sales[0].disallowed_loss, 40.0);
+        assert_eq!(wash_sales[0].reportable_loss, -60.0);
+        assert_eq!(wash_sales[0].replacements.len(), 1);
+        assert_eq!(wash_sales[0].replacements[0].trade_id, 3);
+        assert_eq!(wash_sales[0].replacements[0].quantity, 4.0);
+    }
+
+    #[test]
+    fn purchase_31_days_after_the_loss_sale_does_not_wash_it() {
+        let conn = database::test_connection();
+        conn.execute_batch(
+            "INSERT INTO trades (id, symbol, side, quantity, price, timestamp, order_type, status)
+             VALUES (1, 'XYZ', 'BUY', 10, 100.0, '2024-03-01T10:00:00', 'MARKET', 'Filled'),
+                    (2, 'XYZ', 'SELL', 10, 90.0, '2024-03-05T10:00:00', 'MARKET', 'Filled'),
+                    (3, 'XYZ', 'BUY', 10, 92.0, '2024-04-05T10:00:00', 'MARKET', 'Filled');",
+        )
+        .unwrap();
+
+        assert!(wash_sales_for(&conn).is_empty());
+    }
+
+    #[test]
+    fn purchase_sold_before_the_loss_sale_is_not_a_replacement() {
+        let conn = database::test_connection();
+        // FIFO: 3 closes 1 at a gain, then 4 closes 2 at a loss. Lot 1 is inside the window but no
+        // longer held when 4 is sold
+        conn.execute_batch(
+            "INSERT INTO trades (id, symbol, side, quantity, price, timestamp, order_type, status)
+             VALUES (1, 'XYZ', 'BUY', 10, 100.0, '2024-03-01T10:00:00', 'MARKET', 'Filled'),
+                    (2, 'XYZ', 'BUY', 10, 120.0, '2024-03-02T10:00:00', 'MARKET', 'Filled'),
+                    (3, 'XYZ', 'SELL', 10, 125.0, '2024-03-03T10:00:00', 'MARKET', 'Filled'),
+                    (4, 'XYZ', 'SELL', 10, 90.0, '2024-03-05T10:00:00', 'MARKET', 'Filled');",
+        )
+        .unwrap();
+
+        assert!(wash_sales_for(&conn).is_empty());
+    }
+
+    #[test]
+    fn futures_forex_and_crypto_are_exempt() {
+        let conn = database::test_connection();
+        conn.execute_batch(
+            "INSERT INTO trades (id, symbol, side, quantity, price, timestamp, order_type, status, asset_class)
+             VALUES (1, 'ES', 'BUY', 1, 5000.0, '2024-03-01T10:00:00', 'MARKET', 'Filled', 'future'),
+                    (2, 'ES', 'SELL', 1, 4900.0, '2024-03-05T10:00:00', 'MARKET', 'Filled', 'future'),
+                    (3, 'ES', 'BUY', 1, 4950.0, '2024-03-06T10:00:00', 'MARKET', 'Filled', 'future'),
+                    (4, 'EUR/USD', 'BUY', 1, 1.10, '2024-03-01T10:00:00', 'MARKET', 'Filled', 'forex'),
+                    (5, 'EUR/USD', 'SELL', 1, 1.08, '2024-03-05T10:00:00', 'MARKET', 'Filled', 'forex'),
+                    (6, 'EUR/USD', 'BUY', 1, 1.09, '2024-03-06T10:00:00', 'MARKET', 'Filled', 'forex'),
+                    (7, 'BTC/USD', 'BUY', 0.5, 60000.0, '2024-03-01T10:00:00', 'MARKET', 'Filled', 'crypto'),
+                    (8, 'BTC/USD', 'SELL', 0.5, 55000.0, '2024-03-05T10:00:00', 'MARKET', 'Filled', 'crypto'),
+                    (9, 'BTC/USD', 'BUY', 0.5, 56000.0, '2024-03-06T10:00:00', 'MARKET', 'Filled', 'crypto');",
+        )
+        .unwrap();
+
+        let (trades, pairs) = tax_lots(&conn, Some("FIFO")).unwrap();
+        assert_eq!(pairs.iter().filter(|p| p.net_profit_loss < 0.0).count(), 3);
+        assert!(find_wash_sales(&trades, &pairs, &load_asset_classes(&conn).unwrap()).is_empty());
+    }
+}
@@ -0,0 +1,346 @@
+// Undo/redo for trade edits. Every reversible command (create, edit, delete, strategy change, tags, paper
+// flag, bulk edits) records the affected trade rows and their tags before and after the change in
+// operation_log, inside the same transaction as the change. Undo writes the "before" rows back, redo
+// the "after" rows. The log is a linear stack: recording a new operation drops anything undone.
+// Rows are snapshotted column by column (SELECT *) so columns added by later migrations are covered
+// without touching this module.
+// Some writes aren't logged (import rollbacks, option event deletes, strategy merges, purging the
+// trash). A trade one of them changed or removed no longer looks the way the logged operation left
+// it, so undo and redo skip it rather than resurrect or overwrite it.
+
+use crate::database::{json_to_sql, sql_to_json};
+use rusqlite::types::Value;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::Map;
+
+/// Operations kept in the log; older ones can no longer be undone
+const MAX_OPERATIONS: i64 = 200;
+
+/// One trade as it was at a point in time: every trades column plus the ids of its tags
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TradeSnapshot {
+    pub id: i64,
+    pub row: Map<String, serde_json::Value>,
+    pub tag_ids: Vec<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LoggedOperation {
+    pub id: i64,
+    /// "create", "edit", "delete", "restore", "strategy", "tags", "paper", "bulk_update" or "bulk_delete"
+    pub kind: String,
+    pub description: String,
+    pub trade_count: usize,
+    /// Undone and waiting for redo
+    pub undone: bool,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UndoOutcome {
+    pub operation: LoggedOperation,
+    /// Trades written back (or removed again, for a created trade)
+    pub restored: usize,
+    /// Trades left alone because something outside the log changed or removed them since
+    pub skipped_trade_ids: Vec<i64>,
+}
+
+/// Current state of the given trades; ids that no longer exist are skipped.
+pub fn snapshot_trades(conn: &Connection, ids: &[i64]) -> Result<Vec<TradeSnapshot>, String> {
+    let mut row_stmt = conn.prepare_cached("SELECT * FROM trades WHERE id = ?1").map_err(|e| e.to_string())?;
+    let columns: Vec<String> = row_stmt.column_names().into_iter().map(String::from).collect();
+    let mut tag_stmt = conn
+        .prepare_cached("SELECT tag_id FROM trade_tags WHERE trade_id = ?1 ORDER BY tag_id")
+        .map_err(|e| e.to_string())?;
+    let mut snapshots = Vec::with_capacity(ids.len());
+    for &id in ids {
+        let row = row_stmt
+            .query_row(params![id], |row| {
+                let mut values = Map::new();
+                for (i, column) in columns.iter().enumerate() {
+                    values.insert(column.clone(), sql_to_json(row.get::<_, Value>(i)?));
+                }
+                Ok(values)
+            })
+            .optional()
+            .map_err(|e| e.to_string())?;
+        let Some(row) = row else { continue };
+        let tag_ids = tag_stmt
+            .query_map(params![id], |row| row.get::<_, i64>(0))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        snapshots.push(TradeSnapshot { id, row, tag_ids });
+    }
+    Ok(snapshots)
+}
+
+/// Log an operation. Nothing is recorded when the snapshots are identical (the command changed
+/// nothing); otherwise the redo stack is cleared and the log trimmed to MAX_OPERATIONS.
+pub fn record_operation(
+    conn: &Connection,
+    kind: &str,
+    description: &str,
+    before: &[TradeSnapshot],
+    after: &[TradeSnapshot],
+) -> Result<(), String> {
+    if before == after {
+        return Ok(());
+    }
+    let before_json = serde_json::to_string(before).map_err(|e| e.to_string())?;
+    let after_json = serde_json::to_string(after).map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM operation_log WHERE undone = 1", []).map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO operation_log (kind, description, trade_count, before_json, after_json) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![kind, description, before.len().max(after.len()) as i64, before_json, after_json],
+    )
+    .map_err(|e| e.to_string())?;
+    conn.execute(
+        "DELETE FROM operation_log WHERE id <= (SELECT MAX(id) FROM operation_log) - ?1",
+        params![MAX_OPERATIONS],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Whether a trade still looks the way a snapshot recorded it. Only columns both sides have are
+/// compared, so snapshots taken before a migration added or dropped a column still match.
+fn same_state(current: &TradeSnapshot, expected: &TradeSnapshot) -> bool {
+    current.tag_ids == expected.tag_ids
+        && current.row.iter().all(|(column, value)| expected.row.get(column).is_none_or(|v| v == value))
+}
+
+/// Write one snapshot back: an existing row is updated in place (so tags, plans and reviews stay
+/// attached), a missing one inserted again. Tags that have since been deleted are not recreated.
+fn write_snapshot(conn: &Connection, snapshot: &TradeSnapshot, known: &[String]) -> Result<(), String> {
+    let columns: Vec<&String> = snapshot.row.keys().filter(|c| c.as_str() != "id" && known.contains(c)).collect();
+    let mut values: Vec<Value> = columns.iter().map(|c| json_to_sql(&snapshot.row[c.as_str()])).collect();
+    values.push(Value::Integer(snapshot.id));
+    let assignments: Vec<String> = columns.iter().enumerate().map(|(i, c)| format!("\"{}\" = ?{}", c, i + 1)).collect();
+    let updated = conn
+        .execute(
+            &format!("UPDATE trades SET {} WHERE id = ?{}", assignments.join(", "), values.len()),
+            rusqlite::params_from_iter(values.iter()),
+        )
+        .map_err(|e| e.to_string())?;
+    if updated == 0 {
+        let names: Vec<String> = columns.iter().map(|c| format!("\"{}\"", c)).chain(["id".to_string()]).collect();
+        let placeholders: Vec<String> = (1..=values.len()).map(|i| format!("?{}", i)).collect();
+        conn.execute(
+            &format!("INSERT INTO trades ({}) VALUES ({})", names.join(", "), placeholders.join(", ")),
+            rusqlite::params_from_iter(values.iter()),
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    conn.execute("DELETE FROM trade_tags WHERE trade_id = ?1", params![snapshot.id])
+        .map_err(|e| e.to_string())?;
+    for tag_id in &snapshot.tag_ids {
+        conn.execute(
+            "INSERT OR IGNORE INTO trade_tags (trade_id, tag_id) SELECT ?1, id FROM tags WHERE id = ?2",
+            params![snapshot.id, tag_id],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Move the trades of an operation from `expected` (the state the operation left them in) to `target`.
+/// Trades missing from `target` didn't exist on that side and are deleted. Trades whose current state
+/// isn't `expected` are skipped. Returns how many were moved and the skipped ids.
+fn apply_snapshots(conn: &Connection, expected: &[TradeSnapshot], target: &[TradeSnapshot]) -> Result<(usize, Vec<i64>), String> {
+    // Snapshots taken before a migration dropped a column still carry it
+    let mut stmt = conn.prepare("SELECT name FROM pragma_table_info('trades')").map_err(|e| e.to_string())?;
+    let known = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    let mut ids: Vec<i64> = expected.iter().chain(target).map(|s| s.id).collect();
+    ids.sort_unstable();
+    ids.dedup();
+    let current = snapshot_trades(conn, &ids)?;
+    let find = |snapshots: &'_ [TradeSnapshot], id: i64| snapshots.iter().find(|s| s.id == id).cloned();
+    let mut restored = 0;
+    let mut skipped = Vec::new();
+    for id in ids {
+        let unchanged = match (find(&current, id), find(expected, id)) {
+            (Some(current), Some(expected)) => same_state(&current, &expected),
+            (None, None) => true,
+            _ => false,
+        };
+        if !unchanged {
+            skipped.push(id);
+            continue;
+        }
+        match find(target, id) {
+            Some(snapshot) => write_snapshot(conn, &snapshot, &known)?,
+            None => {
+                conn.execute("DELETE FROM trades WHERE id = ?1", params![id]).map_err(|e| e.to_string())?;
+            }
+        }
+        restored += 1;
+    }
+    Ok((restored, skipped))
+}
+
+fn load_operation(conn: &Connection, id: i64) -> Result<LoggedOperation, String> {
+    conn.query_row(
+        "SELECT id, kind, description, trade_count, undone, created_at FROM operation_log WHERE id = ?1",
+        params![id],
+        |row| {
+            Ok(LoggedOperation {
+                id: row.get(0)?,
+                kind: row.get(1)?,
+                description: row.get(2)?,
+                trade_count: row.get::<_, i64>(3)? as usize,
+                undone: row.get(4)?,
+                created_at: row.get(5)?,
+            })
+        },
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Before and after snapshots of a logged operation
+fn load_snapshots(conn: &Connection, id: i64) -> Result<(Vec<TradeSnapshot>, Vec<TradeSnapshot>), String> {
+    let (before_json, after_json): (String, String) = conn
+        .query_row("SELECT before_json, after_json FROM operation_log WHERE id = ?1", params![id], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })
+        .map_err(|e| e.to_string())?;
+    let before = serde_json::from_str(&before_json).map_err(|e| e.to_string())?;
+    let after = serde_json::from_str(&after_json).map_err(|e| e.to_string())?;
+    Ok((before, after))
+}
+
+/// Undo the most recent operation that hasn't been undone. None when there is nothing to undo.
+pub fn undo_last(conn: &Connection) -> Result<Option<UndoOutcome>, String> {
+    let latest: Option<i64> = conn
+        .query_row("SELECT id FROM operation_log WHERE undone = 0 ORDER BY id DESC LIMIT 1", [], |row| row.get(0))
+        .optional()
+        .map_err(|e| e.to_string())?;
+    let Some(id) = latest else { return Ok(None) };
+    let (before, after) = load_snapshots(conn, id)?;
+    let (restored, skipped_trade_ids) = apply_snapshots(conn, &after, &before)?;
+    conn.execute("UPDATE operation_log SET undone = 1 WHERE id = ?1", params![id])
+        .map_err(|e| e.to_string())?;
+    Ok(Some(UndoOutcome { operation: load_operation(conn, id)?, restored, skipped_trade_ids }))
+}
+
+/// Redo the operation undone last. None when there is nothing to redo.
+pub fn redo_last(conn: &Connection) -> Result<Option<UndoOutcome>, String> {
+    let next: Option<i64> = conn
+        .query_row("SELECT id FROM operation_log WHERE undone = 1 ORDER BY id ASC LIMIT 1", [], |row| row.get(0))
+        .optional()
+        .map_err(|e| e.to_string())?;
+    let Some(id) = next else { return Ok(None) };
+    let (before, after) = load_snapshots(conn, id)?;
+    let (restored, skipped_trade_ids) = apply_snapshots(conn, &before, &after)?;
+    conn.execute("UPDATE operation_log SET undone = 0 WHERE id = ?1", params![id])
+        .map_err(|e| e.to_string())?;
+    Ok(Some(UndoOutcome { operation: load_operation(conn, id)?, restored, skipped_trade_ids }))
+}
+
+/// Most recent operations first, undone ones included.
+pub fn recent_operations(conn: &Connection, limit: i64) -> Result<Vec<LoggedOperation>, String> {
+    let mut stmt = conn
+        .prepare("SELECT id FROM operation_log ORDER BY id DESC LIMIT ?1")
+        .map_err(|e| e.to_string())?;
+    let ids = stmt
+        .query_map(params![limit], |row| row.get::<_, i64>(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    ids.into_iter().map(|id| load_operation(conn, id)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database;
+
+    fn journal() -> Connection {
+        let conn = database::test_connection();
+        conn.execute_batch(
+            "INSERT INTO tags (id, name) VALUES (1, 'fomo'), (2, 'a+ setup');
+             INSERT INTO trades (id, symbol, side, quantity, price, timestamp, order_type, status)
+             VALUES (1, 'AAPL', 'BUY', 10, 100, '2024-03-05T10:00:00-05:00', 'MARKET', 'Filled'),
+                    (2, 'AAPL', 'SELL', 10, 105, '2024-03-05T11:00:00-05:00', 'MARKET', 'Filled');
+             INSERT INTO trade_tags (trade_id, tag_id) VALUES (1, 1);",
+        )
+        .unwrap();
+        conn
+    }
+
+    /// Edit both trades (price, notes, tags) the way the commands do, logging the change
+    fn edit(conn: &Connection) {
+        let before = snapshot_trades(conn, &[1, 2]).unwrap();
+        conn.execute_batch(
+            "UPDATE trades SET price = 101, notes = 'late fill' WHERE id = 1;
+             DELETE FROM trade_tags WHERE trade_id = 1;
+             INSERT INTO trade_tags (trade_id, tag_id) VALUES (1, 2), (2, 1);",
+        )
+        .unwrap();
+        let after = snapshot_trades(conn, &[1, 2]).unwrap();
+        record_operation(conn, "bulk_update", "Edit 2 trades", &before, &after).unwrap();
+    }
+
+    #[test]
+    fn undo_then_redo_restores_the_same_rows() {
+        let conn = journal();
+        let original = snapshot_trades(&conn, &[1, 2]).unwrap();
+        edit(&conn);
+        let edited = snapshot_trades(&conn, &[1, 2]).unwrap();
+        assert_ne!(original, edited);
+
+        let undone = undo_last(&conn).unwrap().unwrap();
+        assert_eq!(undone.operation.kind, "bulk_update");
+        assert_eq!(undone.restored, 2);
+        assert!(undone.skipped_trade_ids.is_empty());
+        assert_eq!(snapshot_trades(&conn, &[1, 2]).unwrap(), original);
+        assert!(undo_last(&conn).unwrap().is_none());
+
+        let redone = redo_last(&conn).unwrap().unwrap();
+        assert_eq!(redone.restored, 2);
+        assert_eq!(snapshot_trades(&conn, &[1, 2]).unwrap(), edited);
+        assert!(redo_last(&conn).unwrap().is_none());
+    }
+
+    #[test]
+    fn undo_skips_trades_changed_outside_the_log() {
+        let conn = journal();
+        edit(&conn);
+        // An unlogged hard delete (import rollback) and an unlogged edit after the logged one
+        conn.execute_batch(
+            "DELETE FROM trade_tags WHERE trade_id = 2;
+             DELETE FROM trades WHERE id = 2;
+             UPDATE trades SET fees = 1.5 WHERE id = 1;",
+        )
+        .unwrap();
+        let current = snapshot_trades(&conn, &[1, 2]).unwrap();
+
+        let undone = undo_last(&conn).unwrap().unwrap();
+        assert_eq!(undone.restored, 0);
+        assert_eq!(undone.skipped_trade_ids, vec![1, 2]);
+        assert_eq!(snapshot_trades(&conn, &[1, 2]).unwrap(), current);
+    }
+
+    #[test]
+    fn undoing_a_create_removes_the_trade_and_redo_brings_it_back() {
+        let conn = journal();
+        conn.execute(
+            "INSERT INTO trades (id, symbol, side, quantity, price, timestamp, order_type, status)
+             VALUES (3, 'MSFT', 'BUY', 5, 400, '2024-03-06T09:45:00-05:00', 'LIMIT', 'Filled')",
+            [],
+        )
+        .unwrap();
+        let created = snapshot_trades(&conn, &[3]).unwrap();
+        record_operation(&conn, "create", "Add MSFT trade", &[], &created).unwrap();
+
+        undo_last(&conn).unwrap().unwrap();
+        assert!(snapshot_trades(&conn, &[3]).unwrap().is_empty());
+        redo_last(&conn).unwrap().unwrap();
+        assert_eq!(snapshot_trades(&conn, &[3]).unwrap(), created);
+    }
+}
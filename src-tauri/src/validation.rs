@@ -0,0 +1,70 @@
+// Checks for trades entered or edited by hand (create_trade, add_trade_manual, update_trade).
+// Imports have their own per-row checks in the importers; these rules stop a manual edit from handing
+// the pairing engine a fill it can't use: zero or negative quantities, unknown sides or statuses,
+// timestamps that don't parse (they sort to the wrong place) and fees that can't be right.
+
+use crate::commands::parse_trade_datetime;
+use crate::database::{Side, Status, Trade};
+use crate::error::TradeButlerError;
+
+/// Fees up to this amount are accepted on any fill, so a commission on a tiny trade isn't flagged
+const FLAT_FEE_ALLOWANCE: f64 = 100.0;
+
+/// Days a timestamp may run ahead of the local clock (fills recorded in an exchange time zone
+/// ahead of this machine)
+const MAX_FUTURE_DAYS: i64 = 1;
+
+/// Every problem with a normalized trade, joined into one Validation error so a form can show them
+/// together. `multiplier` is the instrument's contract multiplier (1 for stocks, 100 for standard
+/// options), used to size the fee check against the fill's value.
+pub fn validate_trade(trade: &Trade, multiplier: f64) -> Result<(), TradeButlerError> {
+    let mut problems = Vec::new();
+
+    if trade.symbol.trim().is_empty() {
+        problems.push("Symbol is required".to_string());
+    }
+    if Side::parse(&trade.side).is_none() {
+        problems.push(format!("Unknown side '{}'. Use BUY, SELL, SELL_SHORT or BUY_TO_COVER", trade.side.trim()));
+    }
+    if Status::parse(&trade.status).is_none() {
+        problems.push(format!(
+            "Unknown status '{}'. Use Filled, Partially Filled, Pending, Cancelled, Rejected or Expired",
+            trade.status.trim()
+        ));
+    }
+    if !trade.quantity.is_finite() || trade.quantity <= 0.0 {
+        problems.push("Quantity must be greater than zero".to_string());
+    }
+    if !trade.price.is_finite() || trade.price < 0.0 {
+        problems.push("Price cannot be negative".to_string());
+    }
+    match parse_trade_datetime(trade.timestamp.trim()) {
+        None => problems.push(format!(
+            "Timestamp '{}' is not a date and time (use YYYY-MM-DDTHH:MM:SS)",
+            trade.timestamp.trim()
+        )),
+        Some(time) if time > chrono::Local::now().naive_local() + chrono::Duration::days(MAX_FUTURE_DAYS) => {
+            problems.push(format!("Timestamp {} is in the future", trade.timestamp.trim()));
+        }
+        Some(_) => {}
+    }
+    if let Some(fees) = trade.fees {
+        let value = (trade.quantity * trade.price * multiplier).abs();
+        if !fees.is_finite() || fees < 0.0 {
+            problems.push("Fees cannot be negative".to_string());
+        } else if value.is_finite() && fees > value.max(FLAT_FEE_ALLOWANCE) {
+            problems.push(format!("Fees of {:.2} are more than the fill is worth ({:.2})", fees, value));
+        }
+    }
+    if let Some(rate) = trade.fx_rate {
+        if !rate.is_finite() || rate <= 0.0 {
+            problems.push("FX rate must be greater than 0".to_string());
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(TradeButlerError::Validation(problems.join("; ")))
+    }
+}
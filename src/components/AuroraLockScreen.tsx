@@ -5,6 +5,7 @@ import { invoke } from "@tauri-apps/api/tauri";
 import { clearAllJournalStickySessions } from "../utils/journalStickySession";
 import { getLockScreenRendererPreference, canUseWebGL2 } from "../utils/lockScreenRenderer";
 import { createAuroraWebGLApi, type AuroraWebGLApi } from "../features/lockScreen/auroraWebGL";
+import { errorMessage } from "../utils/commandError";
 
 interface AuroraLockScreenProps {
   onUnlock: () => void;
@@ -382,7 +383,7 @@ export default function AuroraLockScreen({ onUnlock }: AuroraLockScreenProps) {
       window.location.reload();
     } catch (error) {
       console.error("Error deleting data:", error);
-      setError("Failed to delete data: " + (error instanceof Error ? error.message : String(error)));
+      setError("Failed to delete data: " + errorMessage(error));
     }
   };
 
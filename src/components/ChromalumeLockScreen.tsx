@@ -5,6 +5,7 @@ import { invoke } from "@tauri-apps/api/tauri";
 import { clearAllJournalStickySessions } from "../utils/journalStickySession";
 import { getLockScreenRendererPreference, canUseWebGL2 } from "../utils/lockScreenRenderer";
 import { createMilkyWayWebGLApi, type MilkyWayWebGLApi } from "../features/lockScreen/milkyWayWebGL";
+import { errorMessage } from "../utils/commandError";
 
 interface MilkyWayLockScreenProps {
   onUnlock: () => void;
@@ -311,7 +312,7 @@ export default function MilkyWayLockScreen({ onUnlock }: MilkyWayLockScreenProps
       window.location.reload();
     } catch (error) {
       console.error("Error deleting data:", error);
-      setError("Failed to delete data: " + (error instanceof Error ? error.message : String(error)));
+      setError("Failed to delete data: " + errorMessage(error));
     }
   };
 
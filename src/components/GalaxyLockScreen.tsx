@@ -16,6 +16,7 @@ import {
   type GalaxyParticle,
 } from "../features/lockScreen/galaxyPhysics";
 import { createGalaxyWebGLApi, type GalaxyWebGLApi } from "../features/lockScreen/galaxyWebGL";
+import { errorMessage } from "../utils/commandError";
 
 interface GalaxyLockScreenProps {
   onUnlock: () => void;
@@ -319,7 +320,7 @@ export default function GalaxyLockScreen({ onUnlock }: GalaxyLockScreenProps) {
       window.location.reload();
     } catch (error) {
       console.error("Error deleting data:", error);
-      setError("Failed to delete data: " + (error instanceof Error ? error.message : String(error)));
+      setError("Failed to delete data: " + errorMessage(error));
     }
   };
 
@@ -13,6 +13,7 @@ import {
 import { arrayMove, SortableContext, sortableKeyboardCoordinates, useSortable, verticalListSortingStrategy } from "@dnd-kit/sortable";
 import { CSS } from "@dnd-kit/utilities";
 import type { DataMode } from "../utils/dataMode";
+import { errorMessage } from "../utils/commandError";
 
 type SortableHandleSlot = Pick<ReturnType<typeof useSortable>, "attributes" | "listeners">;
 
@@ -259,7 +260,7 @@ export function JournalChecklistStrategyConfigureModal({
       onClose();
     } catch (e) {
       console.error(e);
-      alert("Failed to save checklist: " + e);
+      alert("Failed to save checklist: " + errorMessage(e));
     } finally {
       setSaving(false);
     }
@@ -42,6 +42,7 @@ import { DataMode, getCurrentDataMode, setCurrentDataMode } from "../utils/dataM
 import { clearJournalWip, hasJournalNavDraft } from "../utils/journalStickySession";
 import { addSandboxTrade, resetSandboxState } from "../utils/sandboxStore";
 import { resetSandboxDocumentation } from "../data/sandboxDocumentation";
+import { errorMessage } from "../utils/commandError";
 
 interface LayoutProps {
   children: React.ReactNode;
@@ -264,10 +265,20 @@ export default function Layout({ children }: LayoutProps) {
         
         // Check if it's JSON (export file) or CSV
         if (file.toLowerCase().endsWith(".json")) {
+          // Encrypted backups must be unlocked with their passphrase first
+          let jsonText = contents;
+          if (await invoke<boolean>("is_encrypted_backup", { data: contents })) {
+            const passphrase = window.prompt("This backup is encrypted. Enter its passphrase:");
+            if (!passphrase) {
+              return;
+            }
+            jsonText = await invoke<string>("decrypt_backup", { data: contents, passphrase });
+          }
+
           // Parse JSON to check for theme colors
           let importData;
           try {
-            importData = JSON.parse(contents);
+            importData = JSON.parse(jsonText);
           } catch (e) {
             throw new Error("Invalid JSON file");
           }
@@ -324,7 +335,7 @@ export default function Layout({ children }: LayoutProps) {
       }
     } catch (error) {
       console.error("Error importing:", error);
-      alert("Failed to import: " + error);
+      alert("Failed to import: " + errorMessage(error));
     } finally {
       setIsImporting(false);
     }
@@ -365,7 +376,19 @@ export default function Layout({ children }: LayoutProps) {
       }
       
       // Convert back to JSON string
-      const finalJsonData = JSON.stringify(exportData, null, 2);
+      let finalJsonData = JSON.stringify(exportData, null, 2);
+
+      // Optionally encrypt the whole backup with a passphrase
+      const passphrase = window.prompt(
+        "Passphrase to encrypt this backup (at least 8 characters). Leave empty to save it unencrypted.\n\nThe passphrase is not stored anywhere; without it the backup cannot be restored.",
+        ""
+      );
+      if (passphrase === null) {
+        return;
+      }
+      if (passphrase !== "") {
+        finalJsonData = await invoke<string>("encrypt_backup", { jsonData: finalJsonData, passphrase });
+      }
       
       // Then, ask user where to save it
       console.log("Opening save dialog...");
@@ -388,14 +411,69 @@ export default function Layout({ children }: LayoutProps) {
       }
     } catch (error) {
       console.error("Error exporting:", error);
-      const errorMessage = error instanceof Error ? error.message : String(error);
-      alert(`Failed to export: ${errorMessage}\n\nCheck the console (F12) for more details.`);
+      const message = errorMessage(error);
+      alert(`Failed to export: ${message}\n\nCheck the console (F12) for more details.`);
     } finally {
       setIsExporting(false);
       console.log("isExporting set to false");
     }
   };
 
+  const handleExportCsv = async () => {
+    try {
+      setIsExporting(true);
+      const date = new Date().toISOString().split("T")[0];
+      const filter = dataMode === "paper" ? { paper_only: true } : null;
+      const tradesPath = await save({
+        filters: [{ name: "CSV", extensions: ["csv"] }],
+        defaultPath: `TradeButler-Trades-${date}.csv`,
+      });
+      if (!tradesPath || typeof tradesPath !== "string") return;
+      const tradeCount = await invoke<number>("export_trades_csv", { path: tradesPath, filter });
+      const pairsPath = await save({
+        filters: [{ name: "CSV", extensions: ["csv"] }],
+        defaultPath: `TradeButler-Pairs-${date}.csv`,
+      });
+      let message = `Exported ${tradeCount} trades to:\n${tradesPath}`;
+      if (pairsPath && typeof pairsPath === "string") {
+        const pairCount = await invoke<number>("export_paired_trades_csv", { path: pairsPath, pairingMethod: null, filter });
+        message += `\n\nExported ${pairCount} closed pairs to:\n${pairsPath}`;
+      }
+      alert(message);
+    } catch (error) {
+      console.error("Error exporting CSV:", error);
+      alert(`Failed to export: ${errorMessage(error)}`);
+    } finally {
+      setIsExporting(false);
+    }
+  };
+
+  const handleExportXlsx = async () => {
+    try {
+      setIsExporting(true);
+      const date = new Date().toISOString().split("T")[0];
+      const filter = dataMode === "paper" ? { paper_only: true } : null;
+      const path = await save({
+        filters: [{ name: "Excel Workbook", extensions: ["xlsx"] }],
+        defaultPath: `TradeButler-${date}.xlsx`,
+      });
+      if (!path || typeof path !== "string") return;
+      const summary = await invoke<{ trades: number; pairs: number; days: number; symbols: number }>("export_xlsx", {
+        path,
+        pairingMethod: null,
+        filter,
+      });
+      alert(
+        `Exported ${summary.trades} trades, ${summary.pairs} closed pairs, ${summary.days} trading days and ${summary.symbols} symbols to:\n${path}`
+      );
+    } catch (error) {
+      console.error("Error exporting Excel workbook:", error);
+      alert(`Failed to export: ${errorMessage(error)}`);
+    } finally {
+      setIsExporting(false);
+    }
+  };
+
   const handleAddTradeSubmit = async () => {
     setAddTradeError(null);
     const qty = parseFloat(addTradeForm.quantity);
@@ -443,8 +521,9 @@ export default function Layout({ children }: LayoutProps) {
           timestamp,
           order_type: addTradeForm.orderType || null,
           fees: feeVal,
-          notes: notesWithPaper,
+          notes: baseNotes || null,
           strategy_id: null,
+          isPaper: addTradeForm.isPaperTrade,
         });
       }
       setShowAddTradeModal(false);
@@ -463,7 +542,7 @@ export default function Layout({ children }: LayoutProps) {
       window.dispatchEvent(new CustomEvent("tradeButlerTradeAdded"));
       alert("Trade added successfully.");
     } catch (err) {
-      setAddTradeError(err instanceof Error ? err.message : String(err));
+      setAddTradeError(errorMessage(err));
     } finally {
       setIsAddingTrade(false);
     }
@@ -473,13 +552,20 @@ export default function Layout({ children }: LayoutProps) {
     if (!pendingCsvImport) return;
     try {
       setIsImportingCsv(true);
-      await invoke("import_trades_csv", { csvData: pendingCsvImport.contents, mark_as_paper: pendingCsvImport.markAsPaper ? true : undefined });
-      alert("Trades imported successfully!");
+      const summary = await invoke<{ inserted: number; skipped_duplicates: number; skipped_in_trash: number; failed_rows: { line: number | null; message: string }[] }>("import_trades_csv", { csvData: pendingCsvImport.contents, mark_as_paper: pendingCsvImport.markAsPaper ? true : undefined });
+      const failed = summary.failed_rows.map((row) => (row.line ? `Line ${row.line}: ${row.message}` : row.message));
+      alert(
+        `Imported ${summary.inserted} trade(s), skipped ${summary.skipped_duplicates} duplicate(s).` +
+          (summary.skipped_in_trash > 0
+            ? ` ${summary.skipped_in_trash} of them are already in the trash; restore them or empty the trash to import them again.`
+            : "") +
+          (failed.length > 0 ? `\n\n${failed.length} row(s) could not be read:\n${failed.slice(0, 10).join("\n")}` : "")
+      );
       setPendingCsvImport(null);
       window.location.reload();
     } catch (error) {
       console.error("Error importing CSV:", error);
-      alert("Failed to import: " + error);
+      alert("Failed to import: " + errorMessage(error));
     } finally {
       setIsImportingCsv(false);
     }
@@ -934,6 +1020,52 @@ export default function Layout({ children }: LayoutProps) {
               <Download size={16} />
               {isExporting ? "Exporting..." : "Export"}
             </button>
+            <button
+              onClick={handleExportCsv}
+              disabled={isExporting || dataMode === "sandbox"}
+              style={{
+                width: "100%",
+                padding: "10px",
+                backgroundColor: "var(--bg-tertiary)",
+                color: "var(--accent)",
+                border: "1px solid var(--border-color)",
+                borderRadius: "6px",
+                cursor: "pointer",
+                display: "flex",
+                alignItems: "center",
+                justifyContent: "center",
+                gap: "8px",
+                fontSize: "14px",
+                fontWeight: "500",
+                opacity: isExporting || dataMode === "sandbox" ? 0.6 : 1,
+              }}
+            >
+              <Download size={16} />
+              Export CSV
+            </button>
+            <button
+              onClick={handleExportXlsx}
+              disabled={isExporting || dataMode === "sandbox"}
+              style={{
+                width: "100%",
+                padding: "10px",
+                backgroundColor: "var(--bg-tertiary)",
+                color: "var(--accent)",
+                border: "1px solid var(--border-color)",
+                borderRadius: "6px",
+                cursor: "pointer",
+                display: "flex",
+                alignItems: "center",
+                justifyContent: "center",
+                gap: "8px",
+                fontSize: "14px",
+                fontWeight: "500",
+                opacity: isExporting || dataMode === "sandbox" ? 0.6 : 1,
+              }}
+            >
+              <Download size={16} />
+              Export Excel
+            </button>
             <button
               onClick={handleLockToggle}
               disabled={!hasPassword()}
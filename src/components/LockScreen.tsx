@@ -3,6 +3,7 @@ import { Lock, Unlock, AlertCircle, Trash2 } from "lucide-react";
 import { unlockApp, getPasswordType, deletePassword } from "../utils/passwordManager";
 import { invoke } from "@tauri-apps/api/tauri";
 import { clearAllJournalStickySessions } from "../utils/journalStickySession";
+import { errorMessage } from "../utils/commandError";
 
 interface LockScreenProps {
   onUnlock: () => void;
@@ -168,7 +169,7 @@ export default function LockScreen({ onUnlock }: LockScreenProps) {
       window.location.reload();
     } catch (error) {
       console.error("Error deleting data:", error);
-      setError("Failed to delete data: " + (error instanceof Error ? error.message : String(error)));
+      setError("Failed to delete data: " + errorMessage(error));
     }
   };
 
@@ -15,6 +15,7 @@ import {
 import { updateAndProjectSphereLayer } from "../features/lockScreen/sphereProjection";
 import { drawSphereLayerOnCanvas } from "../features/lockScreen/sphereCanvasDraw";
 import { createSphereWebGLApi, type SphereWebGLApi } from "../features/lockScreen/sphereWebGL";
+import { errorMessage } from "../utils/commandError";
 
 interface SphereLockScreenProps {
   onUnlock: () => void;
@@ -714,7 +715,7 @@ export default function SphereLockScreen({ onUnlock }: SphereLockScreenProps) {
       window.location.reload();
     } catch (error) {
       console.error("Error deleting data:", error);
-      setError("Failed to delete data: " + (error instanceof Error ? error.message : String(error)));
+      setError("Failed to delete data: " + errorMessage(error));
     }
   };
 
@@ -4,6 +4,7 @@ import { format } from "date-fns";
 import { invoke } from "@tauri-apps/api/tauri";
 import { Settings } from "lucide-react";
 import { LoadingSphere } from "./LoadingSphere";
+import { errorMessage } from "../utils/commandError";
 
 interface Trade {
   id: number;
@@ -224,7 +225,7 @@ export function TradeChart({ symbol, entryTimestamp, exitTimestamp, entryPrice,
         console.log('Data received from Tauri:', data);
       } catch (fetchError) {
         console.error('Tauri fetch error:', fetchError);
-        const errorMsg = fetchError instanceof Error ? fetchError.message : String(fetchError);
+        const errorMsg = errorMessage(fetchError);
         
         // Provide helpful error messages for common issues
         if (errorMsg.includes('422') || errorMsg.includes('Unprocessable Entity')) {
@@ -842,7 +843,7 @@ export function TradeChart({ symbol, entryTimestamp, exitTimestamp, entryPrice,
                 secondsVisible: false, // Don't show seconds unless needed for very short timeframes
               });
             } catch (chartError) {
-              const errorMsg = chartError instanceof Error ? chartError.message : String(chartError);
+              const errorMsg = errorMessage(chartError);
               setError(`Failed to create chart: ${errorMsg}\n\nDebug info:\n${JSON.stringify(debugData, null, 2)}`);
               setLoading(false);
               return;
@@ -1048,7 +1049,7 @@ export function TradeChart({ symbol, entryTimestamp, exitTimestamp, entryPrice,
                   // Wait a bit longer and retry
                   setTimeout(() => addSeriesWithRetry(attempt + 1), 200 * (attempt + 1));
                 } else {
-                  const errorMsg = seriesError instanceof Error ? seriesError.message : String(seriesError);
+                  const errorMsg = errorMessage(seriesError);
                   const errorStack = seriesError instanceof Error ? seriesError.stack : '';
                   setError(`Failed to add candlestick series after ${attempt + 1} attempts: ${errorMsg}\n\nStack: ${errorStack}\n\nDebug info:\n${JSON.stringify(debugData, null, 2)}`);
                   setLoading(false);
@@ -127,6 +127,7 @@ import {
 } from "../utils/dividendTrackerCharts";
 import ViewFinancialsButton from "../components/ViewFinancialsButton";
 import { LoadingSphere } from "../components/LoadingSphere";
+import { errorMessage } from "../utils/commandError";
 
 interface Metrics {
   total_trades: number;
@@ -700,7 +701,7 @@ function useCurrentPriceQuote(
     } catch (e) {
       setPrice(null);
       setDayOpen(null);
-      setError(e instanceof Error ? e.message : String(e));
+      setError(errorMessage(e));
     } finally {
       setLoading(false);
     }
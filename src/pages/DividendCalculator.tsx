@@ -6,6 +6,7 @@ import { invoke } from "@tauri-apps/api/tauri";
 import { getCurrentDataMode, subscribeToDataMode } from "../utils/dataMode";
 import type { DataMode } from "../utils/dataMode";
 import { formatCompactNumber } from "../utils/formatCompactNumber";
+import { errorMessage } from "../utils/commandError";
 
 interface DividendYearResult {
   year: number;
@@ -460,7 +461,7 @@ export default function DividendCalculator() {
                   }
                   
                 } catch (error) {
-                  alert(`Failed to load stock data: ${error}\n\nPlease check the ticker symbol and try again.`);
+                  alert(`Failed to load stock data: ${errorMessage(error)}\n\nPlease check the ticker symbol and try again.`);
                 } finally {
                   setLoadingTicker(false);
                 }
@@ -69,6 +69,7 @@ import {
   deleteSandboxEmotionSurveysForStateIds,
   upsertSandboxEmotionSurveyFromResponses,
 } from "../utils/sandboxStore";
+import { errorMessage } from "../utils/commandError";
 
 type ChecklistTone = "accent" | "warning";
 function checklistToneVar(tone: ChecklistTone): string {
@@ -3065,7 +3066,7 @@ export default function Journal() {
       setShowDeleteConfirmModal(false);
     } catch (error) {
       console.error("Error deleting entry:", error);
-      alert("Failed to delete entry: " + error);
+      alert("Failed to delete entry: " + errorMessage(error));
       setShowDeleteConfirmModal(false);
     }
   };
@@ -4091,7 +4092,7 @@ export default function Journal() {
       }
     } catch (error) {
       console.error("Error saving entry:", error);
-      alert("Failed to save entry: " + (error instanceof Error ? error.message : String(error)));
+      alert("Failed to save entry: " + errorMessage(error));
     } finally {
       isManualSaveInProgressRef.current = false;
     }
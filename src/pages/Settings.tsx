@@ -59,6 +59,7 @@ import {
   saveNewsSettings,
   NEWS_SETTINGS_CHANGED_EVENT,
 } from "../utils/newsManager";
+import { errorMessage } from "../utils/commandError";
 
 interface VersionInfo {
   current: string;
@@ -163,11 +164,11 @@ export default function Settings() {
       window.dispatchEvent(new CustomEvent("tradebutlerTradesChanged"));
       setShowClearDataModal(false);
       setDeleteConfirmText("");
-      alert("All trade data has been cleared successfully!");
+      alert("All trades have been moved to the trash.");
       window.location.reload();
     } catch (error) {
       console.error("Error clearing data:", error);
-      alert("Failed to clear data: " + error);
+      alert("Failed to clear data: " + errorMessage(error));
     }
   };
 
@@ -3664,7 +3665,7 @@ export default function Settings() {
               lineHeight: "1.6",
             }}
           >
-            Permanently delete all trade data from your database. This action cannot be undone.
+            Move every trade to the trash. Trades stay recoverable until the trash is emptied.
           </p>
           <button
             onClick={handleClearAllData}
@@ -3754,7 +3755,7 @@ export default function Settings() {
                 lineHeight: "1.5",
               }}
             >
-              This cannot be undone. Type <strong style={{ color: "var(--danger)" }}>DELETE</strong> in the box below to confirm.
+              Trades can be restored from the trash until it is emptied. Type <strong style={{ color: "var(--danger)" }}>DELETE</strong> in the box below to confirm.
             </p>
             <input
               type="text"
@@ -8,6 +8,7 @@ import { format } from "date-fns";
 import { BarChart, Bar, Cell, XAxis, YAxis, CartesianGrid, Tooltip, ResponsiveContainer, Brush } from "recharts";
 import { LoadingSphere } from "../components/LoadingSphere";
 import { BRUSH_MIN_POINTS, CHART_BAR_FILL_OPACITY } from "../utils/chartDataSampling";
+import { errorMessage } from "../utils/commandError";
 /** Margin for overview bar charts; minimal bottom so chart area is maximized. */
 const OVERVIEW_CHART_MARGIN = { top: 5, right: 5, left: 5, bottom: 10 };
 /** Max characters per line for wrapped X-axis labels (word-aware). */
@@ -4059,7 +4060,7 @@ export default function Strategies() {
       });
     } catch (error) {
       console.error("Error adding checklist item:", error);
-      alert("Failed to add checklist item: " + error);
+      alert("Failed to add checklist item: " + errorMessage(error));
     }
   };
 
@@ -4133,7 +4134,7 @@ export default function Strategies() {
       setChecklists(new Map(checklists.set(strategyId, updatedChecklist)));
     } catch (error) {
       console.error("Error deleting checklist item:", error);
-      alert("Failed to delete checklist item: " + error);
+      alert("Failed to delete checklist item: " + errorMessage(error));
     }
   };
 
@@ -4169,7 +4170,7 @@ export default function Strategies() {
           await invoke("delete_strategy_checklist_type", { strategyId, checklistType: type });
         } catch (e) {
           console.error("Error deleting checklist type:", e);
-          alert("Failed to delete checklist: " + e);
+          alert("Failed to delete checklist: " + errorMessage(e));
           return;
         }
       }
@@ -4276,7 +4277,7 @@ export default function Strategies() {
       });
     } catch (error) {
       console.error("Error deleting checklist type:", error);
-      alert("Failed to delete checklist: " + error);
+      alert("Failed to delete checklist: " + errorMessage(error));
     }
   };
 
@@ -4369,7 +4370,7 @@ export default function Strategies() {
       await loadChecklists(strategyId);
     } catch (error) {
       console.error("Error grouping checklist items:", error);
-      alert("Failed to group checklist items: " + error);
+      alert("Failed to group checklist items: " + errorMessage(error));
     }
   };
 
@@ -4490,7 +4491,7 @@ export default function Strategies() {
       }
     } catch (error) {
       console.error("Error moving items to group:", error);
-      alert("Failed to move items to group: " + error);
+      alert("Failed to move items to group: " + errorMessage(error));
     }
   };
 
@@ -4539,7 +4540,7 @@ export default function Strategies() {
       }
     } catch (error) {
       console.error("Error ungrouping checklist items:", error);
-      alert("Failed to ungroup checklist items: " + error);
+      alert("Failed to ungroup checklist items: " + errorMessage(error));
     }
   };
 
@@ -4626,7 +4627,7 @@ export default function Strategies() {
       }
     } catch (error) {
       console.error("Error saving checklist item:", error);
-      alert("Failed to save checklist item: " + error);
+      alert("Failed to save checklist item: " + errorMessage(error));
     } finally {
       setEditingItemId(null);
       setEditingItemText("");
@@ -4706,7 +4707,7 @@ export default function Strategies() {
       await loadChecklists(selectedStrategy);
     } catch (error) {
       console.error("Error saving survey item settings:", error);
-      alert("Failed to save survey settings: " + error);
+      alert("Failed to save survey settings: " + errorMessage(error));
     }
   };
 
@@ -4771,7 +4772,7 @@ export default function Strategies() {
       }
     } catch (error) {
       console.error("Error reordering checklist items:", error);
-      alert("Failed to reorder checklist items: " + error);
+      alert("Failed to reorder checklist items: " + errorMessage(error));
       return;
     }
 
@@ -4834,7 +4835,7 @@ export default function Strategies() {
       }
     } catch (error) {
       console.error("Error importing CSV:", error);
-      alert("Failed to import CSV: " + (error instanceof Error ? error.message : String(error)));
+      alert("Failed to import CSV: " + errorMessage(error));
       setIsImportingCSV(false);
     }
   };
@@ -4877,17 +4878,14 @@ export default function Strategies() {
           const tradesInFile = countTradesInCSV(contents);
           totalAttempted += tradesInFile;
           
-          const importedTradeIds = await invoke<number[]>("import_trades_csv", { csvData: contents, mark_as_paper: markImportedTradesAsPaper ? true : undefined });
+          const summary = await invoke<{ inserted_ids: number[]; skipped_duplicates: number; failed_rows: { line: number | null; message: string }[] }>("import_trades_csv", { csvData: contents, mark_as_paper: markImportedTradesAsPaper ? true : undefined });
           
-          if (importedTradeIds && importedTradeIds.length > 0) {
-            allImportedTradeIds.push(...importedTradeIds);
-            totalNewTrades += importedTradeIds.length;
-            // Calculate duplicates for this file
-            const duplicatesInFile = tradesInFile - importedTradeIds.length;
-            totalDuplicates += duplicatesInFile;
-          } else {
-            // All trades in this file were duplicates
-            totalDuplicates += tradesInFile;
+          allImportedTradeIds.push(...summary.inserted_ids);
+          totalNewTrades += summary.inserted_ids.length;
+          totalDuplicates += summary.skipped_duplicates;
+          totalErrors += summary.failed_rows.length;
+          if (summary.failed_rows.length > 0) {
+            console.warn(`Rows not imported from ${fileInfo.path}:`, summary.failed_rows);
           }
         } catch (error) {
           totalErrors++;
@@ -4998,7 +4996,7 @@ export default function Strategies() {
       setPendingCSVFile(null);
     } catch (error) {
       console.error("Error importing CSV:", error);
-      alert("Failed to import CSV: " + (error instanceof Error ? error.message : String(error)));
+      alert("Failed to import CSV: " + errorMessage(error));
       setImportResults(null);
     } finally {
       setIsImportingCSV(false);
@@ -5035,7 +5033,7 @@ export default function Strategies() {
       }
     } catch (error) {
       console.error("Error importing CSV:", error);
-      alert("Failed to import CSV: " + (error instanceof Error ? error.message : String(error)));
+      alert("Failed to import CSV: " + errorMessage(error));
       setIsImportingCSV(false);
     }
   };
@@ -5064,9 +5062,6 @@ export default function Strategies() {
     try {
       setIsAddingTrade(true);
       const baseNotes = addTradeForm.notes.trim();
-      const notesWithPaper = addTradeForm.isPaperTrade
-        ? `${baseNotes ? baseNotes + " " : ""}[PAPER]`
-        : (baseNotes || null);
       const newId = await invoke<number>("add_trade_manual", {
         symbol: addTradeForm.symbol.trim(),
         side: addTradeForm.side,
@@ -5075,8 +5070,9 @@ export default function Strategies() {
         timestamp,
         order_type: addTradeForm.orderType || null,
         fees: feeVal,
-        notes: notesWithPaper,
+        notes: baseNotes || null,
         strategy_id: strategyId,
+        isPaper: addTradeForm.isPaperTrade,
       });
       setShowAddTradeModal(false);
       setAddTradeForm({
@@ -5138,7 +5134,7 @@ export default function Strategies() {
         }
       }
     } catch (err) {
-      setAddTradeError(err instanceof Error ? err.message : String(err));
+      setAddTradeError(errorMessage(err));
     } finally {
       setIsAddingTrade(false);
     }
@@ -5410,7 +5406,7 @@ export default function Strategies() {
       }, 300);
     } catch (error) {
       console.error("Error creating strategy:", error);
-      alert("Failed to create strategy: " + error);
+      alert("Failed to create strategy: " + errorMessage(error));
     }
   };
 
@@ -5821,7 +5817,7 @@ export default function Strategies() {
       }
     } catch (error) {
       console.error("Error saving strategy:", error);
-      alert("Failed to save strategy: " + error);
+      alert("Failed to save strategy: " + errorMessage(error));
     }
   };
 
@@ -5960,7 +5956,7 @@ export default function Strategies() {
       setAssociatedRecords(null);
     } catch (error) {
       console.error("Error deleting strategy:", error);
-      alert("Failed to delete strategy: " + error);
+      alert("Failed to delete strategy: " + errorMessage(error));
     }
   };
 
@@ -5997,7 +5993,7 @@ export default function Strategies() {
       await loadStrategies();
     } catch (error) {
       console.error("Error updating strategy order:", error);
-      alert("Failed to update strategy order: " + error);
+      alert("Failed to update strategy order: " + errorMessage(error));
       // Revert the order change on error
       const saved = localStorage.getItem(STRATEGY_ORDER_KEY);
       if (saved) {
@@ -6106,7 +6102,7 @@ export default function Strategies() {
       setIsCreating(false);
     } catch (error) {
       console.error("Error duplicating strategy:", error);
-      alert("Failed to duplicate strategy: " + error);
+      alert("Failed to duplicate strategy: " + errorMessage(error));
     }
   };
 
@@ -7942,7 +7938,7 @@ export default function Strategies() {
                               }
                             } catch (e) {
                               console.error(e);
-                              alert("Failed to remove duplicates: " + String(e));
+                              alert("Failed to remove duplicates: " + errorMessage(e));
                             }
                           }}
                           style={{
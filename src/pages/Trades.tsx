@@ -22,6 +22,7 @@ import {
 import { buildPositionGroupsAndPairs } from "../utils/sandboxPairing";
 import { LoadingSphere } from "../components/LoadingSphere";
 import ViewFinancialsButton from "../components/ViewFinancialsButton";
+import { errorMessage } from "../utils/commandError";
 
 interface JournalEntrySummary {
   id: number;
@@ -740,15 +741,8 @@ export default function Trades() {
         invoke<Strategy[]>("get_strategies"),
       ]);
 
-      // Backend returns only paper trades when paperOnly; for real mode filter out any [PAPER]-tagged
-      if (dataMode === "paper") {
-        setTradesWithPairing(tradesData);
-      } else {
-        const realOnly = tradesData.filter(
-          (item) => !(item.trade.notes || "").toUpperCase().includes("[PAPER]")
-        );
-        setTradesWithPairing(realOnly);
-      }
+      // Backend returns only paper trades when paperOnly and only live trades otherwise
+      setTradesWithPairing(tradesData);
 
       setPositionGroups(positionsData);
       setStrategies(strategiesData);
@@ -1337,14 +1331,15 @@ export default function Trades() {
       );
     } catch (error) {
       console.error("Error updating trade strategy:", error);
-      alert("Failed to update strategy: " + error);
+      alert("Failed to update strategy: " + errorMessage(error));
     }
   };
 
   const handleDeleteTrade = async (tradeId: number, e: React.MouseEvent) => {
     e.stopPropagation();
     if (deleteLocked) return;
-    if (!window.confirm("Delete this trade? This cannot be undone.")) return;
+    const prompt = dataMode === "sandbox" ? "Delete this trade? This cannot be undone." : "Move this trade to the trash?";
+    if (!window.confirm(prompt)) return;
     try {
       if (dataMode === "sandbox") {
         deleteSandboxTrade(tradeId);
@@ -1355,7 +1350,7 @@ export default function Trades() {
       await loadData();
     } catch (error) {
       console.error("Error deleting trade:", error);
-      alert("Failed to delete trade: " + (error instanceof Error ? error.message : String(error)));
+      alert("Failed to delete trade: " + errorMessage(error));
     }
   };
 
@@ -1372,52 +1367,45 @@ export default function Trades() {
   const markSelectedAsPaper = async () => {
     if (selectedTradeIdsForPaper.size === 0) return;
     try {
-      for (const tradeId of selectedTradeIdsForPaper) {
-        if (dataMode === "sandbox") {
+      if (dataMode === "sandbox") {
+        for (const tradeId of selectedTradeIdsForPaper) {
           const state = loadSandboxState();
           const trade = state.trades.find((t) => t.id === tradeId);
           if (!trade) continue;
           const notes = trade.notes || "";
           const newNotes = notes.trim() ? `${notes.trim()} [PAPER]` : "[PAPER]";
           updateSandboxTradeNotes(tradeId, newNotes);
-        } else {
-          const trade = await invoke<Trade | null>("get_trade_by_id", { id: tradeId });
-          if (!trade) continue;
-          const notes = trade.notes || "";
-          const newNotes = notes.trim() ? `${notes.trim()} [PAPER]` : "[PAPER]";
-          await invoke("update_trade", { id: tradeId, trade: { ...trade, notes: newNotes } });
         }
+      } else {
+        await invoke("set_trades_paper", { tradeIds: Array.from(selectedTradeIdsForPaper), isPaper: true });
       }
       setSelectedTradeIdsForPaper(new Set());
       await loadData();
     } catch (error) {
       console.error("Error marking as paper:", error);
-      alert("Failed to update trades: " + (error instanceof Error ? error.message : String(error)));
+      alert("Failed to update trades: " + errorMessage(error));
     }
   };
 
   const removePaperFromSelected = async () => {
     if (selectedTradeIdsForPaper.size === 0) return;
     try {
-      for (const tradeId of selectedTradeIdsForPaper) {
-        if (dataMode === "sandbox") {
+      if (dataMode === "sandbox") {
+        for (const tradeId of selectedTradeIdsForPaper) {
           const state = loadSandboxState();
           const trade = state.trades.find((t) => t.id === tradeId);
           if (!trade) continue;
           const notes = (trade.notes || "").replace(/\s*\[PAPER\]\s*/gi, "").trim() || null;
           updateSandboxTradeNotes(tradeId, notes);
-        } else {
-          const trade = await invoke<Trade | null>("get_trade_by_id", { id: tradeId });
-          if (!trade) continue;
-          const notes = (trade.notes || "").replace(/\s*\[PAPER\]\s*/gi, "").trim() || null;
-          await invoke("update_trade", { id: tradeId, trade: { ...trade, notes } });
         }
+      } else {
+        await invoke("set_trades_paper", { tradeIds: Array.from(selectedTradeIdsForPaper), isPaper: false });
       }
       setSelectedTradeIdsForPaper(new Set());
       await loadData();
     } catch (error) {
       console.error("Error removing paper flag:", error);
-      alert("Failed to update trades: " + (error instanceof Error ? error.message : String(error)));
+      alert("Failed to update trades: " + errorMessage(error));
     }
   };
 
@@ -0,0 +1,16 @@
+/** Error returned by a Tauri command (TradeButlerError on the Rust side). */
+export interface CommandError {
+  code: "database" | "io" | "network" | "parse" | "validation" | "not_found" | "busy" | "other";
+  message: string;
+}
+
+export function isCommandError(error: unknown): error is CommandError {
+  return typeof error === "object" && error !== null && "code" in error && "message" in error;
+}
+
+/** Human-readable message for anything caught from invoke() or a JS exception. */
+export function errorMessage(error: unknown): string {
+  if (error instanceof Error) return error.message;
+  if (isCommandError(error)) return error.message;
+  return String(error);
+}